@@ -0,0 +1,138 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use radmin_router::{Path, Route, Router, Segment, full};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const ROUTES: usize = 2000;
+
+/// Counts allocations, to report how many matching a request makes.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Deep static paths, e.g. `/api/v1/group3/resource42/items/list`, sharing
+/// chains of single-child literal segments.
+fn router() -> Router<()> {
+    (0..ROUTES).fold(Router::new(Arc::new(())), |router, idx| {
+        router.register(Route::new(Path(vec![
+            Segment::literal("api"),
+            Segment::literal("v1"),
+            Segment::literal(format!("group{}", idx % 10)),
+            Segment::literal(format!("resource{}", idx)),
+            Segment::literal("items"),
+            Segment::literal("list"),
+        ])))
+    })
+}
+
+fn deep_static_match(c: &mut Criterion) {
+    let mut group = c.benchmark_group("deep_static_match");
+    let path = "/api/v1/group7/resource1237/items/list";
+
+    let router = router();
+    group.bench_function("uncompacted", |b| {
+        b.iter(|| black_box(router.recognize(black_box(path))))
+    });
+
+    let router = router.finalize();
+    group.bench_function("compacted", |b| {
+        b.iter(|| black_box(router.recognize(black_box(path))))
+    });
+
+    group.finish();
+}
+
+/// Matches a route with a handler for each common method, reporting the
+/// allocations made per match.
+fn match_with_handlers(c: &mut Criterion) {
+    let route = ["GET", "POST", "PUT", "PATCH", "DELETE"].into_iter().fold(
+        Route::new(Path(vec![
+            Segment::literal("users"),
+            Segment::dynamic("id"),
+        ])),
+        |route, method| {
+            route.method(method.parse().unwrap(), |_, _| {
+                Box::pin(async { Ok(hyper::Response::new(full("OK"))) })
+            })
+        },
+    );
+    let router = Router::<()>::default().register(route).finalize();
+    let path = "/users/42";
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    black_box(router.recognize(path));
+    println!(
+        "match_with_handlers: {} allocations per match",
+        ALLOCATIONS.load(Ordering::Relaxed) - before
+    );
+
+    c.bench_function("match_with_handlers", |b| {
+        b.iter(|| black_box(router.recognize(black_box(path))))
+    });
+}
+
+/// Matches paths through a tree mixing literal, pattern, dynamic and
+/// catch-all segments, where literal branches that lead nowhere fall back to
+/// their dynamic siblings, reporting the allocations made per match.
+fn mixed_match(c: &mut Criterion) {
+    let router = (0..50).fold(Router::<()>::default(), |router, idx| {
+        let resource = format!("resource{}", idx);
+        [
+            format!("/api/v1/{}", resource),
+            format!("/api/v1/{}/[id]", resource),
+            format!("/api/v1/{}/[id]/posts/[post]", resource),
+            format!("/api/v1/{}/[id:int]/comments", resource),
+            format!("/api/v1/{}/export-[date].csv", resource),
+            format!("/api/v1/{}/[id]/posts/[post]/latest/edit", resource),
+            format!("/api/v1/{}/[...rest]", resource),
+        ]
+        .into_iter()
+        .fold(router, |router, path| {
+            router.register(Route::new(path.parse::<Path>().unwrap()))
+        })
+    });
+    let router = router.finalize();
+    let paths = [
+        "/api/v1/resource7/42/posts/99",
+        "/api/v1/resource7/42/comments",
+        "/api/v1/resource7/export-2024.csv",
+        "/api/v1/resource7/42/posts/latest/latest/other",
+    ];
+
+    for path in paths {
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        black_box(router.recognize(path));
+        println!(
+            "mixed_match {}: {} allocations per match",
+            path,
+            ALLOCATIONS.load(Ordering::Relaxed) - before
+        );
+    }
+
+    c.bench_function("mixed_match", |b| {
+        b.iter(|| {
+            for path in paths {
+                black_box(router.recognize(black_box(path)));
+            }
+        })
+    });
+}
+
+criterion_group!(benches, deep_static_match, match_with_handlers, mixed_match);
+criterion_main!(benches);
@@ -0,0 +1,48 @@
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use radmin_router::{Path, Route, Router, Segment};
+use std::hint::black_box;
+use std::sync::Arc;
+
+const ROUTES: usize = 2000;
+
+fn routes() -> Vec<Route<()>> {
+    (0..ROUTES)
+        .map(|idx| {
+            Route::new(Path(vec![
+                Segment::literal(format!("resource{}", idx)),
+                Segment::dynamic("id"),
+            ]))
+        })
+        .collect()
+}
+
+fn bulk_registration(c: &mut Criterion) {
+    let mut group = c.benchmark_group("bulk_registration");
+
+    group.bench_function("without_capacity", |b| {
+        b.iter_batched(
+            routes,
+            |routes| {
+                let router = Router::new(Arc::new(()));
+                black_box(routes.into_iter().fold(router, Router::register))
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    group.bench_function("with_capacity", |b| {
+        b.iter_batched(
+            routes,
+            |routes| {
+                let router = Router::with_capacity(Arc::new(()), ROUTES);
+                black_box(routes.into_iter().fold(router, Router::register))
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bulk_registration);
+criterion_main!(benches);
@@ -1,93 +1,96 @@
 use bytes::Bytes;
 use http_body_util::combinators::BoxBody;
-use http_body_util::{BodyExt, Empty, Full};
-use hyper::server::conn::http1;
-use hyper::service::service_fn;
+use http_body_util::{BodyExt, Full};
 use hyper::{Response, StatusCode, header};
-use hyper_util::rt::TokioIo;
-use radmin_router::{Route, Router, path};
+use radmin_router::{Cors, Route, Router, async_handler, path, stream};
 use std::error::Error;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use tokio::net::TcpListener;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
+    tracing_subscriber::fmt::init();
+
     let bind_addr = SocketAddr::from(([0, 0, 0, 0], 3030));
-    let listener = TcpListener::bind(bind_addr).await?;
 
     println!("Listening on {:?}", bind_addr);
 
-    let router = Router::new(())
-        .register(
-            Route::new(path!("/"))
-                .get(|_, _| {
-                    Box::pin(async {
-                        Ok(Response::builder()
-                            .status(StatusCode::OK)
-                            .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
-                            .header(header::CONTENT_TYPE, "text/plain; charset=UTF-8")
-                            .body(full("OK"))
-                            .unwrap())
-                    })
-                })
-                .options(|_, _| {
-                    Box::pin(async {
-                        Ok(Response::builder()
-                            .status(StatusCode::NO_CONTENT)
-                            .header("Access-Control-Allow-Private-Network", "true")
-                            .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
-                            .body(full(""))
-                            .unwrap())
-                    })
-                }),
-        )
-        .register(Route::new(path!("/[slug]")).get(|_, ctx| {
-            Box::pin(async move {
-                Ok(Response::builder()
-                    .status(StatusCode::OK)
-                    .body(full(ctx.params[0].clone()))
-                    .unwrap())
-            })
-        }))
-        .register(Route::new(path!("/[slug]/literal")).get(|_, ctx| {
+    Arc::new(router()).serve(bind_addr).await?;
+
+    Ok(())
+}
+
+fn router() -> Router<()> {
+    let mut router = Router::new(Arc::new(()))
+        .register(Route::new(path!("/")).get(async_handler(|_, _| async {
+            Ok(Response::builder()
+                .status(StatusCode::OK)
+                .header(header::CONTENT_TYPE, "text/plain; charset=UTF-8")
+                .body(full("OK"))
+                .unwrap())
+        })))
+        .register(Route::new(path!("/stream")).get(|_, _| {
+            let chunks = ["streamed ", "in ", "chunks\n"].map(|chunk| Ok(Bytes::from(chunk)));
+
             Box::pin(async move {
                 Ok(Response::builder()
-                    .status(StatusCode::OK)
-                    .body(full(format!("{} + literal", ctx.params[0])))
+                    .header(header::CONTENT_TYPE, "text/plain; charset=UTF-8")
+                    .body(stream(futures::stream::iter(chunks)))
                     .unwrap())
             })
         }))
-        .register(Route::new(path!("/[slug]/literal/[slug2]")).get(|_, ctx| {
-            Box::pin(async move {
-                Ok(Response::builder()
-                    .status(StatusCode::OK)
-                    .body(full(format!(
-                        "slugs: {} and {}",
-                        ctx.params[0], ctx.params[1]
-                    )))
-                    .unwrap())
-            })
-        }));
-    let router = Arc::new(router);
+        .register(
+            Route::new(path!("/[slug]")).get_into(|_, ctx| async move { ctx.params[0].clone() }),
+        )
+        .register(
+            Route::new(path!("/[slug]/literal"))
+                .get_into(|_, ctx| async move { format!("{} + literal", ctx.params[0]) }),
+        )
+        .register(
+            Route::new(path!("/[slug]/literal/[slug2]")).get_into(|_, ctx| async move {
+                format!("slugs: {} and {}", ctx.params[0], ctx.params[1])
+            }),
+        );
+
+    router.layer(Cors::default().allow_private_network(true).build());
+    router
+}
 
-    loop {
-        let (stream, _) = listener.accept().await?;
-        let io = TokioIo::new(stream);
+fn full<I: Into<Bytes>>(body: I) -> BoxBody<Bytes, radmin_router::BodyError> {
+    Full::new(body.into()).map_err(|e| match e {}).boxed()
+}
 
-        let router = Arc::clone(&router);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_body_util::Empty;
+    use hyper::Request;
 
-        tokio::task::spawn(async move {
-            let router = Arc::clone(&router);
-            let svc = service_fn(move |req| Router::route(Arc::clone(&router), req));
+    async fn get(path: &str) -> (StatusCode, String) {
+        let router = Arc::new(router());
+        let req = Request::get(path).body(Empty::<Bytes>::new()).unwrap();
+        let resp = router.oneshot(req).await.unwrap();
+        let status = resp.status();
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
 
-            if let Err(err) = http1::Builder::new().serve_connection(io, svc).await {
-                eprintln!("Error: {:?}", err);
-            }
-        });
+    #[tokio::test]
+    async fn serves_routes() {
+        assert_eq!(get("/").await, (StatusCode::OK, "OK".to_string()));
+        assert_eq!(
+            get("/stream").await,
+            (StatusCode::OK, "streamed in chunks\n".to_string())
+        );
+        assert_eq!(get("/hello").await, (StatusCode::OK, "hello".to_string()));
+        assert_eq!(
+            get("/a/literal/b").await,
+            (StatusCode::OK, "slugs: a and b".to_string())
+        );
     }
-}
 
-fn full<I: Into<Bytes>>(body: I) -> BoxBody<Bytes, hyper::Error> {
-    Full::new(body.into()).map_err(|e| match e {}).boxed()
+    #[tokio::test]
+    async fn rejects_unknown_paths() {
+        assert_eq!(get("/a/b").await.0, StatusCode::NOT_FOUND);
+    }
 }
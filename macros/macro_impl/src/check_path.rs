@@ -0,0 +1,138 @@
+//! The compile-time check of route paths. It only uses `std`, so that the
+//! tests of `radmin-router` can include it to compare it with `Path::from_str`.
+
+/// Checks `path` with the rules `Path::from_str` applies, returning the
+/// message of the error it would fail with.
+pub(crate) fn check_path(path: &str) -> Result<(), String> {
+    const CONSTRAINTS: &[&str] = &["int", "uuid", "alpha"];
+
+    let mut catch_all = None;
+    let mut optional = None;
+    for segment in path.split('/').filter(|segment| !segment.is_empty()) {
+        if let Some(catch_all) = catch_all {
+            return Err(format!(
+                "catch-all segment `{}` must be the last segment",
+                catch_all
+            ));
+        }
+
+        check_brackets(segment)?;
+        let name = segment
+            .strip_prefix('[')
+            .and_then(|name| name.strip_suffix(']'))
+            .filter(|name| !name.contains(['[', ']']));
+
+        if let Some(name) = name {
+            let bare = name
+                .strip_prefix("...")
+                .or_else(|| name.strip_suffix('?'))
+                .unwrap_or_else(|| name.split_once(':').map_or(name, |(name, _)| name));
+            if bare.is_empty() {
+                return Err(format!(
+                    "placeholder without a name in segment `{}`",
+                    segment
+                ));
+            }
+        }
+
+        let is_optional = name.is_some_and(|name| !name.starts_with("...") && name.ends_with('?'));
+        if let Some(optional) = optional
+            && !is_optional
+        {
+            return Err(format!(
+                "optional segment `{}` must only be followed by optional segments",
+                optional
+            ));
+        }
+
+        match name {
+            Some(name) if name.starts_with("...") => catch_all = Some(segment),
+
+            Some(_) if is_optional => optional = Some(segment),
+
+            Some(name) => {
+                if let Some((_, constraint)) = name.split_once(':')
+                    && !CONSTRAINTS.contains(&constraint)
+                {
+                    return Err(format!("unknown constraint in segment `{}`", segment));
+                }
+            }
+
+            None => check_pattern(segment)?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that the unescaped brackets of `segment` enclose non-empty
+/// placeholder names, one level deep.
+fn check_brackets(segment: &str) -> Result<(), String> {
+    let mut open = None;
+    let mut chars = segment.char_indices();
+
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+
+            '[' if open.is_some() => {
+                return Err(format!("nested brackets in segment `{}`", segment));
+            }
+
+            '[' => open = Some(idx),
+
+            ']' => match open.take() {
+                Some(start) if start + 1 == idx => {
+                    return Err(format!(
+                        "placeholder without a name in segment `{}`",
+                        segment
+                    ));
+                }
+                Some(_) => {}
+                None => return Err(format!("unexpected `]` in segment `{}`", segment)),
+            },
+
+            _ => {}
+        }
+    }
+
+    match open {
+        Some(_) => Err(format!("unterminated `[` in segment `{}`", segment)),
+        None => Ok(()),
+    }
+}
+
+/// Checks that a segment that isn't wholly dynamic has no adjacent
+/// placeholders.
+fn check_pattern(segment: &str) -> Result<(), String> {
+    let bytes = segment.as_bytes();
+    let mut after_placeholder = false;
+    let mut idx = 0;
+
+    while idx < bytes.len() {
+        match bytes[idx] {
+            b'\\' => {
+                idx += 2;
+                after_placeholder = false;
+            }
+
+            b'[' if let Some(len) = segment[idx + 1..].find(']') => {
+                if after_placeholder {
+                    return Err(format!("adjacent placeholders in segment `{}`", segment));
+                }
+
+                idx += len + 2;
+                after_placeholder = true;
+            }
+
+            _ => {
+                idx += 1;
+                after_placeholder = false;
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -1,10 +1,29 @@
+mod check_path;
+
+use check_path::check_path;
+use syn::ext::IdentExt;
 use syn::punctuated::Punctuated;
 use quote::{quote, quote_spanned};
 use syn::spanned::Spanned;
-use syn::{parse_macro_input, parse_quote, parse_quote_spanned, Data, DeriveInput, Expr, Fields, ItemFn, ReturnType, Token};
+use syn::visit_mut::{self, VisitMut};
+use syn::{parse_macro_input, parse_quote, parse_quote_spanned, Data, DeriveInput, Expr, Fields, FnArg, GenericParam, ImplItem, ItemFn, ItemImpl, Lifetime, LitStr, ReturnType, Token, Type, WherePredicate};
 
+/// Rewrites an async function into one returning a boxed future, e.g. for use
+/// as a handler body.
+///
+/// The future is `Send`, so it can run on a multithreaded runtime. Use
+/// `#[box_future(?Send)]` to drop the `Send` bound.
+///
+/// The future is `'static` unless the function has generic parameters or
+/// borrows from its arguments, in which case it's bounded by all of their
+/// lifetimes. Elided lifetimes, including `&self`, are given names.
 #[proc_macro_attribute]
-pub fn box_future(_args: proc_macro::TokenStream, input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+pub fn box_future(args: proc_macro::TokenStream, input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let send = match parse_send_arg(args) {
+        Ok(send) => send,
+        Err(err) => return err.into_compile_error().into()
+    };
+
     let input = parse_macro_input!(input as ItemFn);
     let ItemFn { attrs, vis, mut sig, block } = input;
 
@@ -26,19 +45,135 @@ pub fn box_future(_args: proc_macro::TokenStream, input: proc_macro::TokenStream
         ReturnType::Default => quote_spanned!(sig.paren_token.span=> ()),
         ReturnType::Type(_, ret) => quote!(#ret)
     };
+    let send = send.then(|| quote!(+ ::std::marker::Send));
+    let bound = capture_lifetimes(&mut sig);
     sig.output = parse_quote_spanned!(ret.span()=>
-        -> ::std::pin::Pin<::std::boxed::Box<dyn ::std::future::Future<Output = #ret>>>
+        -> ::std::pin::Pin<::std::boxed::Box<dyn ::std::future::Future<Output = #ret> #send + #bound>>
     );
 
     let expanded = quote! {
         #(#attrs )* #vis #sig {
-            ::std::boxed::Box::pin(async #block)
+            ::std::boxed::Box::pin(async move #block)
         }
     };
 
     proc_macro::TokenStream::from(expanded)
 }
 
+/// Names the elided lifetimes in `sig`'s inputs and, if the future captures
+/// any lifetimes or type parameters, adds a `'box_future` lifetime they all
+/// outlive. Returns the lifetime bounding the future.
+fn capture_lifetimes(sig: &mut syn::Signature) -> Lifetime {
+    let mut elided = ElidedLifetimes::default();
+    for input in &mut sig.inputs {
+        elided.visit_fn_arg_mut(input);
+    }
+
+    let generics = &mut sig.generics;
+    let captures_generics = generics.params.iter().any(|param| !matches!(param, GenericParam::Const(_)));
+    if elided.named.is_empty() && !elided.impl_trait && !captures_generics {
+        return parse_quote!('static);
+    }
+
+    let bound: Lifetime = parse_quote!('box_future);
+    for lifetime in &elided.named {
+        generics.params.push(parse_quote!(#lifetime));
+    }
+
+    let predicates: Vec<WherePredicate> = generics.params.iter()
+        .filter_map(|param| match param {
+            GenericParam::Lifetime(param) => {
+                let lifetime = &param.lifetime;
+                Some(parse_quote!(#lifetime: #bound))
+            }
+
+            GenericParam::Type(param) => {
+                let ident = &param.ident;
+                Some(parse_quote!(#ident: #bound))
+            }
+
+            GenericParam::Const(_) => None
+        })
+        .collect();
+
+    generics.make_where_clause().predicates.extend(predicates);
+    generics.params.push(parse_quote!(#bound));
+    bound
+}
+
+/// Replaces elided lifetimes with named ones (`'life0`, `'life1`, ...), and
+/// bounds `impl Trait` arguments by `'box_future`.
+#[derive(Default)]
+struct ElidedLifetimes {
+    named: Vec<Lifetime>,
+    impl_trait: bool
+}
+
+impl ElidedLifetimes {
+    fn next(&mut self, span: proc_macro2::Span) -> Lifetime {
+        let lifetime = Lifetime::new(&format!("'life{}", self.named.len()), span);
+        self.named.push(lifetime.clone());
+        lifetime
+    }
+}
+
+impl VisitMut for ElidedLifetimes {
+    fn visit_receiver_mut(&mut self, receiver: &mut syn::Receiver) {
+        if let Some((and, lifetime @ None)) = &mut receiver.reference {
+            let named = self.next(and.span());
+            if let syn::Type::Reference(ty) = &mut *receiver.ty {
+                ty.lifetime = Some(named.clone());
+            }
+
+            *lifetime = Some(named);
+        }
+
+        visit_mut::visit_receiver_mut(self, receiver);
+    }
+
+    fn visit_type_reference_mut(&mut self, ty: &mut syn::TypeReference) {
+        if ty.lifetime.is_none() {
+            ty.lifetime = Some(self.next(ty.and_token.span()));
+        }
+
+        visit_mut::visit_type_reference_mut(self, ty);
+    }
+
+    fn visit_lifetime_mut(&mut self, lifetime: &mut Lifetime) {
+        if lifetime.ident == "_" {
+            *lifetime = self.next(lifetime.span());
+        }
+    }
+
+    fn visit_type_impl_trait_mut(&mut self, ty: &mut syn::TypeImplTrait) {
+        self.impl_trait = true;
+        ty.bounds.push(parse_quote!('box_future));
+        visit_mut::visit_type_impl_trait_mut(self, ty);
+    }
+
+    // Elided lifetimes in `fn(&T)` and `Fn(&T)` are higher-ranked.
+    fn visit_type_bare_fn_mut(&mut self, _: &mut syn::TypeBareFn) {}
+
+    fn visit_parenthesized_generic_arguments_mut(&mut self, _: &mut syn::ParenthesizedGenericArguments) {}
+}
+
+/// Parses the arguments of `box_future`, returning whether the future is `Send`.
+fn parse_send_arg(args: proc_macro::TokenStream) -> syn::Result<bool> {
+    if args.is_empty() {
+        return Ok(true);
+    }
+
+    syn::parse::Parser::parse(|input: syn::parse::ParseStream| {
+        input.parse::<Token![?]>()?;
+        let ident = input.parse::<syn::Ident>()?;
+        if ident != "Send" {
+            return Err(syn::Error::new(ident.span(), "expected `?Send`"));
+        }
+
+        Ok(false)
+    }, args)
+}
+
 #[proc_macro_derive(CaseIterable)]
 pub fn derive_case_iterable(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
@@ -79,4 +214,255 @@ pub fn derive_case_iterable(input: proc_macro::TokenStream) -> proc_macro::Token
     };
 
     expanded.into()
-}
\ No newline at end of file
+}
+
+/// Implements `FromParams` for a struct with named fields, parsing each
+/// field with `FromStr` from the route parameter of the same name. `Option`
+/// fields are `None` when their parameter is absent, as for an optional
+/// segment.
+///
+/// With `#[params("/users/[id]")]`, checks at compile time that every field
+/// names a placeholder of that path.
+#[proc_macro_derive(FromParams, attributes(params))]
+pub fn derive_from_params(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand_from_params(&input) {
+        Ok(expanded) => expanded.into(),
+        Err(err) => err.into_compile_error().into()
+    }
+}
+
+fn expand_from_params(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let Data::Struct(ref data) = input.data else {
+        return Err(syn::Error::new(input.span(), "only structs can derive `FromParams`"))
+    };
+
+    let Fields::Named(ref fields) = data.fields else {
+        return Err(syn::Error::new(data.fields.span(), "`FromParams` fields must be named after route parameters"))
+    };
+
+    let mut path = None;
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("params")) {
+        let lit = attr.parse_args::<LitStr>()?;
+        let value = lit.value();
+        check_path(&value).map_err(|message| syn::Error::new(lit.span(), message))?;
+        path = Some((param_names(&value), value));
+    }
+
+    let fields = fields.named.iter().map(|field| {
+        let ident = field.ident.as_ref().expect("named fields have identifiers");
+        let name = ident.unraw().to_string();
+        if let Some((names, path)) = &path && !names.contains(&name) {
+            return Err(syn::Error::new(ident.span(), format!("no parameter named `{}` in `{}`", name, path)));
+        }
+
+        Ok(if is_option(&field.ty) {
+            quote! {
+                #ident: match ctx.param_parsed(#name) {
+                    ::std::result::Result::Ok(value) => ::std::option::Option::Some(value),
+                    ::std::result::Result::Err(::radmin_router::ParamError::Missing(_)) => ::std::option::Option::None,
+                    ::std::result::Result::Err(err) => return ::std::result::Result::Err(err)
+                }
+            }
+        } else {
+            quote!(#ident: ctx.param_parsed(#name)?)
+        })
+    }).collect::<syn::Result<Vec<_>>>()?;
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::radmin_router::FromParams for #ident #ty_generics #where_clause {
+            fn from_context<Extra>(
+                ctx: &::radmin_router::Context<Extra>
+            ) -> ::std::result::Result<Self, ::radmin_router::ParamError> {
+                ::std::result::Result::Ok(Self { #(#fields),* })
+            }
+        }
+    })
+}
+
+/// Returns whether `ty` is spelled as an `Option`.
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(ty) => ty.qself.is_none() && ty.path.segments.last().is_some_and(|segment| segment.ident == "Option"),
+        _ => false
+    }
+}
+
+/// Returns the names of the placeholders in `path`, which must pass
+/// `check_path`.
+fn param_names(path: &str) -> Vec<String> {
+    let mut names = vec![];
+    let mut chars = path.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+
+            '[' => {
+                let name: String = chars.by_ref().take_while(|c| *c != ']').collect();
+                let name = name.strip_prefix("...")
+                    .or_else(|| name.strip_suffix('?'))
+                    .unwrap_or_else(|| name.split_once(':').map_or(&name, |(name, _)| name));
+                names.push(name.to_string());
+            }
+
+            _ => {}
+        }
+    }
+
+    names
+}
+
+/// The HTTP verb attributes accepted by `routes`, named after the `Route`
+/// builder methods they map to.
+const VERBS: &[&str] = &["get", "head", "post", "put", "patch", "delete", "options", "connect", "trace"];
+
+/// Turns the methods of an impl block annotated with a verb and a path, e.g.
+/// `#[get("/users/[id]")]`, into routes.
+///
+/// Handler methods take `&self` (or no receiver), a `Request<Incoming>` and a
+/// `Context<Extra>`, and return a future of `radmin_router::Result`, e.g. as
+/// async methods. `Extra` defaults to `()`; pass another type as
+/// `#[routes(AppState)]`. Methods without a verb attribute are left alone.
+///
+/// Adds `routes(self) -> Vec<Route<Extra>>`, with one route per path, and
+/// `into_router(self, ex: Arc<Extra>) -> Router<Extra>`, which serves them with
+/// `ex` as `Context::ex`. The handlers share `self` through an `Arc`.
+///
+/// Paths are checked at compile time, with the same rules as parsing a
+/// `Path`.
+#[proc_macro_attribute]
+pub fn routes(args: proc_macro::TokenStream, input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let extra = if args.is_empty() {
+        parse_quote!(())
+    } else {
+        parse_macro_input!(args as Type)
+    };
+
+    let mut input = parse_macro_input!(input as ItemImpl);
+    match expand_routes(&mut input, &extra) {
+        Ok(expanded) => expanded.into(),
+        // Keep the impl so that its uses don't report errors too.
+        Err(err) => {
+            let err = err.into_compile_error();
+            quote!(#input #err).into()
+        }
+    }
+}
+
+fn expand_routes(input: &mut ItemImpl, extra: &Type) -> syn::Result<proc_macro2::TokenStream> {
+    // Routes by path, in the order their first handler appears.
+    let mut routes: Vec<(String, Vec<proc_macro2::TokenStream>)> = vec![];
+    let mut seen: Vec<(String, String)> = vec![];
+
+    for item in &mut input.items {
+        let ImplItem::Fn(method) = item else {
+            continue
+        };
+
+        let mut verbs = vec![];
+        let mut error = None;
+        method.attrs.retain(|attr| {
+            let Some(verb) = attr.path().get_ident() else {
+                return true
+            };
+
+            // Other attributes, such as `#[doc]` or `#[inline]`, don't take a
+            // lone string literal.
+            let Ok(path) = attr.parse_args::<LitStr>() else {
+                return true
+            };
+
+            if !VERBS.contains(&verb.to_string().as_str()) {
+                error.get_or_insert(syn::Error::new(
+                    verb.span(),
+                    format!("unknown HTTP verb `{}`, expected one of {}", verb, VERBS.join(", "))));
+                return false
+            }
+
+            verbs.push((verb.clone(), path));
+            false
+        });
+
+        if let Some(error) = error {
+            return Err(error);
+        }
+
+        if verbs.is_empty() {
+            continue
+        }
+
+        let ident = &method.sig.ident;
+        let call = match method.sig.inputs.first() {
+            Some(FnArg::Receiver(receiver)) if receiver.reference.is_some() && receiver.mutability.is_none() => {
+                quote! {
+                    let this = ::std::sync::Arc::clone(&this);
+                    ::std::boxed::Box::pin(async move { this.#ident(req, ctx).await })
+                }
+            }
+
+            Some(FnArg::Receiver(receiver)) => {
+                return Err(syn::Error::new(receiver.span(), "route handlers must take `&self`"))
+            }
+
+            _ => quote! {
+                ::std::boxed::Box::pin(async move { Self::#ident(req, ctx).await })
+            }
+        };
+
+        for (verb, path) in verbs {
+            let value = path.value();
+            check_path(&value).map_err(|message| syn::Error::new(path.span(), message))?;
+
+            let key = (verb.to_string(), value.clone());
+            if seen.contains(&key) {
+                return Err(syn::Error::new(
+                    path.span(),
+                    format!("`{} {}` is routed more than once", key.0.to_uppercase(), value)));
+            }
+            seen.push(key);
+
+            let handler = quote! {
+                .#verb({
+                    let this = ::std::sync::Arc::clone(&this);
+                    move |req, ctx| -> ::std::pin::Pin<::std::boxed::Box<::radmin_router::ResponseFut>> {
+                        #call
+                    }
+                })
+            };
+
+            match routes.iter_mut().find(|(existing, _)| *existing == value) {
+                Some((_, handlers)) => handlers.push(handler),
+                None => routes.push((value, vec![handler]))
+            }
+        }
+    }
+
+    let routes = routes.iter().map(|(path, handlers)| quote! {
+        ::radmin_router::Route::<#extra>::new(::radmin_router::path!(#path)) #(#handlers)*
+    });
+
+    input.items.push(parse_quote! {
+        /// Returns a route for each path handled by this impl's methods.
+        pub fn routes(self) -> ::std::vec::Vec<::radmin_router::Route<#extra>> {
+            let this = ::std::sync::Arc::new(self);
+            ::std::vec![#(#routes),*]
+        }
+    });
+
+    input.items.push(parse_quote! {
+        /// Returns a router serving this impl's routes, sharing `ex` with
+        /// them.
+        pub fn into_router(self, ex: ::std::sync::Arc<#extra>) -> ::radmin_router::Router<#extra> {
+            self.routes()
+                .into_iter()
+                .fold(::radmin_router::Router::new(ex), ::radmin_router::Router::register)
+        }
+    });
+
+    Ok(quote!(#input))
+}
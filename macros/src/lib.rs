@@ -1,10 +1,5 @@
-pub use macro_impl::{box_future, CaseIterable};
+pub use macro_impl::{box_future, routes, CaseIterable, FromParams};
 
 pub trait CaseIterable: 'static + Sized {
     const ALL_CASES: &'static [Self];
 }
-
-#[derive(CaseIterable)]
-enum A {
-    B
-}
\ No newline at end of file
@@ -0,0 +1,133 @@
+use hyper::{Method, StatusCode};
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Formats the access log line of a request, as set with
+/// [`Router::access_log_formatter`](crate::Router::access_log_formatter).
+pub type AccessLogFormatter = Arc<dyn Fn(&LogRecord<'_>) -> String + Send + Sync>;
+
+/// A handled request, as logged by the router.
+#[derive(Clone, Debug)]
+pub struct LogRecord<'a> {
+    pub method: &'a Method,
+    /// The request path, as received.
+    pub path: &'a str,
+    /// The pattern of the matched route, or the request path if none matched.
+    pub route: &'a str,
+    /// The response status, or `None` if the handler failed.
+    pub status: Option<StatusCode>,
+    /// The time taken to produce the response.
+    pub elapsed: Duration,
+    pub remote_addr: Option<SocketAddr>,
+    /// The ID set by the [`request_id`](crate::request_id) middleware.
+    pub request_id: Option<&'a str>,
+}
+
+impl LogRecord<'_> {
+    /// Expands the placeholders of `template` with the fields of the record:
+    /// `{method}`, `{path}`, `{route}`, `{status}`, `{elapsed}`,
+    /// `{remote_addr}` and `{request_id}`. Missing values expand to `-`, and
+    /// unknown placeholders are kept as written.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hyper::{Method, StatusCode};
+    /// use radmin_router::LogRecord;
+    /// use std::time::Duration;
+    ///
+    /// let record = LogRecord {
+    ///     method: &Method::GET,
+    ///     path: "/users/42",
+    ///     route: "/users/[id]",
+    ///     status: Some(StatusCode::OK),
+    ///     elapsed: Duration::from_millis(3),
+    ///     remote_addr: None,
+    ///     request_id: None,
+    /// };
+    /// assert_eq!(
+    ///     record.format("{method} {path} {status} {remote_addr}"),
+    ///     "GET /users/42 200 -"
+    /// );
+    /// ```
+    pub fn format(&self, template: &str) -> String {
+        let mut line = String::with_capacity(template.len());
+        let mut rest = template;
+
+        while let Some(start) = rest.find('{') {
+            line.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            let Some(end) = rest.find('}') else {
+                break;
+            };
+            let placeholder = &rest[..=end];
+            match placeholder {
+                "{method}" => line.push_str(self.method.as_str()),
+                "{path}" => line.push_str(self.path),
+                "{route}" => line.push_str(self.route),
+                "{status}" => match self.status {
+                    Some(status) => write!(line, "{}", status.as_u16()).unwrap(),
+                    None => line.push('-'),
+                },
+                "{elapsed}" => write!(line, "{:?}", self.elapsed).unwrap(),
+                "{remote_addr}" => match self.remote_addr {
+                    Some(addr) => write!(line, "{}", addr).unwrap(),
+                    None => line.push('-'),
+                },
+                "{request_id}" => line.push_str(self.request_id.unwrap_or("-")),
+                _ => {
+                    // Keep the brace and look for a placeholder after it.
+                    line.push('{');
+                    rest = &rest[1..];
+                    continue;
+                }
+            }
+            rest = &rest[end + 1..];
+        }
+
+        line.push_str(rest);
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_templates() {
+        let record = LogRecord {
+            method: &Method::POST,
+            path: "/users/42/posts",
+            route: "/users/[id]/posts",
+            status: Some(StatusCode::CREATED),
+            elapsed: Duration::from_micros(1500),
+            remote_addr: Some("127.0.0.1:5000".parse().unwrap()),
+            request_id: Some("abc-123"),
+        };
+        assert_eq!(
+            record.format(
+                "{remote_addr} {request_id} \"{method} {path}\" {status} {elapsed} route={route}"
+            ),
+            "127.0.0.1:5000 abc-123 \"POST /users/42/posts\" 201 1.5ms route=/users/[id]/posts"
+        );
+        assert_eq!(
+            record.format("{unknown} {{method}} {status"),
+            "{unknown} {POST} {status"
+        );
+
+        let failed = LogRecord {
+            status: None,
+            remote_addr: None,
+            request_id: None,
+            ..record
+        };
+        assert_eq!(
+            failed.format("{status} {remote_addr} {request_id}"),
+            "- - -"
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use bytes::Bytes;
+use http_body_util::combinators::BoxBody;
+use http_body_util::{BodyExt, Empty, Full};
+
+/// Creates an empty response body, for modules that can't rely on the `util`
+/// feature's public `empty`.
+pub(crate) fn empty() -> BoxBody<Bytes, crate::BodyError> {
+    Empty::new().map_err(|never| match never {}).boxed()
+}
+
+/// Creates a response body holding `body`, like the `util` feature's public
+/// `full`.
+pub(crate) fn full(body: impl Into<Bytes>) -> BoxBody<Bytes, crate::BodyError> {
+    Full::new(body.into())
+        .map_err(|never| match never {})
+        .boxed()
+}
+
+/// Why [`collect_limited`] failed.
+#[cfg(any(feature = "form", feature = "json", feature = "multipart"))]
+pub(crate) enum LimitedBodyError {
+    /// The request body couldn't be read.
+    Body(hyper::Error),
+    /// The request body exceeds the router's `max_body_size`.
+    TooLarge,
+}
+
+/// Collects the body of `req`, stopping once it exceeds the router's
+/// [`max_body_size`](crate::Router::max_body_size), if set.
+#[cfg(any(feature = "form", feature = "json", feature = "multipart"))]
+pub(crate) async fn collect_limited(
+    req: hyper::Request<hyper::body::Incoming>,
+) -> Result<Bytes, LimitedBodyError> {
+    let limit = req
+        .extensions()
+        .get::<crate::context::MaxBodySize>()
+        .map_or(usize::MAX, |limit| limit.0);
+
+    let mut body = req.into_body();
+    let mut buf = bytes::BytesMut::new();
+    while let Some(frame) = body.frame().await {
+        let frame = frame.map_err(LimitedBodyError::Body)?;
+        if let Ok(data) = frame.into_data() {
+            if buf.len() + data.len() > limit {
+                return Err(LimitedBodyError::TooLarge);
+            }
+            buf.extend_from_slice(&data);
+        }
+    }
+
+    Ok(buf.freeze())
+}
+
+/// Responds to a body reader's error with its message, as `413 Payload Too
+/// Large` if the body was too large and `400 Bad Request` otherwise.
+#[cfg(any(feature = "form", feature = "json", feature = "multipart"))]
+pub(crate) fn body_error_response(
+    too_large: bool,
+    err: &impl std::fmt::Display,
+) -> crate::Response {
+    let status = if too_large {
+        hyper::StatusCode::PAYLOAD_TOO_LARGE
+    } else {
+        hyper::StatusCode::BAD_REQUEST
+    };
+
+    hyper::Response::builder()
+        .status(status)
+        .header(hyper::header::CONTENT_TYPE, "text/plain; charset=utf-8")
+        .body(full(err.to_string()))
+        .unwrap()
+}
@@ -0,0 +1,329 @@
+use crate::body::full;
+use crate::context::Context;
+use crate::middleware::Next;
+use bytes::Bytes;
+use encoding_rs::{Encoding, UTF_8};
+use futures::future::BoxFuture;
+use http_body_util::BodyExt;
+use hyper::body::{Body, Incoming};
+use hyper::header::{self, HeaderValue};
+use hyper::{Request, Response, StatusCode};
+
+/// A middleware transcoding text response bodies to the charset preferred by
+/// the request's `Accept-Charset` header, updating the `Content-Type` charset
+/// parameter accordingly.
+///
+/// Charset labels are resolved per the WHATWG Encoding Standard, so e.g.
+/// `iso-8859-1` is encoded as `windows-1252`, and charsets that are only
+/// decoded, such as `utf-16`, are answered in UTF-8. `Content-Type` names the
+/// encoding actually used. If the body has characters the preferred charset
+/// can't represent, the next accepted charset is tried instead.
+///
+/// Responses without a `text/*` content type, `text/event-stream` and other
+/// streamed bodies without an exact length, and requests without
+/// `Accept-Charset`, pass through unchanged. When none of the accepted
+/// charsets are supported or can represent the body, responds with
+/// `406 Not Acceptable`.
+///
+/// # Example
+///
+/// ```
+/// use radmin_router::{Router, charset};
+///
+/// Router::<()>::default()
+///     .layer(charset());
+/// ```
+pub fn charset<Extra>()
+-> impl Fn(Request<Incoming>, Context<Extra>, Next<Extra>) -> BoxFuture<'static, crate::Result>
++ Send
++ Sync
++ 'static
+where
+    Extra: Send + Sync + 'static,
+{
+    |req, ctx, next| {
+        let accept = req
+            .headers()
+            .get(header::ACCEPT_CHARSET)
+            .and_then(|value| value.to_str().ok())
+            .map(String::from);
+
+        Box::pin(async move {
+            let resp = next.run(req, ctx).await?;
+            let Some(accept) = accept else {
+                return Ok(resp);
+            };
+
+            let Some(source) = text_charset(&resp) else {
+                return Ok(resp);
+            };
+            // Transcoding needs the whole body, which a stream may never end.
+            if resp.body().size_hint().exact().is_none() {
+                return Ok(resp);
+            }
+
+            let encodings = negotiate(&accept);
+            if encodings.first() == Some(&source) {
+                return Ok(resp);
+            }
+
+            let (mut parts, body) = resp.into_parts();
+            let body = body.collect().await?.to_bytes();
+            let (text, _, _) = source.decode(&body);
+            let transcoded = encodings.into_iter().find_map(|encoding| {
+                if encoding == source {
+                    return Some((encoding, body.clone()));
+                }
+                // Unmappable characters are replaced with HTML character
+                // references, which only mean anything in HTML.
+                let (encoded, _, unmappable) = encoding.encode(&text);
+                (!unmappable).then(|| (encoding, Bytes::from(encoded.into_owned())))
+            });
+            let Some((target, encoded)) = transcoded else {
+                return Ok(Response::builder()
+                    .status(StatusCode::NOT_ACCEPTABLE)
+                    .body(full("Not Acceptable"))
+                    .unwrap());
+            };
+
+            let mime = parts.headers[header::CONTENT_TYPE]
+                .to_str()
+                .unwrap_or_default()
+                .split(';')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_string();
+            if let Ok(value) =
+                HeaderValue::from_str(&format!("{}; charset={}", mime, target.name()))
+            {
+                parts.headers.insert(header::CONTENT_TYPE, value);
+            }
+            parts
+                .headers
+                .insert(header::CONTENT_LENGTH, encoded.len().into());
+
+            Ok(Response::from_parts(parts, full(encoded)))
+        })
+    }
+}
+
+/// Returns the charset of a `text/*` response, defaulting to UTF-8, other
+/// than for `text/event-stream`.
+fn text_charset(resp: &crate::Response) -> Option<&'static Encoding> {
+    let content_type = resp.headers().get(header::CONTENT_TYPE)?.to_str().ok()?;
+    let mut params = content_type.split(';');
+
+    let mime = params.next()?.trim();
+    if !mime.starts_with("text/") || mime.eq_ignore_ascii_case("text/event-stream") {
+        return None;
+    }
+
+    let charset = params
+        .filter_map(|param| param.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("charset"))
+        .and_then(|(_, value)| Encoding::for_label(value.trim().trim_matches('"').as_bytes()));
+
+    Some(charset.unwrap_or(UTF_8))
+}
+
+/// Lists the supported charsets accepted by an `Accept-Charset` value, most
+/// preferred first.
+fn negotiate(accept: &str) -> Vec<&'static Encoding> {
+    let mut accepted: Vec<(f32, &'static Encoding)> = Vec::new();
+
+    for entry in accept.split(',') {
+        let mut params = entry.split(';');
+        let label = params.next().unwrap_or_default().trim();
+        let quality = params
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|q| q.parse::<f32>().ok())
+            .unwrap_or(1.0);
+
+        if quality <= 0.0 {
+            continue;
+        }
+
+        let named = match label {
+            "*" => Some(UTF_8),
+            label => Encoding::for_label(label.as_bytes()),
+        };
+        if let Some(named) = named {
+            accepted.push((quality, named.output_encoding()));
+        }
+    }
+
+    // Stable, so equally preferred charsets keep the client's order.
+    accepted.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+    let mut encodings: Vec<&'static Encoding> = Vec::new();
+    for (_, encoding) in accepted {
+        if !encodings.contains(&encoding) {
+            encodings.push(encoding);
+        }
+    }
+    encodings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::send;
+    use crate::{Route, Router, path};
+
+    fn router() -> Router<()> {
+        let text = |content_type: &'static str, body: &'static [u8]| {
+            move |_, _| -> std::pin::Pin<Box<crate::ResponseFut>> {
+                Box::pin(async move {
+                    Ok(Response::builder()
+                        .header(header::CONTENT_TYPE, content_type)
+                        .body(full(body))
+                        .unwrap())
+                })
+            }
+        };
+        let stream = |content_type: &'static str| {
+            move |_, _| -> std::pin::Pin<Box<crate::ResponseFut>> {
+                let chunks = futures::stream::iter(["data: café\n\n"; 2].map(|chunk| {
+                    Ok::<_, crate::BodyError>(hyper::body::Frame::data(Bytes::from(chunk)))
+                }));
+                Box::pin(async move {
+                    Ok(Response::builder()
+                        .header(header::CONTENT_TYPE, content_type)
+                        .body(http_body_util::StreamBody::new(chunks).boxed())
+                        .unwrap())
+                })
+            }
+        };
+
+        let mut router = Router::<()>::default()
+            .register(
+                Route::new(path!("/")).get(text("text/plain; charset=utf-8", "café".as_bytes())),
+            )
+            .register(
+                Route::new(path!("/latin1"))
+                    .get(text("text/plain; charset=iso-8859-1", b"caf\xE9")),
+            )
+            .register(
+                Route::new(path!("/euro")).get(text("text/plain; charset=utf-8", "5 €".as_bytes())),
+            )
+            .register(
+                Route::new(path!("/kanji"))
+                    .get(text("text/plain; charset=utf-8", "漢字".as_bytes())),
+            )
+            .register(Route::new(path!("/events")).get(stream("text/event-stream")))
+            .register(Route::new(path!("/stream")).get(stream("text/plain")));
+        router.layer(charset());
+        router
+    }
+
+    #[tokio::test]
+    async fn transcodes_to_latin1() {
+        let req = Request::get("/")
+            .header(header::ACCEPT_CHARSET, "iso-8859-1, utf-8;q=0.5")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(router(), req).await;
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers()[header::CONTENT_TYPE],
+            "text/plain; charset=windows-1252"
+        );
+        assert_eq!(resp.body().as_ref(), b"caf\xE9");
+
+        // `€` is only in windows-1252, which iso-8859-1 is encoded as.
+        let req = Request::get("/euro")
+            .header(header::ACCEPT_CHARSET, "iso-8859-1")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(router(), req).await;
+
+        assert_eq!(
+            resp.headers()[header::CONTENT_TYPE],
+            "text/plain; charset=windows-1252"
+        );
+        assert_eq!(resp.body().as_ref(), b"5 \x80");
+    }
+
+    #[tokio::test]
+    async fn falls_back_on_unmappable_characters() {
+        let req = Request::get("/kanji")
+            .header(
+                header::ACCEPT_CHARSET,
+                "iso-8859-1, shift_jis;q=0.8, utf-8;q=0.5",
+            )
+            .body(Default::default())
+            .unwrap();
+        let resp = send(router(), req).await;
+
+        assert_eq!(
+            resp.headers()[header::CONTENT_TYPE],
+            "text/plain; charset=Shift_JIS"
+        );
+        assert_eq!(resp.body().as_ref(), b"\x8A\xBF\x8E\x9A");
+
+        let req = Request::get("/kanji")
+            .header(header::ACCEPT_CHARSET, "iso-8859-1")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(router(), req).await;
+
+        assert_eq!(resp.status(), StatusCode::NOT_ACCEPTABLE);
+    }
+
+    #[tokio::test]
+    async fn labels_the_charset_sent() {
+        let req = Request::get("/latin1")
+            .header(header::ACCEPT_CHARSET, "utf-16")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(router(), req).await;
+
+        assert_eq!(
+            resp.headers()[header::CONTENT_TYPE],
+            "text/plain; charset=UTF-8"
+        );
+        assert_eq!(resp.body().as_ref(), "café".as_bytes());
+    }
+
+    #[tokio::test]
+    async fn streams_pass_through() {
+        let router = std::sync::Arc::new(router());
+
+        for (path, content_type) in [("/events", "text/event-stream"), ("/stream", "text/plain")] {
+            let req = Request::get(path)
+                .header(header::ACCEPT_CHARSET, "iso-8859-1")
+                .body(Default::default())
+                .unwrap();
+            let resp = send(std::sync::Arc::clone(&router), req).await;
+
+            assert_eq!(resp.headers()[header::CONTENT_TYPE], content_type);
+            assert_eq!(
+                resp.body().as_ref(),
+                "data: café\n\ndata: café\n\n".as_bytes()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn unsupported_charset() {
+        let req = Request::get("/")
+            .header(header::ACCEPT_CHARSET, "x-klingon")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(router(), req).await;
+
+        assert_eq!(resp.status(), StatusCode::NOT_ACCEPTABLE);
+    }
+
+    #[tokio::test]
+    async fn utf8_passthrough() {
+        let resp = send(
+            router(),
+            Request::get("/").body(Default::default()).unwrap(),
+        )
+        .await;
+
+        assert_eq!(resp.body().as_ref(), "café".as_bytes());
+    }
+}
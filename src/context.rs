@@ -1,19 +1,386 @@
-use std::fmt::{Debug, Formatter};
+use crate::body::full;
+use crate::path::Path;
+use crate::response::IntoResponse;
+use hyper::header::{AsHeaderName, HeaderMap, HeaderValue};
+use hyper::http::Extensions;
+use hyper::{Method, StatusCode, header};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::net::SocketAddr;
+use std::str::FromStr;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Marks a request as received over TLS.
+///
+/// TLS acceptors insert this into the request's extensions before routing;
+/// the router carries it over into [`Context::extensions`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Secure;
+
+/// The most bytes a request body may hold, set with
+/// [`Router::max_body_size`](crate::Router::max_body_size).
+///
+/// The router inserts this into the request's extensions before routing, for
+/// body readers to stop at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MaxBodySize(pub usize);
 
 /// Context for an incoming request.
 pub struct Context<Extra> {
     /// Parameters corresponding to dynamic route segments.
     pub params: Vec<String>,
+    /// Parameters keyed by the name of their dynamic route segment.
+    pub params_by_name: HashMap<String, String>,
+    /// Decoded query string pairs in request order, including repeated keys.
+    pub query: Vec<(String, String)>,
     /// Shared pointer to router-level extra data (shared state).
     pub ex: Arc<Extra>,
+    /// Request-scoped data, e.g. inserted by middleware for downstream handlers.
+    pub extensions: Extensions,
+    /// When the request times out, if the router has a timeout configured.
+    pub deadline: Option<Instant>,
+    /// The path of the matched route, e.g. `/users/[id]` for a request to
+    /// `/users/42`. Useful as a low-cardinality label for metrics.
+    pub matched_pattern: Option<Path>,
+    /// The request's headers, shared so middleware and error handlers can
+    /// read them without the request.
+    pub headers: Arc<HeaderMap>,
+    /// The request's method.
+    pub method: Method,
+    /// The address of the client, when the router is given it, as by
+    /// [`Router::serve`](crate::Router::serve) and
+    /// [`Router::route_with_addr`](crate::Router::route_with_addr).
+    pub remote_addr: Option<SocketAddr>,
+    /// The ID of the request, when tagged by the
+    /// [`request_id`](crate::request_id) middleware.
+    pub request_id: Option<String>,
+}
+
+impl<Extra> Context<Extra> {
+    /// Starts building a context sharing `ex`, e.g. to call handler logic in
+    /// unit tests. Everything else starts out empty, as for a GET request
+    /// without parameters, query or headers.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::Context;
+    /// use std::sync::Arc;
+    ///
+    /// fn greeting(ctx: &Context<String>) -> String {
+    ///     format!("{}, {}", ctx.ex, ctx.param("name").unwrap_or("stranger"))
+    /// }
+    ///
+    /// let ctx = Context::builder(Arc::new("Hello".to_string()))
+    ///     .param("name", "alice")
+    ///     .build();
+    /// assert_eq!(greeting(&ctx), "Hello, alice");
+    /// ```
+    pub fn builder(ex: Arc<Extra>) -> ContextBuilder<Extra> {
+        ContextBuilder(Context {
+            params: vec![],
+            params_by_name: HashMap::new(),
+            query: vec![],
+            ex,
+            extensions: Extensions::new(),
+            deadline: None,
+            matched_pattern: None,
+            headers: Default::default(),
+            method: Method::GET,
+            remote_addr: None,
+            request_id: None,
+        })
+    }
+
+    /// Returns the parameter for the dynamic segment named `name`, e.g. `id`
+    /// for a route on `/users/[id]`.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params_by_name.get(name).map(String::as_str)
+    }
+
+    /// Parses the parameter for the dynamic segment named `name`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::{IntoResponse, Route, full, path};
+    /// use hyper::Response;
+    ///
+    /// Route::<()>::new(path!("/users/[id]")).get(|_, ctx| {
+    ///     let resp = match ctx.param_parsed::<u64>("id") {
+    ///         Ok(id) => Response::new(full(format!("user {}", id))),
+    ///         Err(err) => err.into_response(),
+    ///     };
+    ///
+    ///     Box::pin(async move { Ok(resp) })
+    /// });
+    /// ```
+    pub fn param_parsed<T>(&self, name: &str) -> Result<T, ParamError>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let value = self
+            .param(name)
+            .ok_or_else(|| ParamError::Missing(name.to_string()))?;
+        parse_param(name.to_string(), value)
+    }
+
+    /// Parses the parameter at position `idx` in [`Context::params`].
+    pub fn param_at<T>(&self, idx: usize) -> Result<T, ParamError>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let value = self
+            .params
+            .get(idx)
+            .ok_or_else(|| ParamError::Missing(idx.to_string()))?;
+        parse_param(idx.to_string(), value)
+    }
+
+    /// Returns the first query string value for `key`. Keys without a value,
+    /// like `flag` in `?flag`, yield an empty string.
+    pub fn query(&self, key: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the request's cookies by name, parsed from its `Cookie`
+    /// headers. Quotes around values are removed. If a name is sent more
+    /// than once, the first value is kept.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::{Route, full, path};
+    /// use hyper::Response;
+    ///
+    /// Route::<()>::new(path!("/")).get(|_, ctx| {
+    ///     let session = ctx.cookies().remove("session").unwrap_or_default();
+    ///     Box::pin(async move { Ok(Response::new(full(session))) })
+    /// });
+    /// ```
+    pub fn cookies(&self) -> HashMap<String, String> {
+        let mut cookies = HashMap::new();
+        let pairs = self
+            .headers
+            .get_all(header::COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(';'));
+        for pair in pairs {
+            let Some((name, value)) = pair.split_once('=') else {
+                continue;
+            };
+
+            let name = name.trim();
+            if name.is_empty() {
+                continue;
+            }
+
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|value| value.strip_suffix('"'))
+                .unwrap_or(value);
+            cookies
+                .entry(name.to_string())
+                .or_insert_with(|| value.to_string());
+        }
+
+        cookies
+    }
+
+    /// Returns the request header `name`, or the first one if it was sent
+    /// more than once.
+    pub fn header(&self, name: impl AsHeaderName) -> Option<&HeaderValue> {
+        self.headers.get(name)
+    }
+
+    /// Returns the request's method.
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    /// Returns the time left until the request's deadline, e.g. to bound
+    /// downstream calls. Zero once the deadline has passed.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Returns whether the request arrived over TLS, either directly or, when
+    /// the router trusts it, as reported by a proxy's `X-Forwarded-Proto`.
+    pub fn is_secure(&self) -> bool {
+        self.extensions.get::<Secure>().is_some()
+    }
+}
+
+/// Builds a [`Context`], created with [`Context::builder`].
+pub struct ContextBuilder<Extra>(Context<Extra>);
+
+impl<Extra> ContextBuilder<Extra> {
+    /// Adds the parameter `value` for the dynamic segment named `name`, after
+    /// the parameters added before it.
+    pub fn param(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        let value = value.into();
+        self.0.params.push(value.clone());
+        self.0.params_by_name.insert(name.into(), value);
+        self
+    }
+
+    /// Adds a decoded query string pair.
+    pub fn query(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.0.query.push((key.into(), value.into()));
+        self
+    }
+
+    /// Adds a request header.
+    pub fn header(mut self, name: header::HeaderName, value: HeaderValue) -> Self {
+        Arc::make_mut(&mut self.0.headers).append(name, value);
+        self
+    }
+
+    /// Sets the request's method. Defaults to GET.
+    pub fn method(mut self, method: Method) -> Self {
+        self.0.method = method;
+        self
+    }
+
+    /// Inserts request-scoped data, as middleware would.
+    pub fn extension<T: Clone + Send + Sync + 'static>(mut self, value: T) -> Self {
+        self.0.extensions.insert(value);
+        self
+    }
+
+    /// Sets the path of the matched route.
+    pub fn matched_pattern(mut self, pattern: impl Into<Path>) -> Self {
+        self.0.matched_pattern = Some(pattern.into());
+        self
+    }
+
+    /// Sets the address of the client.
+    pub fn remote_addr(mut self, addr: SocketAddr) -> Self {
+        self.0.remote_addr = Some(addr);
+        self
+    }
+
+    /// Sets the ID of the request.
+    pub fn request_id(mut self, id: impl Into<String>) -> Self {
+        self.0.request_id = Some(id.into());
+        self
+    }
+
+    /// Returns the built context.
+    pub fn build(self) -> Context<Extra> {
+        self.0
+    }
+}
+
+fn parse_param<T>(param: String, value: &str) -> Result<T, ParamError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    value.parse().map_err(|err: T::Err| ParamError::Invalid {
+        param,
+        value: value.to_string(),
+        reason: err.to_string(),
+    })
+}
+
+/// A route parameter that couldn't be extracted with
+/// [`Context::param_parsed`] or [`Context::param_at`].
+///
+/// Responds with `400 Bad Request`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParamError {
+    /// The route has no parameter with this name or at this position.
+    Missing(String),
+    /// The parameter's value doesn't parse as the requested type.
+    Invalid {
+        /// The name or position of the parameter.
+        param: String,
+        /// The value that failed to parse.
+        value: String,
+        /// Why the value failed to parse.
+        reason: String,
+    },
+}
+
+impl Display for ParamError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Missing(param) => write!(f, "missing parameter `{}`", param),
+            Self::Invalid {
+                param,
+                value,
+                reason,
+            } => write!(f, "invalid parameter `{}` ({:?}): {}", param, value, reason),
+        }
+    }
+}
+
+impl Error for ParamError {}
+
+impl IntoResponse for ParamError {
+    fn into_response(self) -> crate::Response {
+        hyper::Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+            .body(full(self.to_string()))
+            .unwrap()
+    }
+}
+
+/// A set of route parameters parsed together from a [`Context`], usually
+/// derived with [`macros::FromParams`](crate::macros::FromParams).
+///
+/// # Example
+///
+/// ```
+/// use hyper::Response;
+/// use radmin_router::{FromParams, IntoResponse, Route, full, macros, path};
+///
+/// #[derive(macros::FromParams)]
+/// #[params("/users/[id]/posts/[slug]")]
+/// struct PostParams {
+///     id: u64,
+///     slug: String,
+/// }
+///
+/// Route::<()>::new(path!("/users/[id]/posts/[slug]")).get(|_, ctx| {
+///     let resp = match PostParams::from_context(&ctx) {
+///         Ok(PostParams { id, slug }) => Response::new(full(format!("{} by {}", slug, id))),
+///         Err(err) => err.into_response(),
+///     };
+///
+///     Box::pin(async move { Ok(resp) })
+/// });
+/// ```
+pub trait FromParams: Sized {
+    fn from_context<Extra>(ctx: &Context<Extra>) -> Result<Self, ParamError>;
 }
 
 impl<Extra> Clone for Context<Extra> {
     fn clone(&self) -> Self {
         Self {
             params: Clone::clone(&self.params),
+            params_by_name: Clone::clone(&self.params_by_name),
+            query: Clone::clone(&self.query),
             ex: Arc::clone(&self.ex),
+            extensions: Clone::clone(&self.extensions),
+            deadline: self.deadline,
+            matched_pattern: Clone::clone(&self.matched_pattern),
+            headers: Arc::clone(&self.headers),
+            method: Clone::clone(&self.method),
+            remote_addr: self.remote_addr,
+            request_id: Clone::clone(&self.request_id),
         }
     }
 }
@@ -22,7 +389,137 @@ impl<Extra: Debug> Debug for Context<Extra> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Context")
             .field("params", &self.params)
+            .field("params_by_name", &self.params_by_name)
+            .field("query", &self.query)
             .field("ex", &self.ex)
+            .field("extensions", &self.extensions)
+            .field("deadline", &self.deadline)
+            .field("matched_pattern", &self.matched_pattern)
+            .field("headers", &self.headers)
+            .field("method", &self.method)
+            .field("remote_addr", &self.remote_addr)
+            .field("request_id", &self.request_id)
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(params: &[(&str, &str)]) -> Context<()> {
+        params
+            .iter()
+            .fold(Context::builder(Arc::new(())), |builder, (name, value)| {
+                builder.param(*name, *value)
+            })
+            .build()
+    }
+
+    #[test]
+    fn builds_contexts() {
+        let addr = "127.0.0.1:8080".parse().unwrap();
+        let ctx = Context::builder(Arc::new(7))
+            .param("org", "acme")
+            .param("id", "42")
+            .query("page", "2")
+            .header(header::COOKIE, HeaderValue::from_static("session=abc"))
+            .method(Method::PUT)
+            .extension(Secure)
+            .matched_pattern("/orgs/[org]/users/[id]")
+            .remote_addr(addr)
+            .request_id("req-1")
+            .build();
+
+        assert_eq!(*ctx.ex, 7);
+        assert_eq!(ctx.params, ["acme", "42"]);
+        assert_eq!(ctx.param_parsed::<u64>("id"), Ok(42));
+        assert_eq!(ctx.query("page"), Some("2"));
+        assert_eq!(ctx.cookies()["session"], "abc");
+        assert_eq!(ctx.method(), Method::PUT);
+        assert!(ctx.is_secure());
+        assert_eq!(
+            ctx.matched_pattern.unwrap().to_string(),
+            "/orgs/[org]/users/[id]"
+        );
+        assert_eq!(ctx.remote_addr, Some(addr));
+        assert_eq!(ctx.request_id.as_deref(), Some("req-1"));
+
+        let empty = Context::builder(Arc::new(())).build();
+        assert!(empty.params.is_empty() && empty.query.is_empty() && empty.headers.is_empty());
+        assert_eq!(empty.method(), Method::GET);
+    }
+
+    #[test]
+    fn parses_integer_params() {
+        let ctx = context(&[("id", "42"), ("name", "alice")]);
+
+        assert_eq!(ctx.param_parsed::<u64>("id"), Ok(42));
+        assert_eq!(ctx.param_at::<u64>(0), Ok(42));
+        assert_eq!(
+            ctx.param_parsed::<u64>("name"),
+            Err(ParamError::Invalid {
+                param: "name".into(),
+                value: "alice".into(),
+                reason: "invalid digit found in string".into(),
+            })
+        );
+    }
+
+    #[test]
+    fn parses_cookies() {
+        let mut headers = HeaderMap::new();
+        headers.append(header::COOKIE, HeaderValue::from_static("a=1; b=2"));
+        headers.append(
+            header::COOKIE,
+            HeaderValue::from_static("quoted=\"hello world\";a=3; flag"),
+        );
+
+        let mut ctx = context(&[]);
+        assert!(ctx.cookies().is_empty());
+        ctx.headers = Arc::new(headers);
+
+        let cookies = ctx.cookies();
+        assert_eq!(cookies.len(), 3);
+        assert_eq!(cookies["a"], "1");
+        assert_eq!(cookies["b"], "2");
+        assert_eq!(cookies["quoted"], "hello world");
+    }
+
+    #[test]
+    fn missing_params() {
+        let ctx = context(&[("id", "42")]);
+
+        assert_eq!(
+            ctx.param_parsed::<u64>("user"),
+            Err(ParamError::Missing("user".into()))
+        );
+        assert_eq!(ctx.param_at::<u64>(1), Err(ParamError::Missing("1".into())));
+        assert_eq!(
+            ctx.param_parsed::<u64>("user")
+                .unwrap_err()
+                .into_response()
+                .status(),
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn parses_uuid_params() {
+        let ctx = context(&[("id", "67e55044-10b1-426f-9247-bb680e5fe0c8")]);
+
+        assert_eq!(
+            ctx.param_parsed::<uuid::Uuid>("id").unwrap(),
+            uuid::Uuid::from_u128(0x67e5504410b1426f9247bb680e5fe0c8)
+        );
+        assert!(matches!(
+            ctx.param_parsed::<uuid::Uuid>("missing"),
+            Err(ParamError::Missing(_))
+        ));
+        assert!(matches!(
+            context(&[("id", "not-a-uuid")]).param_parsed::<uuid::Uuid>("id"),
+            Err(ParamError::Invalid { .. })
+        ));
+    }
+}
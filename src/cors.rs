@@ -0,0 +1,394 @@
+use crate::body::empty;
+use crate::context::Context;
+use crate::middleware::Next;
+use futures::future::BoxFuture;
+use hyper::body::Incoming;
+use hyper::header::{self, HeaderMap, HeaderName, HeaderValue};
+use hyper::{Method, Request, Response, StatusCode};
+use std::sync::Arc;
+use std::time::Duration;
+
+const ACCESS_CONTROL_REQUEST_PRIVATE_NETWORK: HeaderName =
+    HeaderName::from_static("access-control-request-private-network");
+const ACCESS_CONTROL_ALLOW_PRIVATE_NETWORK: HeaderName =
+    HeaderName::from_static("access-control-allow-private-network");
+
+/// A configurable Cross-Origin Resource Sharing policy.
+///
+/// The middleware built with [`Cors::build`] answers preflight requests
+/// (`OPTIONS` with `Access-Control-Request-Method`) with `204 No Content` and
+/// the configured headers, without running the route's handler, and adds
+/// `Access-Control-Allow-Origin` to all other responses. Requests without an
+/// `Origin` header, or from an origin that isn't allowed, pass through
+/// without CORS headers. With an origin list, every response gets
+/// `Vary: Origin`, since whether it allows the origin depends on the request.
+///
+/// By default any origin is allowed with `GET`, `HEAD` and `POST`.
+///
+/// # Example
+///
+/// ```
+/// use hyper::Method;
+/// use hyper::header;
+/// use radmin_router::{Cors, Router};
+/// use std::time::Duration;
+///
+/// Router::<()>::default().layer(
+///     Cors::default()
+///         .allow_origin("https://admin.example.com")
+///         .allow_methods([Method::GET, Method::PUT, Method::DELETE])
+///         .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION])
+///         .allow_credentials(true)
+///         .max_age(Duration::from_secs(600))
+///         .build(),
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct Cors {
+    origins: Option<Vec<HeaderValue>>,
+    methods: Vec<Method>,
+    headers: Vec<HeaderName>,
+    credentials: bool,
+    private_network: bool,
+    max_age: Option<Duration>,
+}
+
+impl Default for Cors {
+    fn default() -> Self {
+        Self {
+            origins: None,
+            methods: vec![Method::GET, Method::HEAD, Method::POST],
+            headers: Vec::new(),
+            credentials: false,
+            private_network: false,
+            max_age: None,
+        }
+    }
+}
+
+impl Cors {
+    /// Allows requests from `origin`, e.g. `https://example.com`, echoing it
+    /// in `Access-Control-Allow-Origin`. Call repeatedly to allow several
+    /// origins; the first call replaces the default of allowing any origin.
+    ///
+    /// `*` allows any origin again.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `origin` isn't a valid header value.
+    pub fn allow_origin(mut self, origin: &str) -> Self {
+        if origin == "*" {
+            self.origins = None;
+            return self;
+        }
+
+        let origin = HeaderValue::from_str(origin).expect("invalid origin");
+        self.origins.get_or_insert_with(Vec::new).push(origin);
+        self
+    }
+
+    /// Sets the methods sent in `Access-Control-Allow-Methods`.
+    pub fn allow_methods(mut self, methods: impl IntoIterator<Item = Method>) -> Self {
+        self.methods = methods.into_iter().collect();
+        self
+    }
+
+    /// Sets the request headers sent in `Access-Control-Allow-Headers`.
+    pub fn allow_headers(mut self, headers: impl IntoIterator<Item = HeaderName>) -> Self {
+        self.headers = headers.into_iter().collect();
+        self
+    }
+
+    /// Sets whether to send `Access-Control-Allow-Credentials: true`.
+    ///
+    /// Credentials need an explicit list of origins from
+    /// [`allow_origin`](Cors::allow_origin): allowing them from any origin
+    /// would let every site make authenticated requests.
+    pub fn allow_credentials(mut self, credentials: bool) -> Self {
+        self.credentials = credentials;
+        self
+    }
+
+    /// Sets whether preflights carrying
+    /// `Access-Control-Request-Private-Network: true` are answered with
+    /// `Access-Control-Allow-Private-Network: true`, letting public sites
+    /// reach a server on a private network or `localhost`.
+    pub fn allow_private_network(mut self, private_network: bool) -> Self {
+        self.private_network = private_network;
+        self
+    }
+
+    /// Sets how long browsers may cache preflight responses, sent in
+    /// `Access-Control-Max-Age`.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Builds a middleware for use with [`Router::layer`](crate::Router::layer).
+    ///
+    /// # Panics
+    ///
+    /// Panics if credentials are allowed without an explicit list of origins.
+    pub fn build<Extra>(
+        self,
+    ) -> impl Fn(Request<Incoming>, Context<Extra>, Next<Extra>) -> BoxFuture<'static, crate::Result>
+    + Send
+    + Sync
+    + 'static
+    where
+        Extra: Send + Sync + 'static,
+    {
+        assert!(
+            !self.credentials || self.origins.is_some(),
+            "CORS credentials need an explicit list of allowed origins"
+        );
+        let policy = Arc::new(Policy::new(self));
+
+        move |req, ctx, next| {
+            let policy = Arc::clone(&policy);
+            let origin = req
+                .headers()
+                .get(header::ORIGIN)
+                .and_then(|origin| policy.allow_origin(origin));
+
+            Box::pin(async move {
+                let is_preflight = req.method() == Method::OPTIONS
+                    && req
+                        .headers()
+                        .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD);
+                let mut resp = if is_preflight && origin.is_some() {
+                    let mut resp = Response::new(empty());
+                    *resp.status_mut() = StatusCode::NO_CONTENT;
+                    policy.preflight(resp.headers_mut());
+                    let private_network = req
+                        .headers()
+                        .get(ACCESS_CONTROL_REQUEST_PRIVATE_NETWORK)
+                        .is_some_and(|value| value == "true");
+                    if private_network && policy.private_network {
+                        resp.headers_mut().insert(
+                            ACCESS_CONTROL_ALLOW_PRIVATE_NETWORK,
+                            HeaderValue::from_static("true"),
+                        );
+                    }
+                    resp
+                } else {
+                    next.run(req, ctx).await?
+                };
+
+                let headers = resp.headers_mut();
+                if policy.origins.is_some() {
+                    headers.append(header::VARY, HeaderValue::from_static("Origin"));
+                }
+                if let Some(origin) = origin {
+                    headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+                    if policy.credentials {
+                        headers.insert(
+                            header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                            HeaderValue::from_static("true"),
+                        );
+                    }
+                }
+
+                Ok(resp)
+            })
+        }
+    }
+}
+
+/// A [`Cors`] policy with its preflight headers prepared.
+struct Policy {
+    origins: Option<Vec<HeaderValue>>,
+    credentials: bool,
+    private_network: bool,
+    preflight: Vec<(HeaderName, HeaderValue)>,
+}
+
+impl Policy {
+    fn new(cors: Cors) -> Self {
+        let join = |values: Vec<&str>| HeaderValue::from_str(&values.join(", ")).unwrap();
+        let mut preflight = vec![(
+            header::ACCESS_CONTROL_ALLOW_METHODS,
+            join(cors.methods.iter().map(Method::as_str).collect()),
+        )];
+
+        if !cors.headers.is_empty() {
+            preflight.push((
+                header::ACCESS_CONTROL_ALLOW_HEADERS,
+                join(cors.headers.iter().map(HeaderName::as_str).collect()),
+            ));
+        }
+
+        if let Some(max_age) = cors.max_age {
+            preflight.push((
+                header::ACCESS_CONTROL_MAX_AGE,
+                HeaderValue::from(max_age.as_secs()),
+            ));
+        }
+
+        Self {
+            origins: cors.origins,
+            credentials: cors.credentials,
+            private_network: cors.private_network,
+            preflight,
+        }
+    }
+
+    /// Returns the `Access-Control-Allow-Origin` value for a request from
+    /// `origin`, if it's allowed.
+    fn allow_origin(&self, origin: &HeaderValue) -> Option<HeaderValue> {
+        match &self.origins {
+            None => Some(HeaderValue::from_static("*")),
+            Some(origins) => origins.contains(origin).then(|| origin.clone()),
+        }
+    }
+
+    fn preflight(&self, headers: &mut HeaderMap) {
+        for (name, value) in &self.preflight {
+            headers.insert(name, value.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::send;
+    use crate::{Route, Router, path};
+
+    fn router(cors: Cors) -> Arc<Router<()>> {
+        let mut router = Router::<()>::default().register(
+            Route::new(path!("/"))
+                .get(|_, _| Box::pin(async { Ok(hyper::Response::new(crate::full("OK"))) })),
+        );
+        router.layer(cors.build());
+        Arc::new(router)
+    }
+
+    #[tokio::test]
+    async fn preflight() {
+        let cors = Cors::default()
+            .allow_origin("https://example.com")
+            .allow_methods([Method::GET, Method::DELETE])
+            .allow_headers([header::CONTENT_TYPE])
+            .max_age(Duration::from_secs(600));
+        let req = Request::options("/")
+            .header(header::ORIGIN, "https://example.com")
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "DELETE")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(router(cors), req).await;
+
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        let headers = resp.headers();
+        assert_eq!(
+            headers[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://example.com"
+        );
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_METHODS], "GET, DELETE");
+        assert_eq!(
+            headers[header::ACCESS_CONTROL_ALLOW_HEADERS],
+            "content-type"
+        );
+        assert_eq!(headers[header::ACCESS_CONTROL_MAX_AGE], "600");
+        assert_eq!(headers[header::VARY], "Origin");
+    }
+
+    #[tokio::test]
+    async fn private_network_preflight() {
+        let preflight = |private_network: bool| {
+            let req = Request::options("/")
+                .header(header::ORIGIN, "https://example.com")
+                .header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET");
+            let req = if private_network {
+                req.header(ACCESS_CONTROL_REQUEST_PRIVATE_NETWORK, "true")
+            } else {
+                req
+            };
+            req.body(Default::default()).unwrap()
+        };
+
+        let allowed = router(Cors::default().allow_private_network(true));
+        let resp = send(Arc::clone(&allowed), preflight(true)).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert_eq!(resp.headers()[ACCESS_CONTROL_ALLOW_PRIVATE_NETWORK], "true");
+
+        let resp = send(allowed, preflight(false)).await;
+        assert!(
+            !resp
+                .headers()
+                .contains_key(ACCESS_CONTROL_ALLOW_PRIVATE_NETWORK)
+        );
+
+        let resp = send(router(Cors::default()), preflight(true)).await;
+        assert!(
+            !resp
+                .headers()
+                .contains_key(ACCESS_CONTROL_ALLOW_PRIVATE_NETWORK)
+        );
+    }
+
+    #[tokio::test]
+    async fn simple_request() {
+        let router = router(Cors::default());
+        let req = Request::get("/")
+            .header(header::ORIGIN, "https://example.com")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(Arc::clone(&router), req).await;
+
+        assert_eq!(resp.body().as_ref(), b"OK");
+        assert_eq!(resp.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert!(!resp.headers().contains_key(header::VARY));
+
+        let req = Request::get("/").body(Default::default()).unwrap();
+        let resp = send(router, req).await;
+        assert!(
+            !resp
+                .headers()
+                .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+        );
+    }
+
+    #[tokio::test]
+    async fn echoes_allowed_origins() {
+        let router = router(
+            Cors::default()
+                .allow_origin("https://a.example.com")
+                .allow_origin("https://b.example.com")
+                .allow_credentials(true),
+        );
+
+        let req = Request::get("/")
+            .header(header::ORIGIN, "https://b.example.com")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(Arc::clone(&router), req).await;
+        let headers = resp.headers();
+        assert_eq!(
+            headers[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://b.example.com"
+        );
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+
+        let req = Request::get("/")
+            .header(header::ORIGIN, "https://evil.example.com")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(
+            !resp
+                .headers()
+                .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+        );
+        // Caches mustn't serve this response to an allowed origin.
+        assert_eq!(resp.headers()[header::VARY], "Origin");
+    }
+
+    #[test]
+    #[should_panic(expected = "explicit list of allowed origins")]
+    fn credentials_need_origins() {
+        let _ = Cors::default().allow_credentials(true).build::<()>();
+    }
+}
@@ -0,0 +1,199 @@
+use std::borrow::Cow;
+
+/// Percent-decodes `input`, leaving malformed escapes untouched and
+/// replacing invalid UTF-8 with `U+FFFD`.
+pub(crate) fn percent_decode(input: &str) -> Cow<'_, str> {
+    match decode_bytes(input, false) {
+        Cow::Borrowed(_) => Cow::Borrowed(input),
+        Cow::Owned(decoded) => Cow::Owned(String::from_utf8_lossy(&decoded).into_owned()),
+    }
+}
+
+/// Percent-decodes `input`, leaving malformed escapes untouched. Returns
+/// `None` if it decodes to invalid UTF-8.
+pub(crate) fn percent_decode_utf8(input: &str) -> Option<Cow<'_, str>> {
+    match decode_bytes(input, false) {
+        Cow::Borrowed(_) => Some(Cow::Borrowed(input)),
+        Cow::Owned(decoded) => String::from_utf8(decoded).ok().map(Cow::Owned),
+    }
+}
+
+/// Percent-encodes `input` for use as a single path segment, escaping all
+/// bytes but unreserved characters, sub-delimiters, `:` and `@`.
+pub(crate) fn percent_encode(input: &str) -> Cow<'_, str> {
+    let is_allowed =
+        |byte: u8| byte.is_ascii_alphanumeric() || b"-._~!$&'()*+,;=:@".contains(&byte);
+    if input.bytes().all(is_allowed) {
+        return Cow::Borrowed(input);
+    }
+
+    let mut encoded = String::with_capacity(input.len() * 3);
+    for byte in input.bytes() {
+        if is_allowed(byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+
+    Cow::Owned(encoded)
+}
+
+/// Decodes an `application/x-www-form-urlencoded` string into key-value
+/// pairs, treating `+` as a space. Keys without a value map to an empty value.
+///
+/// When `strict`, returns `None` if any key or value contains a malformed
+/// escape or decodes to invalid UTF-8; otherwise malformed escapes are left
+/// untouched and invalid UTF-8 is replaced with `U+FFFD`.
+pub(crate) fn form_decode(input: &str, strict: bool) -> Option<Vec<(String, String)>> {
+    let decode = |component: &str| {
+        let bytes = decode_bytes(component, true);
+
+        if strict {
+            // Every valid escape shortens the input by two bytes.
+            if bytes.len() + 2 * component.matches('%').count() != component.len() {
+                return None;
+            }
+
+            String::from_utf8(bytes.into_owned()).ok()
+        } else {
+            Some(String::from_utf8_lossy(&bytes).into_owned())
+        }
+    };
+
+    input
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            Some((decode(key)?, decode(value)?))
+        })
+        .collect()
+}
+
+/// Decodes the percent escapes of `input`, and `+` as a space if
+/// `plus_as_space`, leaving malformed escapes untouched.
+fn decode_bytes(input: &str, plus_as_space: bool) -> Cow<'_, [u8]> {
+    let bytes = input.as_bytes();
+    if !bytes.contains(&b'%') && (!plus_as_space || !bytes.contains(&b'+')) {
+        return Cow::Borrowed(bytes);
+    }
+
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+
+    while idx < bytes.len() {
+        if bytes[idx] == b'%'
+            && let Some(byte) = decode_hex_pair(&bytes[idx + 1..])
+        {
+            decoded.push(byte);
+            idx += 3;
+            continue;
+        }
+
+        decoded.push(match bytes[idx] {
+            b'+' if plus_as_space => b' ',
+            byte => byte,
+        });
+        idx += 1;
+    }
+
+    Cow::Owned(decoded)
+}
+
+/// Strips `prefix` off the front of `input`, which may percent-encode any of
+/// the bytes `prefix` is compared with, returning the rest of `input`.
+pub(crate) fn strip_decoded_prefix<'a>(input: &'a str, prefix: &str) -> Option<&'a str> {
+    let bytes = input.as_bytes();
+    let mut idx = 0;
+
+    for &expected in prefix.as_bytes() {
+        match bytes.get(idx)? {
+            &byte if byte == expected => idx += 1,
+            b'%' if decode_hex_pair(&bytes[idx + 1..]) == Some(expected) => idx += 3,
+            _ => return None,
+        }
+    }
+
+    input.get(idx..)
+}
+
+/// Returns the offset in `input` of the first occurrence of `needle`,
+/// matched like [`strip_decoded_prefix`].
+pub(crate) fn find_decoded(input: &str, needle: &str) -> Option<usize> {
+    input
+        .char_indices()
+        .map(|(idx, _)| idx)
+        .find(|&idx| strip_decoded_prefix(&input[idx..], needle).is_some())
+}
+
+fn decode_hex_pair(bytes: &[u8]) -> Option<u8> {
+    let hi = (*bytes.first()? as char).to_digit(16)?;
+    let lo = (*bytes.get(1)? as char).to_digit(16)?;
+    Some((hi * 16 + lo) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_escapes() {
+        assert_eq!(percent_decode("a%2Fb"), "a/b");
+        assert_eq!(percent_decode("john%20doe"), "john doe");
+        assert_eq!(percent_decode("a+b"), "a+b");
+    }
+
+    #[test]
+    fn malformed_escapes_untouched() {
+        assert_eq!(percent_decode("%zz"), "%zz");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%ff"), "\u{FFFD}");
+    }
+
+    #[test]
+    fn decode_utf8() {
+        assert_eq!(percent_decode_utf8("john%20doe").unwrap(), "john doe");
+        assert_eq!(percent_decode_utf8("%zz").unwrap(), "%zz");
+        assert!(percent_decode_utf8("%ff").is_none());
+    }
+
+    #[test]
+    fn encode_segment() {
+        assert_eq!(percent_encode("users"), "users");
+        assert_eq!(percent_encode("a/b c"), "a%2Fb%20c");
+        assert_eq!(percent_encode("100%"), "100%25");
+        assert_eq!(percent_encode("café"), "caf%C3%A9");
+        assert_eq!(percent_decode(&percent_encode("a/b?c#d")), "a/b?c#d");
+    }
+
+    #[test]
+    fn form_decode_pairs() {
+        let pairs = form_decode("a=1&b=two+words&flag&c=%26%3D", false).unwrap();
+        assert_eq!(
+            pairs,
+            vec![
+                ("a".into(), "1".into()),
+                ("b".into(), "two words".into()),
+                ("flag".into(), "".into()),
+                ("c".into(), "&=".into()),
+            ]
+        );
+        assert!(form_decode("", true).unwrap().is_empty());
+    }
+
+    #[test]
+    fn form_decode_strict() {
+        assert_eq!(
+            form_decode("name=%ff", false).unwrap(),
+            vec![("name".into(), "\u{FFFD}".into())]
+        );
+        assert!(form_decode("name=%ff", true).is_none());
+        assert!(form_decode("name=100%", true).is_none());
+        assert!(form_decode("%zz=1", true).is_none());
+        assert_eq!(
+            form_decode("name=caf%C3%A9", true).unwrap(),
+            vec![("name".into(), "café".into())]
+        );
+    }
+}
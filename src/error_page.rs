@@ -0,0 +1,120 @@
+use crate::body::full;
+use hyper::header::{self, HeaderValue};
+use hyper::{Method, Response, StatusCode};
+
+/// Details of a failed request passed to an [`ErrorPageRenderer`].
+#[derive(Clone, Copy, Debug)]
+pub struct ErrorPage<'a> {
+    pub status: StatusCode,
+    pub method: &'a Method,
+    pub path: &'a str,
+}
+
+/// Renders the router's built-in error responses, configured with
+/// [`Router::error_pages`](crate::Router::error_pages).
+///
+/// Used for `404 Not Found` when no `route_not_found` handler is registered
+/// and for `500 Internal Server Error` when a handler panics without a panic
+/// handler.
+pub trait ErrorPageRenderer: Send + Sync {
+    fn render(&self, page: ErrorPage<'_>) -> crate::Response;
+}
+
+/// An [`ErrorPageRenderer`] producing a basic HTML page with the status and
+/// requested path.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultErrorPages;
+
+impl ErrorPageRenderer for DefaultErrorPages {
+    fn render(&self, page: ErrorPage<'_>) -> crate::Response {
+        let title = format!(
+            "{} {}",
+            page.status.as_u16(),
+            page.status.canonical_reason().unwrap_or("Error")
+        );
+        let body = format!(
+            "<!DOCTYPE html>\n\
+             <html>\n\
+             <head><title>{title}</title></head>\n\
+             <body>\n\
+             <h1>{title}</h1>\n\
+             <p>{} <code>{}</code></p>\n\
+             </body>\n\
+             </html>\n",
+            page.method,
+            escape_html(page.path)
+        );
+
+        let mut resp = Response::new(full(body));
+        *resp.status_mut() = page.status;
+        resp.headers_mut().insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("text/html; charset=utf-8"),
+        );
+        resp
+    }
+}
+
+/// The body format of the router's built-in error responses, set with
+/// [`Router::error_format`](crate::Router::error_format).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    /// The status's reason phrase as `text/plain`, e.g. `Not Found`.
+    #[default]
+    Text,
+    /// A JSON object with the reason phrase and status code, e.g.
+    /// `{"error":"Not Found","status":404}`.
+    Json,
+}
+
+impl Format {
+    /// Generates a bare response with `status`, described in this format.
+    pub(crate) fn render(self, status: StatusCode) -> crate::Response {
+        let reason = status.canonical_reason().unwrap_or("Error");
+        let (body, content_type) = match self {
+            // Reason phrases never need escaping in a JSON string.
+            Self::Json => (
+                format!(r#"{{"error":"{}","status":{}}}"#, reason, status.as_u16()),
+                "application/json",
+            ),
+            Self::Text => (reason.to_string(), "text/plain; charset=utf-8"),
+        };
+
+        let mut resp = Response::new(full(body));
+        *resp.status_mut() = status;
+        resp.headers_mut()
+            .insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+        resp
+    }
+}
+
+/// Escapes text for inclusion in HTML element content or attribute values.
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_html() {
+        assert_eq!(
+            escape_html(r#"/<script>alert("x&y")</script>"#),
+            "/&lt;script&gt;alert(&quot;x&amp;y&quot;)&lt;/script&gt;"
+        );
+    }
+}
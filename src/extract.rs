@@ -0,0 +1,205 @@
+use crate::context::Context;
+use crate::response::IntoResponse;
+use crate::route::{HandlerFn, Response, ResponseFut};
+use hyper::Request;
+use hyper::body::Incoming;
+use std::convert::Infallible;
+use std::ops::Deref;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// A value a handler can extract from a request and its context, passed to
+/// handlers adapted with [`extract_handler`].
+pub trait FromRequest<Extra>: Sized {
+    /// The response sent when extraction fails.
+    type Rejection: IntoResponse;
+
+    /// Extracts the value from `req` and `ctx`, or returns the rejection
+    /// to respond with instead of running the handler.
+    fn from_request(req: &Request<Incoming>, ctx: &Context<Extra>)
+    -> Result<Self, Self::Rejection>;
+}
+
+/// Adapts an async closure or function taking a value extracted with
+/// [`FromRequest`], along with the request and its context, into a
+/// [`HandlerFn`]. Requests the value can't be extracted from are answered
+/// with the extractor's rejection, without running the handler.
+///
+/// # Example
+///
+/// ```
+/// use hyper::Response;
+/// use radmin_router::{Route, State, extract_handler, full, path};
+///
+/// struct AppConfig {
+///     name: &'static str,
+/// }
+///
+/// Route::<AppConfig>::new(path!("/")).get(extract_handler(
+///     |State(config): State<AppConfig>, _, _| async move {
+///         Ok(Response::new(full(config.name)))
+///     },
+/// ));
+/// ```
+pub fn extract_handler<Extra, T, F, Fut>(handler: F) -> impl HandlerFn<Extra>
+where
+    T: FromRequest<Extra>,
+    F: Fn(T, Request<Incoming>, Context<Extra>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Response> + Send + 'static,
+{
+    move |req, ctx| -> Pin<Box<ResponseFut>> {
+        match T::from_request(&req, &ctx) {
+            Ok(value) => Box::pin(handler(value, req, ctx)),
+            Err(rejection) => {
+                let resp = rejection.into_response();
+                Box::pin(async move { Ok(resp) })
+            }
+        }
+    }
+}
+
+/// State shared through a router's `Extra`, or a part of it.
+#[derive(Debug)]
+pub struct State<T>(pub Arc<T>);
+
+impl<T> Clone for State<T> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<T> Deref for State<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// Conversion from a router's `Extra` into state extracted with [`State`].
+///
+/// Every `Extra` converts into itself. Implement this for parts of `Extra`
+/// held in an `Arc`:
+///
+/// ```
+/// use radmin_router::FromExtra;
+/// use std::sync::Arc;
+///
+/// struct AppConfig;
+///
+/// struct App {
+///     config: Arc<AppConfig>,
+/// }
+///
+/// impl FromExtra<App> for AppConfig {
+///     fn from_extra(ex: &Arc<App>) -> Arc<Self> {
+///         Arc::clone(&ex.config)
+///     }
+/// }
+/// ```
+pub trait FromExtra<Extra> {
+    /// Returns the state held by `ex`.
+    fn from_extra(ex: &Arc<Extra>) -> Arc<Self>;
+}
+
+impl<Extra> FromExtra<Extra> for Extra {
+    fn from_extra(ex: &Arc<Extra>) -> Arc<Self> {
+        Arc::clone(ex)
+    }
+}
+
+impl<Extra, T: FromExtra<Extra>> FromRequest<Extra> for State<T> {
+    type Rejection = Infallible;
+
+    fn from_request(_: &Request<Incoming>, ctx: &Context<Extra>) -> Result<Self, Infallible> {
+        Ok(State(T::from_extra(&ctx.ex)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::send;
+    use crate::{Route, Router, full, path};
+    use hyper::{Response, StatusCode, header};
+
+    struct AppConfig {
+        greeting: &'static str,
+    }
+
+    struct App {
+        config: Arc<AppConfig>,
+        name: &'static str,
+    }
+
+    impl FromExtra<App> for AppConfig {
+        fn from_extra(ex: &Arc<App>) -> Arc<Self> {
+            Arc::clone(&ex.config)
+        }
+    }
+
+    #[tokio::test]
+    async fn extracts_state() {
+        let app = App {
+            config: Arc::new(AppConfig { greeting: "hello" }),
+            name: "radmin",
+        };
+        let router = Router::new(Arc::new(app))
+            .register(Route::new(path!("/config")).get(extract_handler(
+                |State(config): State<AppConfig>, _, _| async move {
+                    Ok(Response::new(full(config.greeting)))
+                },
+            )))
+            .register(Route::new(path!("/app")).get(extract_handler(
+                |State(app): State<App>, _, _| async move { Ok(Response::new(full(app.name))) },
+            )));
+        let router = Arc::new(router);
+
+        let req = hyper::Request::get("/config")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(Arc::clone(&router), req).await;
+        assert_eq!(resp.body().as_ref(), b"hello");
+
+        let req = hyper::Request::get("/app")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.body().as_ref(), b"radmin");
+    }
+
+    /// The value of the `Authorization` header, which must be present.
+    struct Token(String);
+
+    impl<Extra> FromRequest<Extra> for Token {
+        type Rejection = StatusCode;
+
+        fn from_request(req: &Request<Incoming>, _: &Context<Extra>) -> Result<Self, StatusCode> {
+            let token = req
+                .headers()
+                .get(header::AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .ok_or(StatusCode::UNAUTHORIZED)?;
+            Ok(Token(token.to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_without_running_handler() {
+        let router = Arc::new(Router::<()>::default().register(Route::new(path!("/")).get(
+            extract_handler(|Token(token), _, _| async move { Ok(Response::new(full(token))) }),
+        )));
+
+        let req = hyper::Request::get("/")
+            .header(header::AUTHORIZATION, "secret")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(Arc::clone(&router), req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.body().as_ref(), b"secret");
+
+        let req = hyper::Request::get("/").body(Default::default()).unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+}
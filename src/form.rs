@@ -0,0 +1,211 @@
+use crate::body::{LimitedBodyError, body_error_response, collect_limited};
+use crate::response::IntoResponse;
+use hyper::Request;
+use hyper::body::Incoming;
+use hyper::header;
+use serde::de::DeserializeOwned;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+/// An error reading a form body with [`read_form`].
+///
+/// Responds with `413 Payload Too Large` for bodies over the router's
+/// [`max_body_size`](crate::Router::max_body_size), and `400 Bad Request`
+/// otherwise.
+#[derive(Debug)]
+pub enum FormError {
+    /// The request body couldn't be read.
+    Body(hyper::Error),
+    /// The request body exceeds the router's `max_body_size`.
+    TooLarge,
+    /// The request isn't `application/x-www-form-urlencoded`.
+    ContentType,
+    /// The form doesn't decode into the expected type.
+    Parse(serde_urlencoded::de::Error),
+}
+
+impl Display for FormError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Body(err) => write!(f, "failed to read request body: {}", err),
+            Self::TooLarge => f.write_str("request body is too large"),
+            Self::ContentType => f.write_str("expected an application/x-www-form-urlencoded body"),
+            Self::Parse(err) => write!(f, "malformed form: {}", err),
+        }
+    }
+}
+
+impl Error for FormError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Body(err) => Some(err),
+            Self::Parse(err) => Some(err),
+            Self::TooLarge | Self::ContentType => None,
+        }
+    }
+}
+
+impl IntoResponse for FormError {
+    fn into_response(self) -> crate::Response {
+        body_error_response(matches!(self, Self::TooLarge), &self)
+    }
+}
+
+impl From<LimitedBodyError> for FormError {
+    fn from(err: LimitedBodyError) -> Self {
+        match err {
+            LimitedBodyError::Body(err) => Self::Body(err),
+            LimitedBodyError::TooLarge => Self::TooLarge,
+        }
+    }
+}
+
+/// Collects the `application/x-www-form-urlencoded` body of `req`, e.g. an
+/// HTML form submission, and deserializes it.
+///
+/// `+` decodes to a space and percent-escapes are resolved. Decode into a
+/// `HashMap<String, String>` to accept any fields. Stops with
+/// [`FormError::TooLarge`] once the body exceeds the router's
+/// [`max_body_size`](crate::Router::max_body_size), if set.
+///
+/// # Example
+///
+/// ```
+/// use hyper::Response;
+/// use radmin_router::{IntoResponse, Route, full, path, read_form};
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Login {
+///     username: String,
+/// }
+///
+/// Route::<()>::new(path!("/login")).post(|req, _| {
+///     Box::pin(async move {
+///         Ok(match read_form::<Login>(req).await {
+///             Ok(login) => Response::new(full(format!("Welcome, {}", login.username))),
+///             Err(err) => err.into_response(),
+///         })
+///     })
+/// });
+/// ```
+pub async fn read_form<T: DeserializeOwned>(req: Request<Incoming>) -> Result<T, FormError> {
+    let is_form = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .is_some_and(|mime| {
+            mime.trim()
+                .eq_ignore_ascii_case("application/x-www-form-urlencoded")
+        });
+    if !is_form {
+        return Err(FormError::ContentType);
+    }
+
+    let buf = collect_limited(req).await?;
+    serde_urlencoded::from_bytes(&buf).map_err(FormError::Parse)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::body::full;
+    use crate::testing::send;
+    use crate::{Route, Router, path};
+    use bytes::Bytes;
+    use http_body_util::Full;
+    use hyper::StatusCode;
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    #[derive(Deserialize)]
+    struct Person {
+        name: String,
+        age: u32,
+    }
+
+    fn router() -> Router<()> {
+        Router::<()>::default()
+            .register(Route::new(path!("/person")).post(|req, _| {
+                Box::pin(async move {
+                    Ok(match read_form::<Person>(req).await {
+                        Ok(person) => {
+                            hyper::Response::new(full(format!("{} is {}", person.name, person.age)))
+                        }
+                        Err(err) => err.into_response(),
+                    })
+                })
+            }))
+            .register(Route::new(path!("/map")).post(|req, _| {
+                Box::pin(async move {
+                    Ok(match read_form::<HashMap<String, String>>(req).await {
+                        Ok(map) => {
+                            let mut pairs = map.into_iter().collect::<Vec<_>>();
+                            pairs.sort();
+                            hyper::Response::new(full(format!("{pairs:?}")))
+                        }
+                        Err(err) => err.into_response(),
+                    })
+                })
+            }))
+    }
+
+    fn post(path: &str, content_type: &str, body: &'static str) -> Request<Full<Bytes>> {
+        Request::post(path)
+            .header(header::CONTENT_TYPE, content_type)
+            .body(Full::new(Bytes::from_static(body.as_bytes())))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn decodes_forms() {
+        let router = std::sync::Arc::new(router());
+        let form = "application/x-www-form-urlencoded";
+
+        let resp = send(
+            router.clone(),
+            post("/person", form, "name=John+Doe&age=30"),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.body().as_ref(), b"John Doe is 30");
+
+        let req = post(
+            "/map",
+            "Application/X-WWW-Form-Urlencoded; charset=utf-8",
+            "name=John+Doe&age=30&note=a%26b",
+        );
+        let resp = send(router, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.body().as_ref(),
+            br#"[("age", "30"), ("name", "John Doe"), ("note", "a&b")]"#
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_forms() {
+        let router = std::sync::Arc::new(router());
+        let form = "application/x-www-form-urlencoded";
+
+        let resp = send(
+            router.clone(),
+            post("/person", "application/json", r#"{"age":30}"#),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let resp = send(
+            router.clone(),
+            post("/person", form, "name=John&age=thirty"),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let mut router = self::router();
+        router.max_body_size(8);
+        let resp = send(router, post("/person", form, "name=John+Doe&age=30")).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
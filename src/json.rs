@@ -0,0 +1,323 @@
+use crate::body::{LimitedBodyError, body_error_response, collect_limited, full};
+use crate::response::IntoResponse;
+use bytes::Bytes;
+use futures::{Stream, StreamExt, future};
+use http_body_util::{BodyExt, StreamBody};
+use hyper::body::{Frame, Incoming};
+use hyper::header::{self, HeaderValue};
+use hyper::{Request, StatusCode};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+/// Serializes `value` into an `application/json` response.
+///
+/// Responds with `500 Internal Server Error` if `value` fails to serialize,
+/// e.g. a map with non-string keys.
+///
+/// # Example
+///
+/// ```
+/// use radmin_router::json;
+/// use serde_json::json;
+///
+/// let resp = json(&json!({ "ok": true }));
+/// assert_eq!(resp.headers()["Content-Type"], "application/json");
+/// ```
+pub fn json<T: Serialize + ?Sized>(value: &T) -> crate::Response {
+    match serde_json::to_vec(value) {
+        Ok(body) => {
+            let mut resp = hyper::Response::new(full(body));
+            resp.headers_mut().insert(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("application/json"),
+            );
+            resp
+        }
+
+        Err(_) => hyper::Response::builder()
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+            .body(full("Internal Server Error"))
+            .unwrap(),
+    }
+}
+
+impl IntoResponse for Value {
+    fn into_response(self) -> crate::Response {
+        json(&self)
+    }
+}
+
+/// An error reading a JSON request body with [`read_json`].
+///
+/// Responds with `413 Payload Too Large` for bodies over the router's
+/// [`max_body_size`](crate::Router::max_body_size), and `400 Bad Request`
+/// otherwise.
+#[derive(Debug)]
+pub enum JsonError {
+    /// The request body couldn't be read.
+    Body(hyper::Error),
+    /// The request body exceeds the router's `max_body_size`.
+    TooLarge,
+    /// The request body isn't valid JSON for the expected type.
+    Parse(serde_json::Error),
+}
+
+impl Display for JsonError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Body(err) => write!(f, "failed to read request body: {}", err),
+            Self::TooLarge => f.write_str("request body is too large"),
+            Self::Parse(err) => write!(f, "malformed JSON: {}", err),
+        }
+    }
+}
+
+impl Error for JsonError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Body(err) => Some(err),
+            Self::Parse(err) => Some(err),
+            Self::TooLarge => None,
+        }
+    }
+}
+
+impl IntoResponse for JsonError {
+    fn into_response(self) -> crate::Response {
+        body_error_response(matches!(self, Self::TooLarge), &self)
+    }
+}
+
+impl From<LimitedBodyError> for JsonError {
+    fn from(err: LimitedBodyError) -> Self {
+        match err {
+            LimitedBodyError::Body(err) => Self::Body(err),
+            LimitedBodyError::TooLarge => Self::TooLarge,
+        }
+    }
+}
+
+/// Collects the body of `req` and deserializes it from JSON.
+///
+/// Stops with [`JsonError::TooLarge`] once the body exceeds the router's
+/// [`max_body_size`](crate::Router::max_body_size), if set.
+///
+/// # Example
+///
+/// ```
+/// use radmin_router::{IntoResponse, Route, json, path, read_json};
+/// use serde_json::Value;
+///
+/// Route::<()>::new(path!("/echo")).post(|req, _| {
+///     Box::pin(async move {
+///         Ok(match read_json::<Value>(req).await {
+///             Ok(value) => json(&value),
+///             Err(err) => err.into_response(),
+///         })
+///     })
+/// });
+/// ```
+pub async fn read_json<T: DeserializeOwned>(req: Request<Incoming>) -> Result<T, JsonError> {
+    let buf = collect_limited(req).await?;
+    serde_json::from_slice(&buf).map_err(JsonError::Parse)
+}
+
+/// Streams `items` as newline-delimited JSON (`application/x-ndjson`),
+/// serializing each item as it's produced.
+///
+/// If an item fails to serialize, a final `{"error": "..."}` line is sent in
+/// its place and the stream ends, since the response status has already been
+/// sent.
+///
+/// # Example
+///
+/// ```
+/// use radmin_router::ndjson;
+///
+/// let resp = ndjson(futures::stream::iter(vec![1, 2, 3]));
+/// assert_eq!(resp.headers()["Content-Type"], "application/x-ndjson");
+/// ```
+pub fn ndjson<S>(items: S) -> crate::Response
+where
+    S: Stream + Send + Sync + 'static,
+    S::Item: Serialize,
+{
+    let lines = items.scan(false, |failed, item| {
+        if *failed {
+            return future::ready(None);
+        }
+
+        let line = serde_json::to_vec(&item).unwrap_or_else(|err| {
+            *failed = true;
+            serde_json::to_vec(&serde_json::json!({ "error": err.to_string() }))
+                .expect("error objects always serialize")
+        });
+
+        future::ready(Some(line))
+    });
+    let frames = lines.map(|mut line| {
+        line.push(b'\n');
+        Ok::<_, crate::BodyError>(Frame::data(Bytes::from(line)))
+    });
+
+    let mut resp = hyper::Response::new(BodyExt::boxed(StreamBody::new(frames)));
+    resp.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/x-ndjson"),
+    );
+    resp
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::send;
+    use crate::{Route, Router, path};
+    use serde::Serializer;
+
+    #[derive(Serialize)]
+    struct Row {
+        id: u32,
+        name: &'static str,
+    }
+
+    async fn lines(resp: crate::Response) -> Vec<Value> {
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        let body = std::str::from_utf8(&body).unwrap();
+
+        assert!(body.ends_with('\n'));
+        body.lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn streams_lines() {
+        let rows = vec![
+            Row { id: 1, name: "a" },
+            Row { id: 2, name: "b" },
+            Row { id: 3, name: "c" },
+        ];
+        let resp = ndjson(futures::stream::iter(rows));
+
+        assert_eq!(resp.headers()[header::CONTENT_TYPE], "application/x-ndjson");
+        let lines = lines(resp).await;
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1]["id"], 2);
+        assert_eq!(lines[2]["name"], "c");
+    }
+
+    #[tokio::test]
+    async fn serialization_error_ends_stream() {
+        struct Item(bool);
+
+        impl Serialize for Item {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                if self.0 {
+                    serializer.serialize_bool(true)
+                } else {
+                    Err(serde::ser::Error::custom("unserializable"))
+                }
+            }
+        }
+
+        let items = vec![Item(true), Item(false), Item(true)];
+        let lines = lines(ndjson(futures::stream::iter(items))).await;
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], Value::Bool(true));
+        assert_eq!(lines[1]["error"], "unserializable");
+    }
+
+    #[tokio::test]
+    async fn value_into_response() {
+        let router = Router::<()>::default().register(Route::new(path!("/")).get(|_, _| {
+            Box::pin(async {
+                Ok(serde_json::json!({ "id": 1, "tags": ["a", "b"] }).into_response())
+            })
+        }));
+
+        let resp = send(
+            router,
+            hyper::Request::get("/").body(Default::default()).unwrap(),
+        )
+        .await;
+        assert_eq!(resp.headers()[header::CONTENT_TYPE], "application/json");
+        let body: Value = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(body, serde_json::json!({ "id": 1, "tags": ["a", "b"] }));
+    }
+
+    #[derive(Debug, PartialEq, Serialize, serde::Deserialize)]
+    struct User {
+        id: u32,
+        name: String,
+    }
+
+    fn echo_router() -> Router<()> {
+        Router::<()>::default().register(Route::new(path!("/users")).post(|req, _| {
+            Box::pin(async move {
+                Ok(match read_json::<User>(req).await {
+                    Ok(user) => json(&user),
+                    Err(err) => err.into_response(),
+                })
+            })
+        }))
+    }
+
+    #[tokio::test]
+    async fn round_trips_json() {
+        let user = User {
+            id: 7,
+            name: String::from("radmin"),
+        };
+        let req = hyper::Request::post("/users")
+            .body(serde_json::to_vec(&user).unwrap().into())
+            .unwrap();
+        let resp = send(echo_router(), req).await;
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()[header::CONTENT_TYPE], "application/json");
+        assert_eq!(serde_json::from_slice::<User>(resp.body()).unwrap(), user);
+    }
+
+    #[tokio::test]
+    async fn malformed_json() {
+        for body in [r#"{"id": 7"#, r#"{"id": "seven", "name": "radmin"}"#] {
+            let req = hyper::Request::post("/users").body(body.into()).unwrap();
+            let resp = send(echo_router(), req).await;
+
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[tokio::test]
+    async fn json_too_large() {
+        let mut router = echo_router();
+        router.max_body_size(16);
+        let router = std::sync::Arc::new(router);
+
+        // Without a `Content-Length`, the limit is only caught while reading.
+        let req = hyper::Request::post("/users")
+            .header(header::TRANSFER_ENCODING, "chunked")
+            .body(r#"{"id": 7, "name": "radmin"}"#.into())
+            .unwrap();
+        let resp = send(router.clone(), req).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let req = hyper::Request::post("/users")
+            .header(header::TRANSFER_ENCODING, "chunked")
+            .body(r#"{"id": 7}"#.into())
+            .unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn json_serialization_error() {
+        let map = std::collections::HashMap::from([((1, 2), 3)]);
+        assert_eq!(json(&map).status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
@@ -1,23 +1,81 @@
 use bytes::Bytes;
 use http_body_util::combinators::BoxBody;
 
+#[cfg(feature = "logging")]
+mod access_log;
+mod body;
+#[cfg(feature = "charset")]
+mod charset;
 mod context;
+mod cors;
+mod encoding;
+mod error_page;
+mod extract;
+#[cfg(feature = "form")]
+mod form;
+#[cfg(any(feature = "fs", feature = "util"))]
+mod hash;
+#[cfg(feature = "json")]
+mod json;
+mod middleware;
+#[cfg(feature = "multipart")]
+mod multipart;
 mod node;
 mod path;
+mod response;
 mod route;
 mod router;
 mod segment;
+#[cfg(feature = "fs")]
+mod serve_dir;
+#[cfg(feature = "sse")]
+mod sse;
+#[cfg(test)]
+mod testing;
+#[cfg(feature = "tls")]
+mod tls;
 #[cfg(feature = "util")]
 mod util;
+#[cfg(feature = "ws")]
+mod ws;
 
+#[cfg(feature = "logging")]
+pub use access_log::*;
+#[cfg(feature = "charset")]
+pub use charset::*;
 pub use context::*;
+pub use cors::*;
+pub use error_page::*;
+pub use extract::*;
+#[cfg(feature = "form")]
+pub use form::*;
+#[cfg(feature = "json")]
+pub use json::*;
 pub use macros;
+pub use middleware::*;
+#[cfg(feature = "multipart")]
+pub use multipart::*;
 pub use path::*;
+pub use response::*;
 pub use route::*;
 pub use router::*;
 pub use segment::*;
+#[cfg(feature = "fs")]
+pub use serve_dir::*;
+#[cfg(feature = "sse")]
+pub use sse::*;
+#[cfg(feature = "tls")]
+pub use tls::*;
 #[cfg(feature = "util")]
 pub use util::*;
+#[cfg(feature = "uuid")]
+pub use uuid;
+#[cfg(feature = "ws")]
+pub use ws::*;
 
-pub type Response = hyper::Response<BoxBody<Bytes, hyper::Error>>;
-pub type Result = std::result::Result<Response, hyper::Error>;
+/// The error type of handlers and response bodies. Boxed, so that streaming
+/// bodies can fail with their natural errors, such as the `io::Error`s of a
+/// file; `hyper::Error`s convert into it with `?`.
+pub type BodyError = Box<dyn std::error::Error + Send + Sync>;
+pub type Response = hyper::Response<BoxBody<Bytes, BodyError>>;
+pub type Result = std::result::Result<Response, BodyError>;
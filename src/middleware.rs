@@ -0,0 +1,767 @@
+use crate::body::full;
+use crate::context::Context;
+use bytes::Bytes;
+use futures::future::{self, BoxFuture};
+use hyper::body::Incoming;
+use hyper::header::{self, HeaderMap, HeaderName, HeaderValue};
+use hyper::{Method, Request, StatusCode};
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+use std::hash::{BuildHasher, RandomState};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// A middleware wrapping the handler chain of every matched route.
+///
+/// Middleware receives the request, its context and a [`Next`] which runs the
+/// remaining middleware and, finally, the route's handler. The response
+/// returned by [`Next::run`] can be inspected and modified before it's
+/// returned, e.g. depending on its status:
+///
+/// ```
+/// use hyper::StatusCode;
+/// use hyper::header::{self, HeaderValue};
+/// use radmin_router::Router;
+///
+/// Router::<()>::default()
+///     .layer(|req, ctx, next| {
+///         Box::pin(async move {
+///             let mut resp = next.run(req, ctx).await?;
+///             if resp.status() == StatusCode::TOO_MANY_REQUESTS {
+///                 resp.headers_mut()
+///                     .insert(header::RETRY_AFTER, HeaderValue::from_static("60"));
+///             }
+///             Ok(resp)
+///         })
+///     });
+/// ```
+pub type Middleware<Extra> = Arc<
+    dyn Fn(Request<Incoming>, Context<Extra>, Next<Extra>) -> BoxFuture<'static, crate::Result>
+        + Send
+        + Sync,
+>;
+
+/// The innermost handler of a middleware chain.
+pub(crate) type Endpoint<Extra> = Arc<
+    dyn Fn(Request<Incoming>, Context<Extra>) -> BoxFuture<'static, crate::Result> + Send + Sync,
+>;
+
+/// The remainder of a middleware chain.
+pub struct Next<Extra> {
+    layers: Arc<Vec<Middleware<Extra>>>,
+    idx: usize,
+    endpoint: Endpoint<Extra>,
+}
+
+impl<Extra> Next<Extra> {
+    pub(crate) fn new(layers: Arc<Vec<Middleware<Extra>>>, endpoint: Endpoint<Extra>) -> Self {
+        Self {
+            layers,
+            idx: 0,
+            endpoint,
+        }
+    }
+
+    /// Runs the next middleware in the chain, or the handler if none remain.
+    pub fn run(
+        self,
+        req: Request<Incoming>,
+        ctx: Context<Extra>,
+    ) -> BoxFuture<'static, crate::Result> {
+        match self.layers.get(self.idx) {
+            Some(layer) => {
+                let layer = Arc::clone(layer);
+                let next = Self {
+                    layers: self.layers,
+                    idx: self.idx + 1,
+                    endpoint: self.endpoint,
+                };
+
+                layer(req, ctx, next)
+            }
+
+            None => (self.endpoint)(req, ctx),
+        }
+    }
+}
+
+/// A configurable set of security-related response headers.
+///
+/// Each header is only added when the handler didn't set it already.
+#[derive(Clone, Debug)]
+pub struct SecurityHeaders {
+    headers: Vec<(HeaderName, HeaderValue)>,
+}
+
+impl Default for SecurityHeaders {
+    fn default() -> Self {
+        Self {
+            headers: vec![
+                (
+                    header::X_CONTENT_TYPE_OPTIONS,
+                    HeaderValue::from_static("nosniff"),
+                ),
+                (header::X_FRAME_OPTIONS, HeaderValue::from_static("DENY")),
+                (
+                    header::REFERRER_POLICY,
+                    HeaderValue::from_static("strict-origin-when-cross-origin"),
+                ),
+                (
+                    header::CONTENT_SECURITY_POLICY,
+                    HeaderValue::from_static("default-src 'self'"),
+                ),
+            ],
+        }
+    }
+}
+
+impl SecurityHeaders {
+    /// Sets (or replaces) a header, or removes it from the set when `value` is `None`.
+    pub fn header(mut self, name: HeaderName, value: Option<HeaderValue>) -> Self {
+        self.headers.retain(|(existing, _)| *existing != name);
+
+        if let Some(value) = value {
+            self.headers.push((name, value));
+        }
+
+        self
+    }
+
+    /// Sets the `X-Frame-Options` value, or disables the header.
+    pub fn frame_options(self, value: Option<&'static str>) -> Self {
+        self.header(header::X_FRAME_OPTIONS, value.map(HeaderValue::from_static))
+    }
+
+    /// Sets the `Referrer-Policy` value, or disables the header.
+    pub fn referrer_policy(self, value: Option<&'static str>) -> Self {
+        self.header(header::REFERRER_POLICY, value.map(HeaderValue::from_static))
+    }
+
+    /// Sets the `Content-Security-Policy` value, or disables the header.
+    pub fn content_security_policy(self, value: Option<&'static str>) -> Self {
+        self.header(
+            header::CONTENT_SECURITY_POLICY,
+            value.map(HeaderValue::from_static),
+        )
+    }
+
+    /// Builds a middleware for use with [`Router::layer`](crate::Router::layer).
+    pub fn build<Extra>(
+        self,
+    ) -> impl Fn(Request<Incoming>, Context<Extra>, Next<Extra>) -> BoxFuture<'static, crate::Result>
+    + Send
+    + Sync
+    + 'static
+    where
+        Extra: Send + Sync + 'static,
+    {
+        let headers = Arc::new(self.headers);
+
+        move |req, ctx, next| {
+            let headers = Arc::clone(&headers);
+
+            Box::pin(async move {
+                let mut resp = next.run(req, ctx).await?;
+
+                for (name, value) in headers.iter() {
+                    resp.headers_mut()
+                        .entry(name)
+                        .or_insert_with(|| value.clone());
+                }
+
+                Ok(resp)
+            })
+        }
+    }
+}
+
+/// A middleware adding the default [`SecurityHeaders`] to every response.
+///
+/// # Example
+///
+/// ```
+/// use radmin_router::{Router, security_headers};
+///
+/// Router::<()>::default()
+///     .layer(security_headers());
+/// ```
+pub fn security_headers<Extra>()
+-> impl Fn(Request<Incoming>, Context<Extra>, Next<Extra>) -> BoxFuture<'static, crate::Result>
++ Send
++ Sync
++ 'static
+where
+    Extra: Send + Sync + 'static,
+{
+    SecurityHeaders::default().build()
+}
+
+/// The ID assigned to a request by the [`request_id`] middleware, available
+/// to handlers through [`Context::extensions`] and on the response's
+/// extensions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequestId(pub HeaderValue);
+
+/// A middleware tagging each request with an `X-Request-Id`, which is stored
+/// in [`Context::request_id`] and echoed on the response.
+///
+/// An ID sent by the client is kept; otherwise a random version 4 UUID is
+/// generated. With the `logging` feature, the ID is included in the
+/// router's request event.
+///
+/// # Example
+///
+/// ```
+/// use radmin_router::{Router, request_id};
+///
+/// Router::<()>::default()
+///     .layer(request_id());
+/// ```
+pub fn request_id<Extra>()
+-> impl Fn(Request<Incoming>, Context<Extra>, Next<Extra>) -> BoxFuture<'static, crate::Result>
++ Send
++ Sync
++ 'static
+where
+    Extra: Send + Sync + 'static,
+{
+    request_id_header(HeaderName::from_static("x-request-id"))
+}
+
+/// Like [`request_id`], reading and echoing the ID in the header `name`.
+///
+/// # Example
+///
+/// ```
+/// use hyper::header::HeaderName;
+/// use radmin_router::{Router, request_id_header};
+///
+/// Router::<()>::default()
+///     .layer(request_id_header(HeaderName::from_static("x-correlation-id")));
+/// ```
+pub fn request_id_header<Extra>(
+    name: HeaderName,
+) -> impl Fn(Request<Incoming>, Context<Extra>, Next<Extra>) -> BoxFuture<'static, crate::Result>
++ Send
++ Sync
++ 'static
+where
+    Extra: Send + Sync + 'static,
+{
+    move |req, mut ctx, next| {
+        let id = req
+            .headers()
+            .get(&name)
+            .cloned()
+            .unwrap_or_else(random_uuid);
+        ctx.request_id = id.to_str().ok().map(str::to_string);
+        ctx.extensions.insert(RequestId(id.clone()));
+        let name = name.clone();
+
+        Box::pin(async move {
+            let mut resp = next.run(req, ctx).await?;
+            resp.headers_mut().insert(name, id.clone());
+            resp.extensions_mut().insert(RequestId(id));
+            Ok(resp)
+        })
+    }
+}
+
+/// Generates a random version 4 UUID. Unique enough to tell requests apart,
+/// but not suitable as a secret.
+fn random_uuid() -> HeaderValue {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    let count = NEXT.fetch_add(1, Ordering::Relaxed);
+
+    let state = RandomState::new();
+    let mut bits = [0u8; 16];
+    for (half, chunk) in bits.chunks_mut(8).enumerate() {
+        chunk.copy_from_slice(&state.hash_one((count, half)).to_le_bytes());
+    }
+    bits[6] = (bits[6] & 0x0f) | 0x40;
+    bits[8] = (bits[8] & 0x3f) | 0x80;
+
+    let bits = u128::from_be_bytes(bits);
+    let uuid = format!(
+        "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+        bits >> 96,
+        (bits >> 80) & 0xffff,
+        (bits >> 64) & 0xffff,
+        (bits >> 48) & 0xffff,
+        bits & 0xffff_ffff_ffff
+    );
+    HeaderValue::from_str(&uuid).unwrap()
+}
+
+/// Answers GET and HEAD requests with `304 Not Modified` when the
+/// response's `ETag` matches the request's `If-None-Match`, e.g. as set with
+/// `with_etag`.
+///
+/// Tags are compared weakly, so `W/"1"` matches `"1"`, and `*` matches any
+/// tag. The `304` keeps the response's caching headers but has no body.
+///
+/// # Example
+///
+/// ```
+/// use radmin_router::{Router, conditional_get};
+///
+/// Router::<()>::default()
+///     .layer(conditional_get());
+/// ```
+pub fn conditional_get<Extra>()
+-> impl Fn(Request<Incoming>, Context<Extra>, Next<Extra>) -> BoxFuture<'static, crate::Result>
++ Send
++ Sync
++ 'static
+where
+    Extra: Send + Sync + 'static,
+{
+    /// Headers a `304` carries over from the response it replaces.
+    const KEPT: [HeaderName; 6] = [
+        header::CACHE_CONTROL,
+        header::CONTENT_LOCATION,
+        header::DATE,
+        header::ETAG,
+        header::EXPIRES,
+        header::VARY,
+    ];
+
+    |req, ctx, next| {
+        let conditional = matches!(*req.method(), Method::GET | Method::HEAD);
+        let headers = Arc::clone(&ctx.headers);
+
+        Box::pin(async move {
+            let resp = next.run(req, ctx).await?;
+            let matches = resp
+                .headers()
+                .get(header::ETAG)
+                .is_some_and(|etag| if_none_match(&headers, etag));
+            if !conditional || !resp.status().is_success() || !matches {
+                return Ok(resp);
+            }
+
+            let mut not_modified = hyper::Response::new(full(Bytes::new()));
+            *not_modified.status_mut() = StatusCode::NOT_MODIFIED;
+            for name in KEPT {
+                for value in resp.headers().get_all(&name) {
+                    not_modified.headers_mut().append(&name, value.clone());
+                }
+            }
+            Ok(not_modified)
+        })
+    }
+}
+
+/// Returns whether `If-None-Match` matches `etag`, using the weak comparison.
+pub(crate) fn if_none_match(headers: &HeaderMap, etag: &HeaderValue) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let etag = opaque(etag.to_str().unwrap_or_default());
+
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|tag| tag.trim() == "*" || opaque(tag) == etag)
+}
+
+/// The key a [`RateLimit`] counts a request's client by.
+type RateLimitKey<Extra> = Arc<dyn Fn(&Context<Extra>) -> String + Send + Sync>;
+
+/// A fixed-window rate limit, answering requests over the limit with
+/// `429 Too Many Requests` and a `Retry-After` header.
+///
+/// Requests are counted per key, by default the client's IP address from
+/// [`Context::remote_addr`]. Requests without an address share one count.
+/// Behind a proxy, set [`key`](Self::key) to identify clients another way.
+///
+/// # Example
+///
+/// ```
+/// use radmin_router::{RateLimit, Router};
+/// use std::time::Duration;
+///
+/// Router::<()>::default()
+///     .layer(
+///         RateLimit::new(100, Duration::from_secs(60))
+///             .key(|ctx| {
+///                 ctx.header("x-api-key")
+///                     .and_then(|key| key.to_str().ok())
+///                     .unwrap_or_default()
+///                     .to_string()
+///             })
+///             .build(),
+///     );
+/// ```
+pub struct RateLimit<Extra> {
+    requests: u32,
+    per: Duration,
+    key: RateLimitKey<Extra>,
+}
+
+impl<Extra> Clone for RateLimit<Extra> {
+    fn clone(&self) -> Self {
+        Self {
+            requests: self.requests,
+            per: self.per,
+            key: Arc::clone(&self.key),
+        }
+    }
+}
+
+impl<Extra> Debug for RateLimit<Extra> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RateLimit")
+            .field("requests", &self.requests)
+            .field("per", &self.per)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<Extra> RateLimit<Extra> {
+    /// Allows `requests` requests per key in each window of length `per`.
+    pub fn new(requests: u32, per: Duration) -> Self {
+        Self {
+            requests,
+            per,
+            key: Arc::new(|ctx| {
+                ctx.remote_addr
+                    .map(|addr| addr.ip().to_string())
+                    .unwrap_or_default()
+            }),
+        }
+    }
+
+    /// Sets the key requests are counted by, e.g. an API key header.
+    pub fn key<K>(mut self, key: K) -> Self
+    where
+        K: Fn(&Context<Extra>) -> String + Send + Sync + 'static,
+    {
+        self.key = Arc::new(key);
+        self
+    }
+
+    /// Builds a middleware for use with [`Router::layer`](crate::Router::layer).
+    ///
+    /// Counters are shared by every connection the router serves.
+    pub fn build(
+        self,
+    ) -> impl Fn(Request<Incoming>, Context<Extra>, Next<Extra>) -> BoxFuture<'static, crate::Result>
+    + Send
+    + Sync
+    + 'static
+    where
+        Extra: Send + Sync + 'static,
+    {
+        // The start of each key's current window and the requests made in it,
+        // and when expired windows were last dropped.
+        let windows = Arc::new(Mutex::new((
+            HashMap::<String, (Instant, u32)>::new(),
+            Instant::now(),
+        )));
+
+        move |req, ctx, next| {
+            let key = (self.key)(&ctx);
+            let now = Instant::now();
+
+            let retry_after = {
+                let mut guard = windows.lock().unwrap_or_else(PoisonError::into_inner);
+                let (windows, last_sweep) = &mut *guard;
+                // Sweeping at most once per window keeps a flood of distinct
+                // clients from making every request scan them all.
+                if windows.len() >= EXPIRED_WINDOWS_SWEEP
+                    && now.duration_since(*last_sweep) >= self.per
+                {
+                    windows.retain(|_, (start, _)| now.duration_since(*start) < self.per);
+                    *last_sweep = now;
+                }
+
+                let (start, count) = windows.entry(key).or_insert((now, 0));
+                if now.duration_since(*start) >= self.per {
+                    *start = now;
+                    *count = 0;
+                }
+
+                if *count < self.requests {
+                    *count += 1;
+                    None
+                } else {
+                    Some(self.per - now.duration_since(*start))
+                }
+            };
+
+            match retry_after {
+                None => next.run(req, ctx),
+                Some(retry_after) => {
+                    // Whole seconds, rounded up so clients don't retry early.
+                    let secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+                    let resp = hyper::Response::builder()
+                        .status(StatusCode::TOO_MANY_REQUESTS)
+                        .header(header::RETRY_AFTER, secs.max(1))
+                        .body(full("Too Many Requests"))
+                        .unwrap();
+
+                    Box::pin(future::ready(Ok(resp)))
+                }
+            }
+        }
+    }
+}
+
+/// How many windows a [`RateLimit`] tracks before dropping expired ones, at
+/// most once per window length.
+const EXPIRED_WINDOWS_SWEEP: usize = 1024;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::send;
+    use crate::{Route, Router, path};
+
+    #[tokio::test]
+    async fn adds_security_headers() {
+        let mut router = Router::<()>::default().register(
+            Route::new(path!("/"))
+                .get(|_, _| Box::pin(async { Ok(hyper::Response::new(crate::full("OK"))) })),
+        );
+        router.layer(security_headers());
+
+        let resp = send(router, Request::get("/").body(Default::default()).unwrap()).await;
+        let headers = resp.headers();
+        assert_eq!(headers[header::X_CONTENT_TYPE_OPTIONS], "nosniff");
+        assert_eq!(headers[header::X_FRAME_OPTIONS], "DENY");
+        assert!(headers.contains_key(header::REFERRER_POLICY));
+        assert!(headers.contains_key(header::CONTENT_SECURITY_POLICY));
+    }
+
+    #[tokio::test]
+    async fn preserves_handler_headers() {
+        let mut router = Router::<()>::default().register(Route::new(path!("/")).get(|_, _| {
+            Box::pin(async {
+                Ok(hyper::Response::builder()
+                    .header(header::X_FRAME_OPTIONS, "SAMEORIGIN")
+                    .body(crate::full("OK"))
+                    .unwrap())
+            })
+        }));
+        router.layer(SecurityHeaders::default().referrer_policy(None).build());
+
+        let resp = send(router, Request::get("/").body(Default::default()).unwrap()).await;
+        let headers = resp.headers();
+        assert_eq!(headers[header::X_FRAME_OPTIONS], "SAMEORIGIN");
+        assert!(!headers.contains_key(header::REFERRER_POLICY));
+    }
+
+    #[tokio::test]
+    async fn conditional_on_status() {
+        use hyper::StatusCode;
+
+        let mut router = Router::<()>::default()
+            .register(
+                Route::new(path!("/ok"))
+                    .get(|_, _| Box::pin(async { Ok(hyper::Response::new(crate::full("OK"))) })),
+            )
+            .register(Route::new(path!("/busy")).get(|_, _| {
+                Box::pin(async {
+                    Ok(hyper::Response::builder()
+                        .status(StatusCode::SERVICE_UNAVAILABLE)
+                        .body(crate::full("Busy"))
+                        .unwrap())
+                })
+            }));
+        router.layer(|req, ctx, next| {
+            Box::pin(async move {
+                let mut resp = next.run(req, ctx).await?;
+                if resp.status() == StatusCode::SERVICE_UNAVAILABLE {
+                    resp.headers_mut()
+                        .insert(header::RETRY_AFTER, HeaderValue::from_static("30"));
+                }
+                Ok(resp)
+            })
+        });
+        let router = Arc::new(router);
+
+        let req = Request::get("/busy").body(Default::default()).unwrap();
+        let resp = send(Arc::clone(&router), req).await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(resp.headers()[header::RETRY_AFTER], "30");
+
+        let req = Request::get("/ok").body(Default::default()).unwrap();
+        let resp = send(router, req).await;
+        assert!(!resp.headers().contains_key(header::RETRY_AFTER));
+    }
+
+    #[tokio::test]
+    async fn adds_request_id() {
+        let router = || {
+            let mut router =
+                Router::<()>::default().register(Route::new(path!("/")).get(|_, ctx| {
+                    let RequestId(id) = ctx.extensions.get::<RequestId>().unwrap().clone();
+                    Box::pin(async move {
+                        Ok(hyper::Response::new(crate::full(id.as_bytes().to_vec())))
+                    })
+                }));
+            router.layer(request_id());
+            router
+        };
+
+        let resp = send(
+            router(),
+            Request::get("/").body(Default::default()).unwrap(),
+        )
+        .await;
+        let id = &resp.headers()["x-request-id"];
+        assert!(!id.is_empty());
+        assert_eq!(resp.body().as_ref(), id.as_bytes());
+
+        let req = Request::get("/")
+            .header("x-request-id", "abc-123")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(router(), req).await;
+        assert_eq!(resp.headers()["x-request-id"], "abc-123");
+        assert_eq!(resp.body().as_ref(), b"abc-123");
+    }
+
+    #[tokio::test]
+    async fn generates_uuid_request_ids_in_custom_header() {
+        let mut router = Router::<()>::default().register(Route::new(path!("/")).get(|_, ctx| {
+            let id = ctx.request_id.clone().unwrap();
+            Box::pin(async move { Ok(hyper::Response::new(crate::full(id))) })
+        }));
+        router.layer(request_id_header(HeaderName::from_static(
+            "x-correlation-id",
+        )));
+        let router = Arc::new(router);
+
+        let req = || Request::get("/").body(Default::default()).unwrap();
+        let first = send(Arc::clone(&router), req()).await;
+        let second = send(Arc::clone(&router), req()).await;
+        for resp in [&first, &second] {
+            let id = resp.headers()["x-correlation-id"].to_str().unwrap();
+            assert_eq!(resp.body().as_ref(), id.as_bytes());
+            assert_eq!(id.len(), 36);
+            assert_eq!(id.as_bytes()[14], b'4');
+            assert!(matches!(id.as_bytes()[19], b'8' | b'9' | b'a' | b'b'));
+            assert!(!resp.headers().contains_key("x-request-id"));
+        }
+        assert_ne!(first.body(), second.body());
+
+        let req = Request::get("/")
+            .header("x-correlation-id", "abc-123")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.headers()["x-correlation-id"], "abc-123");
+        assert_eq!(resp.body().as_ref(), b"abc-123");
+    }
+
+    #[tokio::test]
+    async fn layers_wrap_method_not_allowed_in_order() {
+        fn trace(
+            tag: &'static str,
+        ) -> impl Fn(
+            Request<Incoming>,
+            Context<()>,
+            Next<()>,
+        ) -> BoxFuture<'static, crate::Result>
+        + Send
+        + Sync
+        + 'static {
+            move |req, ctx, next| {
+                Box::pin(async move {
+                    let mut resp = next.run(req, ctx).await?;
+                    resp.headers_mut()
+                        .append("x-trace", HeaderValue::from_static(tag));
+                    Ok(resp)
+                })
+            }
+        }
+
+        let mut router = Router::<()>::default().register(
+            Route::new(path!("/"))
+                .get(|_, _| Box::pin(async { Ok(hyper::Response::new(crate::full("OK"))) })),
+        );
+        router.layer(trace("outer")).layer(trace("inner"));
+
+        let resp = send(router, Request::post("/").body(Default::default()).unwrap()).await;
+        assert_eq!(resp.status(), hyper::StatusCode::METHOD_NOT_ALLOWED);
+        let trace = resp.headers().get_all("x-trace").iter().collect::<Vec<_>>();
+        assert_eq!(trace, vec!["inner", "outer"]);
+    }
+
+    #[tokio::test]
+    async fn rate_limits_by_key() {
+        let mut router = Router::<()>::default().register(
+            Route::new(path!("/"))
+                .get(|_, _| Box::pin(async { Ok(hyper::Response::new(crate::full("OK"))) })),
+        );
+        router.layer(
+            RateLimit::new(3, Duration::from_secs(60))
+                .key(|ctx| {
+                    ctx.header("x-api-key")
+                        .and_then(|key| key.to_str().ok())
+                        .unwrap_or_default()
+                        .to_string()
+                })
+                .build(),
+        );
+        let router = Arc::new(router);
+        let req = |key: &'static str| {
+            Request::get("/")
+                .header("x-api-key", key)
+                .body(Default::default())
+                .unwrap()
+        };
+
+        for _ in 0..3 {
+            let resp = send(Arc::clone(&router), req("alice")).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+        }
+        let resp = send(Arc::clone(&router), req("alice")).await;
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        let retry_after = resp.headers()[header::RETRY_AFTER].to_str().unwrap();
+        assert!((1..=60).contains(&retry_after.parse::<u64>().unwrap()));
+
+        let resp = send(router, req("bob")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn answers_matching_etags_with_not_modified() {
+        let mut router = Router::<()>::default().register(Route::new(path!("/")).get(|_, _| {
+            Box::pin(async {
+                let resp = hyper::Response::builder()
+                    .header(header::CACHE_CONTROL, "max-age=60")
+                    .body(crate::full("hello"))
+                    .unwrap();
+                Ok(crate::with_etag(resp, "v1"))
+            })
+        }));
+        router.layer(conditional_get());
+        let router = Arc::new(router);
+        let req = |if_none_match: Option<&'static str>| {
+            let mut req = Request::get("/");
+            if let Some(tags) = if_none_match {
+                req = req.header(header::IF_NONE_MATCH, tags);
+            }
+            req.body(Default::default()).unwrap()
+        };
+
+        for tags in [r#""v1""#, r#"W/"v1""#, r#""v0", "v1""#, "*"] {
+            let resp = send(Arc::clone(&router), req(Some(tags))).await;
+            assert_eq!(resp.status(), StatusCode::NOT_MODIFIED, "{tags}");
+            assert_eq!(resp.headers()[header::ETAG], r#""v1""#);
+            assert_eq!(resp.headers()[header::CACHE_CONTROL], "max-age=60");
+            assert!(resp.body().is_empty());
+        }
+
+        for tags in [Some(r#""v2""#), Some(r#"W/"v11""#), None] {
+            let resp = send(Arc::clone(&router), req(tags)).await;
+            assert_eq!(resp.status(), StatusCode::OK, "{tags:?}");
+            assert_eq!(resp.headers()[header::ETAG], r#""v1""#);
+            assert_eq!(resp.body().as_ref(), b"hello");
+        }
+    }
+}
@@ -0,0 +1,349 @@
+use crate::body::{LimitedBodyError, body_error_response, collect_limited};
+use crate::response::IntoResponse;
+use bytes::Bytes;
+use hyper::Request;
+use hyper::body::Incoming;
+use hyper::header::{self, HeaderMap, HeaderName, HeaderValue};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+/// A part of a `multipart/form-data` body, read with [`read_multipart`].
+#[derive(Clone, Debug)]
+pub struct Part {
+    /// The name of the form field.
+    pub name: String,
+    /// The name of the uploaded file, for file fields.
+    pub filename: Option<String>,
+    /// The part's `Content-Type`, if given.
+    pub content_type: Option<String>,
+    /// The part's headers, including `Content-Disposition`.
+    pub headers: HeaderMap,
+    /// The part's contents.
+    pub body: Bytes,
+}
+
+/// An error reading a `multipart/form-data` request body with
+/// [`read_multipart`].
+///
+/// Responds with `413 Payload Too Large` for bodies over the router's
+/// [`max_body_size`](crate::Router::max_body_size), and `400 Bad Request`
+/// otherwise.
+#[derive(Debug)]
+pub enum MultipartError {
+    /// The request body couldn't be read.
+    Body(hyper::Error),
+    /// The request body exceeds the router's `max_body_size`.
+    TooLarge,
+    /// The request isn't `multipart/form-data`, or has no boundary.
+    NotMultipart,
+    /// The body isn't valid `multipart/form-data`.
+    Malformed(&'static str),
+}
+
+impl Display for MultipartError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Body(err) => write!(f, "failed to read request body: {}", err),
+            Self::TooLarge => f.write_str("request body is too large"),
+            Self::NotMultipart => {
+                f.write_str("expected a multipart/form-data body with a boundary")
+            }
+            Self::Malformed(reason) => write!(f, "malformed multipart body: {}", reason),
+        }
+    }
+}
+
+impl Error for MultipartError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Body(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl IntoResponse for MultipartError {
+    fn into_response(self) -> crate::Response {
+        body_error_response(matches!(self, Self::TooLarge), &self)
+    }
+}
+
+impl From<LimitedBodyError> for MultipartError {
+    fn from(err: LimitedBodyError) -> Self {
+        match err {
+            LimitedBodyError::Body(err) => Self::Body(err),
+            LimitedBodyError::TooLarge => Self::TooLarge,
+        }
+    }
+}
+
+/// Collects the body of `req` and splits it into the parts of a
+/// `multipart/form-data` form, in order.
+///
+/// Stops with [`MultipartError::TooLarge`] once the body exceeds the
+/// router's [`max_body_size`](crate::Router::max_body_size), if set.
+///
+/// # Example
+///
+/// ```
+/// use hyper::Response;
+/// use radmin_router::{IntoResponse, Route, full, path, read_multipart};
+///
+/// Route::<()>::new(path!("/upload")).post(|req, _| {
+///     Box::pin(async move {
+///         Ok(match read_multipart(req).await {
+///             Ok(parts) => Response::new(full(format!("{} parts", parts.len()))),
+///             Err(err) => err.into_response(),
+///         })
+///     })
+/// });
+/// ```
+pub async fn read_multipart(req: Request<Incoming>) -> Result<Vec<Part>, MultipartError> {
+    let boundary = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(boundary)
+        .ok_or(MultipartError::NotMultipart)?;
+    let buf = collect_limited(req).await?;
+    parse(buf, &boundary)
+}
+
+/// Returns the boundary of a `multipart/form-data` content type.
+fn boundary(content_type: &str) -> Option<String> {
+    let (mime, params) = content_type.split_once(';')?;
+    if !mime.trim().eq_ignore_ascii_case("multipart/form-data") {
+        return None;
+    }
+
+    parse_params(params)
+        .into_iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("boundary"))
+        .map(|(_, value)| value)
+        .filter(|boundary| (1..=70).contains(&boundary.len()))
+}
+
+/// Splits `body` on `--boundary` delimiters into parts.
+fn parse(body: Bytes, boundary: &str) -> Result<Vec<Part>, MultipartError> {
+    let delimiter = format!("--{}", boundary);
+    let delimiter = delimiter.as_bytes();
+    // Delimiters after the first are preceded by the CRLF ending the
+    // previous part.
+    let next_delimiter = [b"\r\n", delimiter].concat();
+
+    // Anything before the first delimiter is a preamble to be ignored.
+    let mut pos = if body.starts_with(delimiter) {
+        delimiter.len()
+    } else {
+        find(&body, &next_delimiter).ok_or(MultipartError::Malformed("missing boundary"))?
+            + next_delimiter.len()
+    };
+
+    let mut parts = vec![];
+    loop {
+        let rest = &body[pos..];
+        if rest.starts_with(b"--") {
+            return Ok(parts);
+        }
+
+        // The delimiter line may end with whitespace before its CRLF.
+        let padding = rest
+            .iter()
+            .take_while(|c| **c == b' ' || **c == b'\t')
+            .count();
+        if !rest[padding..].starts_with(b"\r\n") {
+            return Err(MultipartError::Malformed("missing closing boundary"));
+        }
+        pos += padding + 2;
+
+        let rest = &body[pos..];
+        let headers_len = if rest.starts_with(b"\r\n") {
+            0
+        } else {
+            find(rest, b"\r\n\r\n").ok_or(MultipartError::Malformed("unterminated part headers"))?
+                + 2
+        };
+        let headers = parse_headers(&rest[..headers_len])?;
+        pos += headers_len + 2;
+
+        let len = find(&body[pos..], &next_delimiter)
+            .ok_or(MultipartError::Malformed("missing closing boundary"))?;
+        parts.push(part(headers, body.slice(pos..pos + len))?);
+        pos += len + next_delimiter.len();
+    }
+}
+
+/// Parses a part's CRLF-terminated header lines.
+fn parse_headers(raw: &[u8]) -> Result<HeaderMap, MultipartError> {
+    let mut headers = HeaderMap::new();
+    for line in raw.split(|c| *c == b'\n').filter(|line| !line.is_empty()) {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let colon = line
+            .iter()
+            .position(|c| *c == b':')
+            .ok_or(MultipartError::Malformed("invalid part header"))?;
+        let name = HeaderName::from_bytes(&line[..colon])
+            .map_err(|_| MultipartError::Malformed("invalid part header"))?;
+        let value = HeaderValue::from_bytes(line[colon + 1..].trim_ascii())
+            .map_err(|_| MultipartError::Malformed("invalid part header"))?;
+        headers.append(name, value);
+    }
+
+    Ok(headers)
+}
+
+/// Builds a part from its headers and contents, reading its field name from
+/// `Content-Disposition`.
+fn part(headers: HeaderMap, body: Bytes) -> Result<Part, MultipartError> {
+    let disposition = headers
+        .get(header::CONTENT_DISPOSITION)
+        .and_then(|value| value.to_str().ok())
+        .ok_or(MultipartError::Malformed(
+            "part without Content-Disposition",
+        ))?;
+    let (kind, params) = disposition.split_once(';').unwrap_or((disposition, ""));
+    if !kind.trim().eq_ignore_ascii_case("form-data") {
+        return Err(MultipartError::Malformed("part isn't form-data"));
+    }
+
+    let mut name = None;
+    let mut filename = None;
+    for (param, value) in parse_params(params) {
+        if param.eq_ignore_ascii_case("name") {
+            name.get_or_insert(value);
+        } else if param.eq_ignore_ascii_case("filename") {
+            filename.get_or_insert(value);
+        }
+    }
+
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+
+    Ok(Part {
+        name: name.ok_or(MultipartError::Malformed("part without a name"))?,
+        filename,
+        content_type,
+        headers,
+        body,
+    })
+}
+
+/// Parses `; `-separated `name=value` parameters, unquoting quoted values.
+fn parse_params(raw: &str) -> Vec<(String, String)> {
+    let mut params = vec![];
+    let mut chars = raw.chars().peekable();
+
+    loop {
+        while chars.next_if(|c| *c == ';' || c.is_whitespace()).is_some() {}
+        let name: String = chars.by_ref().take_while(|c| *c != '=').collect();
+        if name.is_empty() {
+            return params;
+        }
+
+        let mut value = String::new();
+        if chars.next_if_eq(&'"').is_some() {
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' => break,
+                    '\\' => value.extend(chars.next()),
+                    c => value.push(c),
+                }
+            }
+            while chars.next_if(|c| *c != ';').is_some() {}
+        } else {
+            while let Some(c) = chars.next_if(|c| *c != ';') {
+                value.push(c);
+            }
+        }
+
+        params.push((name.trim().to_string(), value.trim_end().to_string()));
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::body::full;
+    use crate::testing::send;
+    use crate::{Route, Router, path};
+    use http_body_util::Full;
+    use hyper::StatusCode;
+
+    const BODY: &[u8] = b"preamble\r\n\
+        --XyZ\r\n\
+        Content-Disposition: form-data; name=\"title\"\r\n\
+        \r\n\
+        Holiday photos\r\n\
+        --XyZ\r\n\
+        Content-Disposition: form-data; name=\"photo\"; filename=\"beach \\\"1\\\".png\"\r\n\
+        Content-Type: image/png\r\n\
+        \r\n\
+        \x89PNG\r\n--not the boundary\r\n\
+        --XyZ--\r\n";
+
+    fn router() -> Router<()> {
+        Router::<()>::default().register(Route::new(path!("/upload")).post(|req, _| {
+            Box::pin(async move {
+                let parts = match read_multipart(req).await {
+                    Ok(parts) => parts,
+                    Err(err) => return Ok(err.into_response()),
+                };
+
+                let summary = parts
+                    .iter()
+                    .map(|part| {
+                        format!(
+                            "{}|{:?}|{:?}|{:?}",
+                            part.name, part.filename, part.content_type, part.body
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                Ok(hyper::Response::new(full(summary)))
+            })
+        }))
+    }
+
+    fn upload(body: &'static [u8]) -> Request<Full<Bytes>> {
+        Request::post("/upload")
+            .header(
+                header::CONTENT_TYPE,
+                "multipart/form-data; boundary=\"XyZ\"",
+            )
+            .body(Full::new(Bytes::from_static(body)))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn reads_text_and_file_fields() {
+        let resp = send(router(), upload(BODY)).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.body(),
+            "title|None|None|b\"Holiday photos\"\n\
+             photo|Some(\"beach \\\"1\\\".png\")|Some(\"image/png\")|b\"\\x89PNG\\r\\n--not the boundary\""
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_bodies() {
+        let resp = send(router(), upload(b"--XyZ\r\n\r\nno disposition\r\n--XyZ--")).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let resp = send(router(), upload(&BODY[..BODY.len() - 9])).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let mut router = router();
+        router.max_body_size(64);
+        let resp = send(router, upload(BODY)).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
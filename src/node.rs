@@ -1,11 +1,41 @@
+use crate::encoding::percent_decode;
+use crate::middleware::Middleware;
+use crate::path::Path;
 use crate::route::Route;
-use crate::segment::Segment;
+use crate::router::{PanicHandler, RouteNotFoundHandler};
+use crate::segment::{Constraint, PatternPart, Segment};
+use hyper::Method;
+use macros::CaseIterable;
+use std::borrow::Cow;
+use std::cmp::Reverse;
 use std::collections::HashMap;
 use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
 
 pub struct Node<Extra: Send + Sync> {
-    pub route: Option<Route<Extra>>,
+    /// The route at this node, shared with in-flight requests matching it.
+    pub route: Option<Arc<Route<Extra>>>,
     pub children: HashMap<Segment, Node<Extra>>,
+    /// Handler for panics raised by routes in this subtree.
+    pub on_panic: Option<PanicHandler<Extra>>,
+    /// Handler for unmatched paths in this subtree.
+    pub route_not_found: Option<RouteNotFoundHandler<Extra>>,
+    /// Middleware wrapping the handlers of routes in this subtree.
+    pub layers: Vec<Middleware<Extra>>,
+    /// Whether an exact route lies in this subtree, disabling fallback to the
+    /// node's dynamic and catch-all siblings.
+    pub exact: bool,
+    /// Methods of the node's route whose handler was replaced by a later
+    /// registration of the same path.
+    pub replaced: Vec<Method>,
+    /// Literal segments following the node's own segment, merged into it by
+    /// [`Node::compact`].
+    pub chain: Vec<String>,
+    /// The pattern children of the node, in the order matching tries them.
+    pub patterns: Vec<Segment>,
+    /// Whether a route with a non-default [priority](Route::priority) was
+    /// appended below this node, so matching must weigh every candidate.
+    pub prioritized: bool,
 }
 
 impl<Extra: Send + Sync> Clone for Node<Extra> {
@@ -13,6 +43,14 @@ impl<Extra: Send + Sync> Clone for Node<Extra> {
         Self {
             route: Clone::clone(&self.route),
             children: Clone::clone(&self.children),
+            on_panic: Clone::clone(&self.on_panic),
+            route_not_found: Clone::clone(&self.route_not_found),
+            layers: Clone::clone(&self.layers),
+            exact: self.exact,
+            replaced: Clone::clone(&self.replaced),
+            chain: Clone::clone(&self.chain),
+            patterns: Clone::clone(&self.patterns),
+            prioritized: self.prioritized,
         }
     }
 }
@@ -22,6 +60,14 @@ impl<Extra: Send + Sync> Debug for Node<Extra> {
         f.debug_struct("Node")
             .field("route", &self.route)
             .field("children", &self.children)
+            .field("on_panic", &self.on_panic.is_some())
+            .field("route_not_found", &self.route_not_found.is_some())
+            .field("layers", &self.layers.len())
+            .field("exact", &self.exact)
+            .field("replaced", &self.replaced)
+            .field("chain", &self.chain)
+            .field("patterns", &self.patterns)
+            .field("prioritized", &self.prioritized)
             .finish()
     }
 }
@@ -31,22 +77,465 @@ impl<Extra: Send + Sync> Default for Node<Extra> {
         Self {
             route: None,
             children: HashMap::new(),
+            on_panic: None,
+            route_not_found: None,
+            layers: vec![],
+            exact: false,
+            replaced: vec![],
+            chain: vec![],
+            patterns: vec![],
+            prioritized: false,
         }
     }
 }
 
 impl<Extra: Send + Sync> Node<Extra> {
+    /// Constructs an empty node with space reserved for `capacity` children.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            children: HashMap::with_capacity(capacity),
+            ..Default::default()
+        }
+    }
+
     pub fn append(&mut self, route: Route<Extra>) {
+        self.prioritized |= route.priority_value() != 0;
         let mut current = self;
 
-        for segment in route.path.0.clone() {
-            current = current
+        for segment in route.path.0.iter() {
+            current = current.child_mut(segment);
+            current.exact |= route.is_exact();
+        }
+
+        match &mut current.route {
+            Some(existing) => {
+                current.replaced.extend(
+                    route
+                        .allowed_methods()
+                        .into_iter()
+                        .filter(|method| route.has_handler(method) && existing.has_handler(method)),
+                );
+                Arc::make_mut(existing).merge(route);
+            }
+            None => current.route = Some(Arc::new(route)),
+        }
+    }
+
+    /// Returns the child at `segment`, creating it if it's missing.
+    fn child_mut(&mut self, segment: &Segment) -> &mut Node<Extra> {
+        if matches!(segment, Segment::Pattern(_)) && !self.children.contains_key(segment) {
+            let order = pattern_order(segment);
+            let idx = self
+                .patterns
+                .partition_point(|pattern| pattern_order(pattern) < order);
+            self.patterns.insert(idx, segment.clone());
+        }
+
+        self.children.entry(segment.clone()).or_default()
+    }
+
+    /// Merges each chain of nodes that only lead to a single literal child
+    /// into the chain's first node, radix-tree style, so matching takes one
+    /// hop per chain instead of one per segment.
+    ///
+    /// Routes can no longer be added afterwards, and only [`Node::routes`] and
+    /// the lookups of the request path (`panic_handler`,
+    /// `route_not_found_handler` and `layers`) are aware of chains.
+    pub fn compact(&mut self) {
+        for (segment, child) in &mut self.children {
+            // Only the route of a catch-all is used, so it's never merged.
+            if !matches!(segment, Segment::CatchAll(_)) {
+                child.compact_chain();
+            }
+        }
+    }
+
+    fn compact_chain(&mut self) {
+        while self.route.is_none()
+            && self.on_panic.is_none()
+            && self.route_not_found.is_none()
+            && self.layers.is_empty()
+            && self.children.len() == 1
+            && matches!(self.children.keys().next(), Some(Segment::Literal(_)))
+        {
+            let (segment, child) = self.children.drain().next().unwrap();
+            let Segment::Literal(literal) = segment else {
+                unreachable!("only literal children are merged")
+            };
+
+            let mut chain = std::mem::take(&mut self.chain);
+            chain.push(literal);
+            chain.extend(child.chain.iter().cloned());
+            *self = Node { chain, ..child };
+        }
+
+        self.compact();
+    }
+
+    /// Strips the node's [chain](Node::chain) off the front of `segments`,
+    /// returning the segments left, or `None` if they don't start with it.
+    /// Like literal children, the chain also matches percent-encoded segments
+    /// with `decode`, and with `ignore_case`, segments differing in ASCII case.
+    pub fn strip_chain<'s, 'a>(
+        &self,
+        segments: &'s [&'a str],
+        ignore_case: bool,
+        decode: bool,
+    ) -> Option<&'s [&'a str]> {
+        if segments.len() < self.chain.len() {
+            return None;
+        }
+
+        let (head, rest) = segments.split_at(self.chain.len());
+        self.chain_starts_with(head, ignore_case, decode)
+            .then_some(rest)
+    }
+
+    /// Returns whether the node's [chain](Node::chain) starts with `segments`,
+    /// matching them like [`strip_chain`](Node::strip_chain).
+    pub fn chain_starts_with(&self, segments: &[&str], ignore_case: bool, decode: bool) -> bool {
+        segments.len() <= self.chain.len()
+            && segments.iter().zip(&self.chain).all(|(segment, literal)| {
+                if *segment == literal {
+                    return true;
+                }
+
+                let decoded = decode_if(segment, decode);
+                decoded == literal.as_str() || ignore_case && decoded.eq_ignore_ascii_case(literal)
+            })
+    }
+
+    /// Returns the literal child equal to `segment` or, with `decode`, its
+    /// percent-decoded form, and with `ignore_case`, differing from it in
+    /// ASCII case.
+    pub fn literal_child(
+        &self,
+        segment: &str,
+        ignore_case: bool,
+        decode: bool,
+    ) -> Option<&Node<Extra>> {
+        // Literals are stored decoded, so e.g. `%5B` matches an escaped `[`.
+        let literal = match decode_if(segment, decode) {
+            Cow::Owned(decoded) => self
+                .children
+                .get(&Segment::literal(segment))
+                .or_else(|| self.children.get(&Segment::literal(decoded))),
+            Cow::Borrowed(_) => self.children.get(&Segment::literal(segment)),
+        };
+
+        literal.or_else(|| {
+            ignore_case
+                .then(|| self.literal_child_ignore_case(segment, decode))
+                .flatten()
+        })
+    }
+
+    /// Returns the literal child equal to `segment`, or with `decode` its
+    /// percent-decoded form, ignoring ASCII case. Of literals differing only
+    /// in case, the first in byte order is chosen.
+    pub fn literal_child_ignore_case(&self, segment: &str, decode: bool) -> Option<&Node<Extra>> {
+        let decoded = decode_if(segment, decode);
+
+        self.children
+            .iter()
+            .filter_map(|(child_segment, child)| match child_segment {
+                Segment::Literal(literal) if decoded.eq_ignore_ascii_case(literal) => {
+                    Some((literal, child))
+                }
+                _ => None,
+            })
+            .min_by_key(|(literal, _)| *literal)
+            .map(|(_, child)| child)
+    }
+
+    /// Returns the pattern children matching `segment`, most literal text
+    /// first. With `decode`, their literal text also matches percent-encoded.
+    pub fn pattern_children<'n>(
+        &'n self,
+        segment: &str,
+        decode: bool,
+    ) -> impl Iterator<Item = &'n Node<Extra>> {
+        self.patterns
+            .iter()
+            .filter(move |pattern| pattern.is_match_with(segment, decode))
+            .map(|pattern| &self.children[pattern])
+    }
+
+    /// Returns the constrained children whose constraint `segment`
+    /// satisfies, in the order of [`Constraint::ALL_CASES`].
+    pub fn constrained_children<'n>(
+        &'n self,
+        segment: &str,
+    ) -> impl Iterator<Item = &'n Node<Extra>> {
+        Constraint::ALL_CASES.iter().filter_map(move |&constraint| {
+            let (key, child) = self
                 .children
-                .entry(segment)
-                .or_insert(Node::<Extra>::default());
+                .get_key_value(&Segment::constrained("", constraint))?;
+            key.is_match(segment).then_some(child)
+        })
+    }
+
+    /// Collects problems with the routes in this subtree, whose path from the
+    /// root is `path`: handlers replaced by a later registration, parameters
+    /// named differently by routes sharing a segment, and catch-alls followed
+    /// by more segments.
+    pub fn conflicts(&self, path: &mut Vec<Segment>, problems: &mut Vec<String>) {
+        if let Some(route) = &self.route {
+            for method in &self.replaced {
+                problems.push(format!(
+                    "`{method} {}` is registered more than once",
+                    route.path
+                ));
+            }
+
+            for (segment, shared) in route.path.0.iter().zip(path.iter()) {
+                if let (
+                    Segment::Dynamic(name)
+                    | Segment::Constrained(name, _)
+                    | Segment::Optional(name)
+                    | Segment::CatchAll(name),
+                    Segment::Dynamic(other)
+                    | Segment::Constrained(other, _)
+                    | Segment::Optional(other)
+                    | Segment::CatchAll(other),
+                ) = (segment, shared)
+                    && name != other
+                {
+                    problems.push(format!(
+                        "`{}` names parameter `{other}` `{name}` instead",
+                        route.path
+                    ));
+                }
+            }
         }
 
-        current.route = Some(route);
+        for (segment, child) in &self.children {
+            path.push(segment.clone());
+
+            if matches!(segment, Segment::CatchAll(_)) && !child.children.is_empty() {
+                problems.push(format!(
+                    "catch-all `{}` is followed by more segments",
+                    Path(path.clone())
+                ));
+            }
+            child.conflicts(path, problems);
+
+            path.pop();
+        }
+    }
+
+    /// Collects the routes in this subtree depth-first, each with its allowed
+    /// methods in canonical order.
+    pub fn routes(&self, routes: &mut Vec<(Path, Vec<Method>)>) {
+        if let Some(route) = &self.route {
+            routes.push((route.path.clone(), route.allowed_methods()));
+        }
+
+        for child in self.children.values() {
+            child.routes(routes);
+        }
+    }
+
+    /// Grafts the routes and subtree handlers of `other` onto the node at
+    /// `prefix`, prepending `prefix` to the paths of its routes.
+    pub fn graft(&mut self, prefix: &Path, other: Node<Extra>) {
+        let mut path = prefix.0.clone();
+        self.graft_at(prefix, &mut path, other);
+    }
+
+    fn graft_at(&mut self, prefix: &Path, path: &mut Vec<Segment>, other: Node<Extra>) {
+        let target = self.descendant_mut(&Path(path.clone()));
+        target.on_panic = other.on_panic.or(target.on_panic.take());
+        target.route_not_found = other.route_not_found.or(target.route_not_found.take());
+        target.layers.extend(other.layers);
+        target.replaced.extend(other.replaced);
+
+        if let Some(route) = other.route {
+            let mut route = Arc::unwrap_or_clone(route);
+            route.path = Path(prefix.0.iter().chain(&route.path.0).cloned().collect());
+            self.append(route);
+        }
+
+        for (segment, child) in other.children {
+            path.push(segment);
+            self.graft_at(prefix, path, child);
+            path.pop();
+        }
+    }
+
+    /// Removes the route at `segments` below this node, pruning the nodes
+    /// left empty on the way back up.
+    pub fn remove(&mut self, segments: &[Segment]) -> Option<Arc<Route<Extra>>> {
+        let Some((segment, rest)) = segments.split_first() else {
+            self.replaced.clear();
+            let route = self.route.take();
+            self.exact = self.children.values().any(|child| child.exact);
+            return route;
+        };
+
+        let child = self.children.get_mut(segment)?;
+        let route = child.remove(rest)?;
+        if child.is_empty() {
+            self.children.remove(segment);
+            self.patterns.retain(|pattern| pattern != segment);
+        }
+        self.exact = self.route.as_ref().is_some_and(|route| route.is_exact())
+            || self.children.values().any(|child| child.exact);
+
+        Some(route)
+    }
+
+    /// Returns whether the node has no route, children or subtree handlers.
+    fn is_empty(&self) -> bool {
+        self.route.is_none()
+            && self.children.is_empty()
+            && self.on_panic.is_none()
+            && self.route_not_found.is_none()
+            && self.layers.is_empty()
+    }
+
+    /// Returns the node at `path`, if it exists.
+    pub fn descendant(&self, path: &Path) -> Option<&Node<Extra>> {
+        path.0
+            .iter()
+            .try_fold(self, |current, segment| current.children.get(segment))
+    }
+
+    /// Returns the node at `path`, creating it and any missing ancestors.
+    pub fn descendant_mut(&mut self, path: &Path) -> &mut Node<Extra> {
+        let mut current = self;
+
+        for segment in path.0.iter() {
+            current = current.child_mut(segment);
+        }
+
+        current
+    }
+
+    /// Returns the panic handler of the closest node along `path` that has one.
+    pub fn panic_handler(&self, path: &Path) -> Option<PanicHandler<Extra>> {
+        let mut current = self;
+        let mut handler = self.on_panic.as_ref();
+        let mut segments = path.0.as_slice();
+
+        while let Some((segment, rest)) = segments.split_first() {
+            let Some(child) = current.children.get(segment) else {
+                break;
+            };
+
+            current = child;
+            handler = current.on_panic.as_ref().or(handler);
+            segments = rest.get(current.chain.len()..).unwrap_or_default();
+        }
+
+        handler.cloned()
+    }
+
+    /// Returns the route-not-found handler of the deepest node along
+    /// `segments` that has one, descending into literal, pattern, constrained,
+    /// dynamic and then catch-all children. Literals match like they do when
+    /// routing, percent-decoded with `decode` and, with `ignore_case`, in any
+    /// ASCII case.
+    pub fn route_not_found_handler(
+        &self,
+        segments: &[&str],
+        ignore_case: bool,
+        decode: bool,
+    ) -> Option<RouteNotFoundHandler<Extra>> {
+        let mut current = self;
+        let mut handler = self.route_not_found.as_ref();
+        let mut segments = segments;
+
+        while let Some((segment, rest)) = segments.split_first() {
+            let child = current
+                .literal_child(segment, ignore_case, decode)
+                .or_else(|| current.pattern_children(segment, decode).next())
+                .or_else(|| current.constrained_children(segment).next())
+                .or_else(|| current.children.get(&Segment::dynamic("")))
+                .or_else(|| current.children.get(&Segment::catch_all("")));
+            let Some(child) = child else {
+                break;
+            };
+            let Some(rest) = child.strip_chain(rest, ignore_case, decode) else {
+                break;
+            };
+
+            current = child;
+            segments = rest;
+            handler = current.route_not_found.as_ref().or(handler);
+        }
+
+        handler.cloned()
+    }
+
+    /// Returns the middleware of the nodes along `path`, outermost first.
+    pub fn layers(&self, path: &Path) -> Vec<Middleware<Extra>> {
+        let mut current = self;
+        let mut layers = self.layers.clone();
+        let mut segments = path.0.as_slice();
+
+        while let Some((segment, rest)) = segments.split_first() {
+            let Some(child) = current.children.get(segment) else {
+                break;
+            };
+
+            current = child;
+            layers.extend(current.layers.iter().cloned());
+            segments = rest.get(current.chain.len()..).unwrap_or_default();
+        }
+
+        layers
+    }
+}
+
+/// Percent-decodes `segment` if `decode`, for comparing it with literals,
+/// which are stored decoded.
+fn decode_if(segment: &str, decode: bool) -> Cow<'_, str> {
+    if decode {
+        percent_decode(segment)
+    } else {
+        Cow::Borrowed(segment)
+    }
+}
+
+/// Orders pattern segments for matching: those with the most literal text
+/// first, then by their text.
+fn pattern_order(pattern: &Segment) -> (Reverse<usize>, String) {
+    let Segment::Pattern(parts) = pattern else {
+        unreachable!("only patterns are ordered")
+    };
+    let literal_len = parts
+        .iter()
+        .map(|part| match part {
+            PatternPart::Literal(literal) => literal.len(),
+            PatternPart::Placeholder(_) => 0,
+        })
+        .sum();
+    (Reverse(literal_len), format!("{pattern:?}"))
+}
+
+/// A key for looking up literal children, reused across the segments of a
+/// path so matching it doesn't allocate per segment.
+pub struct LiteralKey(Segment);
+
+impl LiteralKey {
+    /// Constructs a key with room for segments of up to `capacity` bytes.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self(Segment::Literal(String::with_capacity(capacity)))
+    }
+
+    /// Returns the literal child of `node` equal to `segment`.
+    pub fn child<'n, Extra: Send + Sync>(
+        &mut self,
+        node: &'n Node<Extra>,
+        segment: &str,
+    ) -> Option<&'n Node<Extra>> {
+        let Segment::Literal(literal) = &mut self.0 else {
+            unreachable!("keys are literals")
+        };
+        literal.clear();
+        literal.push_str(segment);
+        node.children.get(&self.0)
     }
 }
 
@@ -62,6 +551,13 @@ mod tests {
         assert!(node.route.is_none());
     }
 
+    #[test]
+    fn with_capacity() {
+        let node = Node::<()>::with_capacity(64);
+        assert!(node.children.capacity() >= 64);
+        assert!(node.route.is_none());
+    }
+
     #[test]
     fn insert_root() {
         let mut root = Node::<()>::default();
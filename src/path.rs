@@ -1,20 +1,155 @@
-use crate::segment::Segment;
-use std::convert::Infallible;
+use crate::encoding::percent_decode;
+use crate::segment::{Constraint, PatternPart, Segment};
+use std::error::Error;
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
+/// Parses a [`Path`] from a string literal or a runtime string such as a
+/// `String`, panicking if it's malformed.
+///
+/// # Example
+///
+/// ```
+/// use radmin_router::path;
+///
+/// let prefix = String::from("/api");
+/// assert_eq!(path!(format!("{prefix}/users")), path!("/api/users"));
+/// ```
 #[macro_export]
 macro_rules! path {
     ($path:literal) => {{
         use std::str::FromStr;
-        ::radmin_router::Path::from_str($path).unwrap()
+        $crate::Path::from_str($path).unwrap()
     }};
+    ($path:expr) => {
+        $crate::Path::from(::std::convert::AsRef::<str>::as_ref(&$path))
+    };
 }
 
+/// Parses a [`Path`], returning a `Result` with a [`PathParseError`] instead
+/// of panicking like [`path!`] when malformed.
+///
+/// # Example
+///
+/// ```
+/// use radmin_router::{PathParseError, try_path};
+///
+/// assert!(try_path!("/users/[id]").is_ok());
+/// assert_eq!(
+///     try_path!("/users/[id"),
+///     Err(PathParseError::UnterminatedBracket("[id".into()))
+/// );
+/// ```
+#[macro_export]
+macro_rules! try_path {
+    ($path:expr) => {
+        <$crate::Path as ::std::str::FromStr>::from_str($path)
+    };
+}
+
+/// An error encountered while parsing a `Path`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PathParseError {
+    /// A catch-all segment appeared before the final segment.
+    MisplacedCatchAll(String),
+    /// A dynamic segment named a constraint other than `int`, `uuid` or `alpha`.
+    UnknownConstraint(String),
+    /// A pattern segment had two placeholders in a row, e.g. `[a][b]`.
+    AdjacentPlaceholders(String),
+    /// An optional segment was followed by a segment that isn't optional.
+    MisplacedOptional(String),
+    /// A segment had a `[` without a matching `]`.
+    UnterminatedBracket(String),
+    /// A segment had a `]` without a matching `[`.
+    UnexpectedClosingBracket(String),
+    /// A placeholder contained another `[`, e.g. `[a[b]]`.
+    NestedBrackets(String),
+    /// A placeholder had no name, e.g. `[]` or `[...]`.
+    EmptyName(String),
+}
+
+impl Display for PathParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MisplacedCatchAll(segment) => {
+                write!(
+                    f,
+                    "catch-all segment `{}` must be the last segment",
+                    segment
+                )
+            }
+            Self::UnknownConstraint(segment) => {
+                write!(f, "unknown constraint in segment `{}`", segment)
+            }
+            Self::AdjacentPlaceholders(segment) => {
+                write!(f, "adjacent placeholders in segment `{}`", segment)
+            }
+            Self::MisplacedOptional(segment) => {
+                write!(
+                    f,
+                    "optional segment `{}` must only be followed by optional segments",
+                    segment
+                )
+            }
+            Self::UnterminatedBracket(segment) => {
+                write!(f, "unterminated `[` in segment `{}`", segment)
+            }
+            Self::UnexpectedClosingBracket(segment) => {
+                write!(f, "unexpected `]` in segment `{}`", segment)
+            }
+            Self::NestedBrackets(segment) => {
+                write!(f, "nested brackets in segment `{}`", segment)
+            }
+            Self::EmptyName(segment) => {
+                write!(f, "placeholder without a name in segment `{}`", segment)
+            }
+        }
+    }
+}
+
+impl Error for PathParseError {}
+
 /// A route path, i.e. an ordered list of segments.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Path(pub Vec<Segment>);
 
+/// A segment of a `Path` along with its position, as yielded by [`Path::iter_with_meta`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SegmentMeta<'a> {
+    /// Zero-based position of the segment in the path.
+    pub index: usize,
+    /// Whether this is the final segment of the path.
+    pub is_last: bool,
+    pub segment: &'a Segment,
+}
+
+impl Path {
+    /// Iterates over the path's segments along with their position metadata.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::path;
+    ///
+    /// let path = path!("/users/[id]");
+    /// let last = path.iter_with_meta().last().unwrap();
+    /// assert_eq!(last.index, 1);
+    /// assert!(last.is_last);
+    /// ```
+    pub fn iter_with_meta(&self) -> impl Iterator<Item = SegmentMeta<'_>> {
+        let len = self.0.len();
+
+        self.0
+            .iter()
+            .enumerate()
+            .map(move |(index, segment)| SegmentMeta {
+                index,
+                is_last: index + 1 == len,
+                segment,
+            })
+    }
+}
+
 impl Display for Path {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let formatted = self
@@ -22,7 +157,19 @@ impl Display for Path {
             .iter()
             .map(|seg| match seg {
                 Segment::Dynamic(name) => format!("[{}]", name),
-                Segment::Literal(segment) => segment.clone(),
+                Segment::Constrained(name, constraint) => {
+                    format!("[{}:{}]", name, constraint.name())
+                }
+                Segment::Optional(name) => format!("[{}?]", name),
+                Segment::CatchAll(name) => format!("[...{}]", name),
+                Segment::Literal(segment) => escape_percent(&escape_literal(segment)),
+                Segment::Pattern(parts) => parts
+                    .iter()
+                    .map(|part| match part {
+                        PatternPart::Literal(literal) => escape_percent(&escape_literal(literal)),
+                        PatternPart::Placeholder(name) => format!("[{}]", name),
+                    })
+                    .collect(),
             })
             .reduce(|mut acc, v| {
                 acc += "/";
@@ -36,9 +183,29 @@ impl Display for Path {
 }
 
 impl FromStr for Path {
-    type Err = Infallible;
+    type Err = PathParseError;
 
-    /// Infallibly parses a `Path` from a string.
+    /// Parses a `Path` from a string.
+    ///
+    /// Brackets in literal segments are written as `\[` and `\]` (and a
+    /// backslash as `\\`), or percent-encoded as `%5B` and `%5D`. Literal
+    /// segments, and the literal text of patterns, are stored
+    /// percent-decoded, as request paths are decoded before comparing them,
+    /// so `/hello%20world` and `/hello world` are the same route, and
+    /// `/caf%C3%A9-[id]` is `/café-[id]`. They match requests to
+    /// `/hello%20world` and `/caf%C3%A9-7`.
+    ///
+    /// Dynamic segments may be constrained with `[name:int]`, `[name:uuid]`
+    /// or `[name:alpha]`. Segments mixing text and placeholders, such as
+    /// `report-[year].csv`, are parsed as [patterns](Segment::Pattern).
+    ///
+    /// Optional segments are written `[name?]`.
+    ///
+    /// Fails if a catch-all segment (`[...name]`) is not the last segment, if
+    /// an optional segment is followed by one that isn't optional, if a
+    /// constraint is unknown, if a pattern has adjacent placeholders, or if a
+    /// segment has unbalanced or nested brackets or a placeholder without a
+    /// name.
     fn from_str(path: &str) -> Result<Self, Self::Err> {
         let path = path.to_string();
 
@@ -49,17 +216,51 @@ impl FromStr for Path {
                 continue;
             }
 
-            let segment = String::from(segment);
+            check_brackets(segment)?;
+            let is_dynamic = segment.len() >= 2
+                && segment.starts_with('[')
+                && segment.ends_with(']')
+                && !segment[1..segment.len() - 1].contains(['[', ']']);
 
-            let chars = segment.chars().collect::<Vec<_>>();
-            let is_dynamic = chars[0] == '[' && chars[segment.len() - 1] == ']';
+            if let Some(Segment::CatchAll(name)) = segments.last() {
+                return Err(PathParseError::MisplacedCatchAll(format!("[...{}]", name)));
+            }
 
             let segment = if is_dynamic {
-                Segment::dynamic(&segment[1..segment.len() - 2])
+                let name = &segment[1..segment.len() - 1];
+                let bare = name
+                    .strip_prefix("...")
+                    .or_else(|| name.strip_suffix('?'))
+                    .unwrap_or_else(|| name.split_once(':').map_or(name, |(name, _)| name));
+                if bare.is_empty() {
+                    return Err(PathParseError::EmptyName(segment.into()));
+                }
+
+                if let Some(name) = name.strip_prefix("...") {
+                    Segment::catch_all(name)
+                } else if let Some(name) = name.strip_suffix('?') {
+                    Segment::optional(name)
+                } else {
+                    match name.split_once(':') {
+                        Some((name, constraint)) => match Constraint::from_name(constraint) {
+                            Some(constraint) => Segment::constrained(name, constraint),
+                            None => {
+                                return Err(PathParseError::UnknownConstraint(segment.into()));
+                            }
+                        },
+                        None => Segment::dynamic(name),
+                    }
+                }
             } else {
-                Segment::literal(&segment)
+                parse_pattern(segment)?
             };
 
+            if let Some(Segment::Optional(name)) = segments.last()
+                && !matches!(segment, Segment::Optional(_))
+            {
+                return Err(PathParseError::MisplacedOptional(format!("[{}?]", name)));
+            }
+
             segments.push(segment);
         }
 
@@ -67,12 +268,183 @@ impl FromStr for Path {
     }
 }
 
-impl<A> From<A> for Path
-where
-    A: AsRef<[Segment]>,
-{
-    fn from(value: A) -> Self {
-        Self(value.as_ref().to_vec())
+/// Checks that the unescaped brackets of `segment` enclose non-empty
+/// placeholder names, one level deep.
+fn check_brackets(segment: &str) -> Result<(), PathParseError> {
+    let mut open = None;
+    let mut chars = segment.char_indices();
+
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '[' if open.is_some() => return Err(PathParseError::NestedBrackets(segment.into())),
+            '[' => open = Some(idx),
+            ']' => match open.take() {
+                Some(start) if start + 1 == idx => {
+                    return Err(PathParseError::EmptyName(segment.into()));
+                }
+                Some(_) => {}
+                None => return Err(PathParseError::UnexpectedClosingBracket(segment.into())),
+            },
+            _ => {}
+        }
+    }
+
+    match open {
+        Some(_) => Err(PathParseError::UnterminatedBracket(segment.into())),
+        None => Ok(()),
+    }
+}
+
+/// Parses a segment that isn't wholly dynamic: a pattern if it contains
+/// unescaped placeholders, and a literal otherwise.
+fn parse_pattern(segment: &str) -> Result<Segment, PathParseError> {
+    let bytes = segment.as_bytes();
+    let mut parts = vec![];
+    let mut literal_start = 0;
+    let mut idx = 0;
+
+    while idx < bytes.len() {
+        match bytes[idx] {
+            b'\\' => idx += 2,
+            b'[' if let Some(len) = segment[idx + 1..].find(']') => {
+                let literal = &segment[literal_start..idx];
+                if !literal.is_empty() {
+                    parts.push(PatternPart::Literal(decode_literal(literal)));
+                } else if matches!(parts.last(), Some(PatternPart::Placeholder(_))) {
+                    return Err(PathParseError::AdjacentPlaceholders(segment.into()));
+                }
+
+                parts.push(PatternPart::Placeholder(
+                    segment[idx + 1..idx + 1 + len].into(),
+                ));
+                idx += len + 2;
+                literal_start = idx;
+            }
+            _ => idx += 1,
+        }
+    }
+
+    if parts.is_empty() {
+        return Ok(Segment::literal(decode_literal(segment)));
+    }
+
+    if literal_start < segment.len() {
+        parts.push(PatternPart::Literal(decode_literal(
+            &segment[literal_start..],
+        )));
+    }
+    Ok(Segment::Pattern(parts))
+}
+
+/// Resolves the escapes in literal text, stored percent-decoded as requests
+/// are compared with it decoded.
+fn decode_literal(literal: &str) -> String {
+    percent_decode(&unescape_literal(literal)).into_owned()
+}
+
+/// Resolves bracket and backslash escapes in a literal segment.
+fn unescape_literal(segment: &str) -> String {
+    let mut literal = String::with_capacity(segment.len());
+    let mut rest = segment;
+
+    while let Some(c) = rest.chars().next() {
+        let (unescaped, len) = match (c, rest.get(..3)) {
+            ('\\', _) if rest[1..].starts_with(['[', ']', '\\']) => {
+                (rest[1..].chars().next().unwrap(), 2)
+            }
+            ('%', Some(escape)) if escape.eq_ignore_ascii_case("%5B") => ('[', 3),
+            ('%', Some(escape)) if escape.eq_ignore_ascii_case("%5D") => (']', 3),
+            (c, _) => (c, c.len_utf8()),
+        };
+
+        literal.push(unescaped);
+        rest = &rest[len..];
+    }
+
+    literal
+}
+
+/// Escapes a literal segment so that it parses back into the same literal.
+fn escape_literal(literal: &str) -> String {
+    let mut escaped = String::with_capacity(literal.len());
+
+    for c in literal.chars() {
+        if matches!(c, '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+/// Escapes the `%` of a decoded literal segment wherever it would otherwise
+/// be decoded again as a percent-encoded byte.
+fn escape_percent(literal: &str) -> String {
+    let mut escaped = String::with_capacity(literal.len());
+
+    for (idx, c) in literal.char_indices() {
+        let hex = literal.as_bytes().get(idx + 1..idx + 3);
+        if c == '%' && hex.is_some_and(|hex| hex.iter().all(u8::is_ascii_hexdigit)) {
+            escaped.push_str("%25");
+        } else {
+            escaped.push(c);
+        }
+    }
+
+    escaped
+}
+
+impl From<Vec<Segment>> for Path {
+    fn from(segments: Vec<Segment>) -> Self {
+        Self(segments)
+    }
+}
+
+impl From<&Vec<Segment>> for Path {
+    fn from(segments: &Vec<Segment>) -> Self {
+        Self(segments.clone())
+    }
+}
+
+impl From<&[Segment]> for Path {
+    fn from(segments: &[Segment]) -> Self {
+        Self(segments.to_vec())
+    }
+}
+
+impl<const N: usize> From<[Segment; N]> for Path {
+    fn from(segments: [Segment; N]) -> Self {
+        Self(segments.into())
+    }
+}
+
+impl<const N: usize> From<&[Segment; N]> for Path {
+    fn from(segments: &[Segment; N]) -> Self {
+        Self(segments.to_vec())
+    }
+}
+
+/// Parses a `Path` from a string, e.g. one computed at runtime.
+///
+/// # Panics
+///
+/// Panics if the path is malformed; parse it with [`FromStr`] or
+/// [`try_path!`](crate::try_path) to handle the error instead.
+impl From<&str> for Path {
+    fn from(path: &str) -> Self {
+        path.parse()
+            .unwrap_or_else(|err| panic!("invalid path `{}`: {}", path, err))
+    }
+}
+
+/// See [`From<&str>`](#impl-From%3C%26str%3E-for-Path).
+impl From<String> for Path {
+    fn from(path: String) -> Self {
+        Self::from(path.as_str())
     }
 }
 
@@ -117,4 +489,226 @@ mod tests {
             ])
         );
     }
+
+    #[test]
+    fn dynamic_segment_name() {
+        let path = Path::from_str("/[slug]").unwrap();
+        assert!(matches!(&path.0[..], [Segment::Dynamic(name)] if name == "slug"));
+
+        let path = Path::from_str("/a/[...rest]").unwrap();
+        assert!(matches!(&path.0[1], Segment::CatchAll(name) if name == "rest"));
+    }
+
+    #[test]
+    fn display_round_trip() {
+        for source in [
+            "/",
+            "/test/[slug]",
+            "/a/[b]/c/[...rest]",
+            "/100%2541",
+            "/50%",
+        ] {
+            let path = Path::from_str(source).unwrap();
+            assert_eq!(path.to_string(), source);
+            assert_eq!(
+                Path::from_str(&path.to_string()).unwrap().to_string(),
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn short_and_non_ascii_segments() {
+        let path = Path::from_str("/[a]/b/café").unwrap();
+        assert!(matches!(&path.0[0], Segment::Dynamic(name) if name == "a"));
+        assert!(matches!(&path.0[1], Segment::Literal(value) if value == "b"));
+        assert!(matches!(&path.0[2], Segment::Literal(value) if value == "café"));
+    }
+
+    #[test]
+    fn percent_encoded_literals() {
+        assert_eq!(path!("/hello%20world"), path!("/hello world"));
+        assert_eq!(path!("/caf%C3%A9"), path!("/café"));
+        assert_eq!(path!("/caf%c3%a9").to_string(), "/café");
+        assert!(matches!(&path!("/100%2541").0[0], Segment::Literal(value) if value == "100%41"));
+
+        // The literal parts of patterns too.
+        assert_eq!(path!("/caf%C3%A9-[id]"), path!("/café-[id]"));
+        assert_eq!(path!("/caf%c3%a9-[id]").to_string(), "/café-[id]");
+        assert_eq!(path!("/100%2541-[n]").to_string(), "/100%2541-[n]");
+    }
+
+    #[test]
+    fn malformed_brackets() {
+        for (source, err) in [
+            (
+                "/[unclosed",
+                PathParseError::UnterminatedBracket("[unclosed".into()),
+            ),
+            ("/[", PathParseError::UnterminatedBracket("[".into())),
+            (
+                "/report-[year",
+                PathParseError::UnterminatedBracket("report-[year".into()),
+            ),
+            (
+                "/]backwards[",
+                PathParseError::UnexpectedClosingBracket("]backwards[".into()),
+            ),
+            ("/]", PathParseError::UnexpectedClosingBracket("]".into())),
+            ("/[]", PathParseError::EmptyName("[]".into())),
+            ("/a/v[]", PathParseError::EmptyName("v[]".into())),
+            ("/[...]", PathParseError::EmptyName("[...]".into())),
+            ("/[?]", PathParseError::EmptyName("[?]".into())),
+            ("/[:int]", PathParseError::EmptyName("[:int]".into())),
+            ("/[a[b]]", PathParseError::NestedBrackets("[a[b]]".into())),
+        ] {
+            assert_eq!(Path::from_str(source), Err(err), "{source}");
+        }
+    }
+
+    #[test]
+    fn constrained_segments() {
+        let path = Path::from_str("/users/[id:int]/[ref:uuid]/[tag:alpha]").unwrap();
+        assert!(matches!(
+            &path.0[1..],
+            [
+                Segment::Constrained(id, Constraint::Int),
+                Segment::Constrained(reference, Constraint::Uuid),
+                Segment::Constrained(tag, Constraint::Alpha),
+            ] if id == "id" && reference == "ref" && tag == "tag"
+        ));
+        assert_eq!(path.to_string(), "/users/[id:int]/[ref:uuid]/[tag:alpha]");
+
+        assert_eq!(
+            Path::from_str("/users/[id:float]"),
+            Err(PathParseError::UnknownConstraint("[id:float]".into()))
+        );
+    }
+
+    #[test]
+    fn pattern_segments() {
+        let path = Path::from_str("/reports/report-[year].csv").unwrap();
+        assert!(matches!(
+            &path.0[1],
+            Segment::Pattern(parts) if matches!(
+                &parts[..],
+                [
+                    PatternPart::Literal(prefix),
+                    PatternPart::Placeholder(year),
+                    PatternPart::Literal(suffix),
+                ] if prefix == "report-" && year == "year" && suffix == ".csv"
+            )
+        ));
+        assert_eq!(path.to_string(), "/reports/report-[year].csv");
+
+        let path = Path::from_str(r"/[a]-[b]/\[[c]\]").unwrap();
+        assert!(matches!(&path.0[0], Segment::Pattern(parts) if parts.len() == 3));
+        assert!(matches!(&path.0[1], Segment::Pattern(parts) if parts.len() == 3));
+        assert_eq!(path.to_string(), r"/[a]-[b]/\[[c]\]");
+
+        assert_eq!(
+            Path::from_str("/[a][b].txt"),
+            Err(PathParseError::AdjacentPlaceholders("[a][b].txt".into()))
+        );
+    }
+
+    #[test]
+    fn escaped_brackets() {
+        for source in [r"/files/\[draft\]", "/files/%5Bdraft%5d"] {
+            let path = Path::from_str(source).unwrap();
+            assert!(matches!(&path.0[1], Segment::Literal(value) if value == "[draft]"));
+            assert_eq!(path.to_string(), r"/files/\[draft\]");
+        }
+
+        let path = Path::from_str(r"/a\\b/c\d").unwrap();
+        assert!(matches!(&path.0[0], Segment::Literal(value) if value == r"a\b"));
+        assert!(matches!(&path.0[1], Segment::Literal(value) if value == r"c\d"));
+        assert_eq!(path.to_string(), r"/a\\b/c\\d");
+    }
+
+    #[test]
+    fn iter_with_meta() {
+        let path = Path(vec![
+            Segment::literal("files"),
+            Segment::dynamic("owner"),
+            Segment::catch_all("rest"),
+        ]);
+
+        let meta = path.iter_with_meta().collect::<Vec<_>>();
+        assert_eq!(
+            meta,
+            vec![
+                SegmentMeta {
+                    index: 0,
+                    is_last: false,
+                    segment: &Segment::literal("files"),
+                },
+                SegmentMeta {
+                    index: 1,
+                    is_last: false,
+                    segment: &Segment::dynamic("owner"),
+                },
+                SegmentMeta {
+                    index: 2,
+                    is_last: true,
+                    segment: &Segment::catch_all("rest"),
+                },
+            ]
+        );
+        assert!(matches!(meta[2].segment, Segment::CatchAll(name) if name == "rest"));
+
+        assert_eq!(Path(vec![]).iter_with_meta().count(), 0);
+    }
+
+    #[test]
+    fn trailing_catch_all() {
+        let path = Path::from_str("/a/[...rest]");
+        assert_eq!(
+            path,
+            Ok(Path(vec![
+                Segment::literal("a"),
+                Segment::catch_all("rest")
+            ]))
+        );
+    }
+
+    #[test]
+    fn misplaced_catch_all() {
+        let path = Path::from_str("/a/[...rest]/b");
+        assert!(matches!(path, Err(PathParseError::MisplacedCatchAll(_))));
+    }
+
+    #[test]
+    fn optional_segments() {
+        let path = Path::from_str("/posts/[page?]").unwrap();
+        assert!(matches!(&path.0[1], Segment::Optional(name) if name == "page"));
+        assert_eq!(path.to_string(), "/posts/[page?]");
+        assert!(Path::from_str("/[a?]/[b?]").is_ok());
+
+        let path = Path::from_str("/posts/[page?]/comments");
+        assert_eq!(
+            path,
+            Err(PathParseError::MisplacedOptional("[page?]".into()))
+        );
+    }
+
+    #[test]
+    fn routes_from_strings() {
+        use crate::Route;
+
+        let literal = Route::<()>::new("/users/[id]");
+        assert_eq!(literal.path, path!("/users/[id]"));
+
+        let resource = String::from("posts");
+        let runtime = Route::<()>::new(format!("/{}/[id:int]", resource));
+        assert_eq!(runtime.path, path!("/posts/[id:int]"));
+        assert_eq!(path!(resource), path!("/posts"));
+        assert_eq!(path!(&format!("/{}", resource)), path!("/posts"));
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid path `/users/[id`")]
+    fn malformed_string_paths_panic() {
+        let _ = Path::from("/users/[id");
+    }
 }
@@ -0,0 +1,168 @@
+use crate::body::full;
+use bytes::Bytes;
+use hyper::StatusCode;
+use hyper::header::{self, HeaderValue};
+use std::borrow::Cow;
+use std::convert::Infallible;
+
+const TEXT_PLAIN: &str = "text/plain; charset=utf-8";
+const OCTET_STREAM: &str = "application/octet-stream";
+
+/// Conversion of a value into a complete response.
+///
+/// Text bodies are sent as `text/plain; charset=utf-8` and byte bodies as
+/// `application/octet-stream`.
+///
+/// # Example
+///
+/// ```
+/// use radmin_router::IntoResponse;
+///
+/// let resp = String::from("OK").into_response();
+/// assert_eq!(resp.headers()["Content-Type"], "text/plain; charset=utf-8");
+/// ```
+pub trait IntoResponse {
+    fn into_response(self) -> crate::Response;
+}
+
+impl IntoResponse for &'static str {
+    fn into_response(self) -> crate::Response {
+        with_content_type(self, TEXT_PLAIN)
+    }
+}
+
+impl IntoResponse for String {
+    fn into_response(self) -> crate::Response {
+        with_content_type(self, TEXT_PLAIN)
+    }
+}
+
+impl IntoResponse for Cow<'static, str> {
+    fn into_response(self) -> crate::Response {
+        match self {
+            Cow::Borrowed(body) => body.into_response(),
+            Cow::Owned(body) => body.into_response(),
+        }
+    }
+}
+
+impl IntoResponse for &'static [u8] {
+    fn into_response(self) -> crate::Response {
+        with_content_type(self, OCTET_STREAM)
+    }
+}
+
+impl IntoResponse for Vec<u8> {
+    fn into_response(self) -> crate::Response {
+        with_content_type(self, OCTET_STREAM)
+    }
+}
+
+impl IntoResponse for Bytes {
+    fn into_response(self) -> crate::Response {
+        with_content_type(self, OCTET_STREAM)
+    }
+}
+
+impl IntoResponse for crate::Response {
+    fn into_response(self) -> crate::Response {
+        self
+    }
+}
+
+/// An empty response with the status.
+impl IntoResponse for StatusCode {
+    fn into_response(self) -> crate::Response {
+        let mut resp = hyper::Response::new(full(Bytes::new()));
+        *resp.status_mut() = self;
+        resp
+    }
+}
+
+/// The body's response with the status replaced.
+impl<T: IntoResponse> IntoResponse for (StatusCode, T) {
+    fn into_response(self) -> crate::Response {
+        let (status, body) = self;
+        let mut resp = body.into_response();
+        *resp.status_mut() = status;
+        resp
+    }
+}
+
+/// The response of either the value or the error.
+impl<T: IntoResponse, E: IntoResponse> IntoResponse for Result<T, E> {
+    fn into_response(self) -> crate::Response {
+        match self {
+            Ok(value) => value.into_response(),
+            Err(err) => err.into_response(),
+        }
+    }
+}
+
+impl IntoResponse for Infallible {
+    fn into_response(self) -> crate::Response {
+        match self {}
+    }
+}
+
+fn with_content_type(body: impl Into<Bytes>, content_type: &'static str) -> crate::Response {
+    let mut resp = hyper::Response::new(full(body));
+    resp.headers_mut()
+        .insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+    resp
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_body_util::BodyExt;
+
+    async fn parts(resp: crate::Response) -> (String, Bytes) {
+        let content_type = resp.headers()[header::CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        (content_type, body)
+    }
+
+    #[tokio::test]
+    async fn cow_str() {
+        let borrowed = parts(Cow::Borrowed("borrowed").into_response()).await;
+        assert_eq!(borrowed, (TEXT_PLAIN.into(), Bytes::from("borrowed")));
+
+        let owned = parts(Cow::<str>::Owned("owned".into()).into_response()).await;
+        assert_eq!(owned, (TEXT_PLAIN.into(), Bytes::from("owned")));
+    }
+
+    #[tokio::test]
+    async fn status_and_body() {
+        let resp = (StatusCode::CREATED, String::from("done")).into_response();
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        assert_eq!(parts(resp).await, (TEXT_PLAIN.into(), Bytes::from("done")));
+
+        let resp = StatusCode::NO_CONTENT.into_response();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert!(
+            resp.into_body()
+                .collect()
+                .await
+                .unwrap()
+                .to_bytes()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn byte_vec() {
+        let resp = parts(vec![0u8, 1, 2].into_response()).await;
+        assert_eq!(resp, (OCTET_STREAM.into(), Bytes::from_static(&[0, 1, 2])));
+    }
+
+    #[tokio::test]
+    async fn static_bytes() {
+        let body: &'static [u8] = b"\x00raw";
+        let resp = parts(body.into_response()).await;
+        assert_eq!(resp, (OCTET_STREAM.into(), Bytes::from_static(b"\x00raw")));
+    }
+}
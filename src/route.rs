@@ -1,32 +1,100 @@
+use crate::body::full;
 use crate::context::Context;
 use crate::path::Path;
+use crate::response::IntoResponse;
 use bytes::Bytes;
 use http_body_util::combinators::BoxBody;
+use hyper::body::Body;
 use hyper::body::Incoming;
-use hyper::{Method, Request};
+use hyper::header::{self, HeaderMap, HeaderName, HeaderValue};
+use hyper::{Method, Request, StatusCode};
+use macros::CaseIterable;
 use std::collections::HashMap;
 use std::fmt::{Debug, Formatter};
 use std::pin::Pin;
+use std::sync::Arc;
 
 /// The standard return type for all handlers. Returned to hyper.
-pub type Response = Result<hyper::Response<BoxBody<Bytes, hyper::Error>>, hyper::Error>;
+pub type Response = Result<hyper::Response<BoxBody<Bytes, crate::BodyError>>, crate::BodyError>;
 /// The return type of async request handlers.
 pub type ResponseFut = dyn Future<Output = Response> + Send + 'static;
 
-/// A function pointer type for HTTP request handlers.
-pub type Handler<Extra> = fn(Request<Incoming>, Context<Extra>) -> Pin<Box<ResponseFut>>;
+/// A shared HTTP request handler, as stored by a [`Route`].
+pub type Handler<Extra> =
+    Arc<dyn Fn(Request<Incoming>, Context<Extra>) -> Pin<Box<ResponseFut>> + Send + Sync>;
+
+/// Functions and closures usable as request handlers, including closures
+/// capturing state such as a connection pool.
+pub trait HandlerFn<Extra>:
+    Fn(Request<Incoming>, Context<Extra>) -> Pin<Box<ResponseFut>> + Send + Sync + 'static
+{
+}
+
+impl<Extra, F> HandlerFn<Extra> for F where
+    F: Fn(Request<Incoming>, Context<Extra>) -> Pin<Box<ResponseFut>> + Send + Sync + 'static
+{
+}
+
+/// Adapts an async closure or function into a [`HandlerFn`], boxing the
+/// futures it returns so handlers needn't wrap their bodies in
+/// `Box::pin(async move { ... })`.
+///
+/// # Example
+///
+/// ```
+/// use hyper::Response;
+/// use radmin_router::{Route, async_handler, full, path};
+///
+/// Route::<()>::new(path!("/users/[id]")).get(async_handler(|_, ctx| async move {
+///     Ok(Response::new(full(format!("user {}", ctx.params[0]))))
+/// }));
+/// ```
+pub fn async_handler<Extra, F, Fut>(handler: F) -> impl HandlerFn<Extra>
+where
+    F: Fn(Request<Incoming>, Context<Extra>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Response> + Send + 'static,
+{
+    move |req, ctx| -> Pin<Box<ResponseFut>> { Box::pin(handler(req, ctx)) }
+}
 
 /// A route representing a single endpoint (including all matching dynamic segments and HTTP methods).
 pub struct Route<Extra: Send + Sync> {
     pub path: Path,
     handlers: HashMap<Method, Handler<Extra>>,
+    any: Option<Handler<Extra>>,
+    guards: Vec<HeaderGuard>,
+    exact: bool,
+    cache_control: Option<HeaderValue>,
+    auto_head: bool,
+    name: Option<String>,
+    priority: i32,
+}
+
+/// A request header that must be present, with a given value, for a route to match.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct HeaderGuard {
+    name: HeaderName,
+    value: HeaderValue,
+    ignore_case: bool,
+}
+
+impl HeaderGuard {
+    fn matches(&self, headers: &HeaderMap) -> bool {
+        headers.get_all(&self.name).iter().any(|value| {
+            if self.ignore_case {
+                value.as_bytes().eq_ignore_ascii_case(self.value.as_bytes())
+            } else {
+                value == self.value
+            }
+        })
+    }
 }
 
 impl<Extra: Send + Sync> Route<Extra> {
     /// Constructs a new `Route<Extra>` with the provided path.
-    /// 
+    ///
     /// # Example
-    /// 
+    ///
     /// ```
     /// use radmin_router::{path, Route};
     /// Route::<()>::new(path!("/path/[to]/resource"));
@@ -35,66 +103,631 @@ impl<Extra: Send + Sync> Route<Extra> {
         Self {
             path: path.into(),
             handlers: Default::default(),
+            any: None,
+            guards: vec![],
+            exact: false,
+            cache_control: None,
+            auto_head: false,
+            name: None,
+            priority: 0,
+        }
+    }
+
+    /// Constructs a new `Route<Extra>` with the provided path, handling each
+    /// method in `handlers`, e.g. for routes built from a table.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hyper::Method;
+    /// use radmin_router::{Handler, Route, empty, path};
+    /// use std::collections::HashMap;
+    /// use std::sync::Arc;
+    ///
+    /// let ok: Handler<()> =
+    ///     Arc::new(|_, _| Box::pin(async { Ok(hyper::Response::new(empty())) }));
+    /// let handlers = HashMap::from([(Method::GET, ok.clone()), (Method::PUT, ok)]);
+    /// Route::<()>::with_handlers(path!("/resource"), handlers);
+    /// ```
+    pub fn with_handlers(path: impl Into<Path>, handlers: HashMap<Method, Handler<Extra>>) -> Self {
+        Self {
+            handlers,
+            ..Self::new(path)
         }
     }
 
-    /// Returns the methods for which this route has registered handlers.
+    /// Requires the request to carry the header `name` with exactly `value`
+    /// for this route to match. Header names are always case-insensitive.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hyper::header::{HeaderName, HeaderValue};
+    /// use radmin_router::{path, Route};
+    ///
+    /// Route::<()>::new(path!("/deploy"))
+    ///     .require_header(HeaderName::from_static("x-env"), HeaderValue::from_static("prod"));
+    /// ```
+    pub fn require_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.guards.push(HeaderGuard {
+            name,
+            value,
+            ignore_case: false,
+        });
+        self
+    }
+
+    /// Like [`Route::require_header`], but compares the value ASCII-case-insensitively.
+    pub fn require_header_ignore_case(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.guards.push(HeaderGuard {
+            name,
+            value,
+            ignore_case: true,
+        });
+        self
+    }
+
+    /// Marks the route as exact: once a request follows the literal segments
+    /// leading to it, the router no longer falls back to dynamic or catch-all
+    /// siblings of those segments, responding `404 Not Found` instead if the
+    /// rest of the path doesn't match.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::{path, Route};
+    ///
+    /// // `/users/admin/profile` won't fall back to `/users/[id]/profile`.
+    /// Route::<()>::new(path!("/users/admin/settings")).exact();
+    /// ```
+    pub fn exact(mut self) -> Self {
+        self.exact = true;
+        self
+    }
+
+    /// Returns whether the route was marked [`exact`](Self::exact).
+    pub fn is_exact(&self) -> bool {
+        self.exact
+    }
+
+    /// Sets the `Cache-Control` header on responses from this route's
+    /// handlers, unless the handler set one itself.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hyper::header::HeaderValue;
+    /// use radmin_router::{path, Route};
+    ///
+    /// Route::<()>::new(path!("/assets/[...path]"))
+    ///     .cache_control(HeaderValue::from_static("public, max-age=3600"));
+    /// ```
+    pub fn cache_control(mut self, directive: HeaderValue) -> Self {
+        self.cache_control = Some(directive);
+        self
+    }
+
+    /// Names the route, so its URL can be built with
+    /// [`Router::url_for`](crate::Router::url_for).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::{path, Route};
+    ///
+    /// Route::<()>::new(path!("/users/[id]")).name("users.show");
+    /// ```
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// Ranks the route against others matching the same request path, ahead
+    /// of how specific their segments are: the route with the highest
+    /// priority wins, and routes with equal priorities fall back to the
+    /// usual precedence. Routes default to priority 0.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::{path, Route};
+    ///
+    /// // Shadows every other route while enabled.
+    /// Route::<()>::new(path!("/[...path]")).priority(100);
+    /// ```
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub(crate) fn priority_value(&self) -> i32 {
+        self.priority
+    }
+
+    pub(crate) fn route_name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub(crate) fn cache_control_value(&self) -> Option<&HeaderValue> {
+        self.cache_control.as_ref()
+    }
+
+    /// Answers HEAD requests with the GET handler when no HEAD handler is
+    /// registered, keeping the status and headers but dropping the body.
+    /// Both methods are safe, per [`Method::is_safe`], so running the GET
+    /// handler does nothing a HEAD request didn't ask for.
+    ///
+    /// Unless the handler sets `Content-Length` itself, it's set to the length
+    /// the body would have had if that's known without reading the body, and
+    /// omitted for streaming bodies.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hyper::Response;
+    /// use radmin_router::{full, path, Route};
+    ///
+    /// Route::<()>::new(path!("/status"))
+    ///     .get(|_, _| Box::pin(async { Ok(Response::new(full("OK"))) }))
+    ///     .auto_head();
+    /// ```
+    pub fn auto_head(mut self) -> Self {
+        self.auto_head = true;
+        self
+    }
+
+    /// Returns whether `headers` satisfy every header guard of this route.
+    pub fn guards_match(&self, headers: &HeaderMap) -> bool {
+        self.guards.iter().all(|guard| guard.matches(headers))
+    }
+
+    /// Returns the methods for which this route has registered handlers,
+    /// including HEAD when answered by the GET handler per
+    /// [`auto_head`](Self::auto_head).
+    ///
+    /// Standard methods come first, in the order of [`HttpMethod`]: GET, HEAD,
+    /// POST, PUT, PATCH, DELETE, OPTIONS, CONNECT, TRACE, followed by
+    /// extension methods sorted by name.
     pub fn allowed_methods(&self) -> Vec<Method> {
-        self.handlers.keys().map(|m| m.clone()).collect()
+        let mut methods: Vec<_> = self.handlers.keys().cloned().collect();
+        if self.synthesizes_head() {
+            methods.push(Method::HEAD);
+        }
+        HttpMethod::sort(&mut methods);
+        methods
+    }
+
+    /// Returns whether a handler is registered for `method` itself.
+    pub(crate) fn has_handler(&self, method: &Method) -> bool {
+        self.handlers.contains_key(method)
+    }
+
+    pub(crate) fn handler(&self, method: &Method) -> Option<Handler<Extra>>
+    where
+        Extra: 'static,
+    {
+        match self.handlers.get(method) {
+            Some(handler) => Some(Arc::clone(handler)),
+            None if *method == Method::HEAD && self.synthesizes_head() => {
+                let get = Arc::clone(&self.handlers[&Method::GET]);
+                Some(Arc::new(move |req, ctx| -> Pin<Box<ResponseFut>> {
+                    let resp = get(req, ctx);
+
+                    Box::pin(async move {
+                        let (mut parts, body) = resp.await?.into_parts();
+                        // Handlers that check for HEAD themselves may set the
+                        // length while leaving the body empty.
+                        if let Some(len) = body.size_hint().exact() {
+                            parts
+                                .headers
+                                .entry(header::CONTENT_LENGTH)
+                                .or_insert(len.into());
+                        }
+                        Ok(hyper::Response::from_parts(parts, full(Bytes::new())))
+                    })
+                }))
+            }
+            None => self.any.clone(),
+        }
+    }
+
+    /// Returns whether HEAD requests are answered by the GET handler.
+    fn synthesizes_head(&self) -> bool {
+        self.auto_head && self.has_handler(&Method::GET) && !self.has_handler(&Method::HEAD)
+    }
+
+    /// Sets the handler for `method`, replacing any existing one.
+    pub fn set_handler(&mut self, method: Method, handler: impl HandlerFn<Extra>) -> &mut Self {
+        self.handlers.insert(method, Arc::new(handler));
+        self
+    }
+
+    /// Removes the handlers for all methods.
+    pub fn clear_handlers(&mut self) -> &mut Self {
+        self.handlers.clear();
+        self
+    }
+
+    /// Merges `other` into this route. Handlers of `other` replace existing
+    /// handlers for the same method.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the routes have different header guards, which apply to
+    /// every method of a route, so that neither route's guards end up
+    /// restricting the other's handlers.
+    pub fn merge(&mut self, other: Route<Extra>) -> &mut Self {
+        assert!(
+            self.guards.len() == other.guards.len()
+                && other.guards.iter().all(|guard| self.guards.contains(guard)),
+            "cannot merge routes for `{}` with different header guards",
+            self.path
+        );
+
+        self.handlers.extend(other.handlers);
+        if other.any.is_some() {
+            self.any = other.any;
+        }
+        self.exact |= other.exact;
+        self.auto_head |= other.auto_head;
+        if other.cache_control.is_some() {
+            self.cache_control = other.cache_control;
+        }
+        if other.name.is_some() {
+            self.name = other.name;
+        }
+        if other.priority != 0 {
+            self.priority = other.priority;
+        }
+        self
+    }
+
+    /// Registers a handler for `method` that runs on tokio's blocking thread
+    /// pool, for CPU-bound or blocking work. Responds with
+    /// `500 Internal Server Error` if the handler panics.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hyper::{Method, Response};
+    /// use radmin_router::{full, path, Route};
+    ///
+    /// Route::<()>::new(path!("/report")).blocking(Method::GET, |_, _| {
+    ///     let report = std::fs::read_to_string("/etc/hostname").unwrap_or_default();
+    ///     Ok(Response::new(full(report)))
+    /// });
+    /// ```
+    pub fn blocking<F>(self, method: Method, handler: F) -> Self
+    where
+        F: Fn(Request<Incoming>, Context<Extra>) -> Response + Send + Sync + 'static,
+        Extra: 'static,
+    {
+        let handler = Arc::new(handler);
+
+        self.method(method, move |req, ctx| {
+            let handler = Arc::clone(&handler);
+
+            Box::pin(async move {
+                match tokio::task::spawn_blocking(move || handler(req, ctx)).await {
+                    Ok(resp) => resp,
+                    Err(_) => Ok(hyper::Response::builder()
+                        .status(StatusCode::INTERNAL_SERVER_ERROR)
+                        .body(full("Internal Server Error"))
+                        .unwrap()),
+                }
+            })
+        })
     }
 
-    pub(crate) fn handler(&self, method: &Method) -> Option<Handler<Extra>> {
-        self.handlers.get(method).cloned()
+    /// Registers a handler for `method`, including extension methods such as
+    /// WebDAV's `REPORT`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hyper::{Method, Response};
+    /// use radmin_router::{full, path, Route};
+    ///
+    /// let report = Method::from_bytes(b"REPORT").unwrap();
+    /// Route::<()>::new(path!("/calendars/[id]")).method(report, |_, _| {
+    ///     Box::pin(async { Ok(Response::new(full("<multistatus/>"))) })
+    /// });
+    /// ```
+    pub fn method(mut self, method: Method, handler: impl HandlerFn<Extra>) -> Self {
+        self.set_handler(method, handler);
+        self
     }
 
-    fn register(mut self, method: Method, handler: Handler<Extra>) -> Self {
-        self.handlers.insert(method, handler);
+    /// Registers `handler` for each of `methods`, e.g. for PUT and PATCH
+    /// sharing an update handler.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hyper::{Method, Response};
+    /// use radmin_router::{Route, full, path};
+    ///
+    /// Route::<()>::new(path!("/users/[id]")).methods(&[Method::PUT, Method::PATCH], |_, _| {
+    ///     Box::pin(async { Ok(Response::new(full("updated"))) })
+    /// });
+    /// ```
+    pub fn methods(mut self, methods: &[Method], handler: impl HandlerFn<Extra>) -> Self {
+        let handler: Handler<Extra> = Arc::new(handler);
+        for method in methods {
+            self.handlers.insert(method.clone(), Arc::clone(&handler));
+        }
         self
     }
 
+    fn register_into<F, Fut>(self, method: Method, handler: F) -> Self
+    where
+        F: Fn(Request<Incoming>, Context<Extra>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output: IntoResponse> + Send + 'static,
+    {
+        self.method(method, move |req, ctx| {
+            let resp = handler(req, ctx);
+            Box::pin(async move { Ok(resp.await.into_response()) })
+        })
+    }
+
+    /// Registers a handler for requests with any method that has no handler
+    /// of its own, instead of responding with `405 Method Not Allowed`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hyper::{Response, StatusCode};
+    /// use radmin_router::{full, path, Route};
+    ///
+    /// Route::<()>::new(path!("/[...rest]")).any(|_, _| {
+    ///     Box::pin(async {
+    ///         Ok(Response::builder()
+    ///             .status(StatusCode::SERVICE_UNAVAILABLE)
+    ///             .body(full("Down for maintenance"))
+    ///             .unwrap())
+    ///     })
+    /// });
+    /// ```
+    pub fn any(mut self, handler: impl HandlerFn<Extra>) -> Self {
+        self.any = Some(Arc::new(handler));
+        self
+    }
+
+    /// Registers a handler for GET requests returning any [`IntoResponse`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hyper::StatusCode;
+    /// use radmin_router::{path, Route};
+    ///
+    /// Route::<()>::new(path!("/items")).get_into(|_, _| async { (StatusCode::OK, "items") });
+    /// ```
+    pub fn get_into<F, Fut>(self, handler: F) -> Self
+    where
+        F: Fn(Request<Incoming>, Context<Extra>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output: IntoResponse> + Send + 'static,
+    {
+        self.register_into(Method::GET, handler)
+    }
+
+    /// Registers a handler for POST requests returning any [`IntoResponse`].
+    pub fn post_into<F, Fut>(self, handler: F) -> Self
+    where
+        F: Fn(Request<Incoming>, Context<Extra>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output: IntoResponse> + Send + 'static,
+    {
+        self.register_into(Method::POST, handler)
+    }
+
+    /// Registers a fallible handler for `method`, responding with the
+    /// [`IntoResponse`] conversion of the error it returns, e.g. an
+    /// application error type mapping its variants to statuses.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hyper::{Method, StatusCode};
+    /// use radmin_router::{IntoResponse, path, Route};
+    ///
+    /// enum AppError {
+    ///     NotFound,
+    /// }
+    ///
+    /// impl IntoResponse for AppError {
+    ///     fn into_response(self) -> radmin_router::Response {
+    ///         match self {
+    ///             AppError::NotFound => (StatusCode::NOT_FOUND, "no such user").into_response(),
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// Route::<()>::new(path!("/users/[id]")).try_method(Method::GET, |_, ctx| async move {
+    ///     match ctx.param("id") {
+    ///         Some("1") => Ok("alice"),
+    ///         _ => Err(AppError::NotFound),
+    ///     }
+    /// });
+    /// ```
+    pub fn try_method<F, Fut, T, E>(self, method: Method, handler: F) -> Self
+    where
+        F: Fn(Request<Incoming>, Context<Extra>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<T, E>> + Send + 'static,
+        T: IntoResponse,
+        E: IntoResponse,
+    {
+        self.register_into(method, handler)
+    }
+
+    /// Registers a fallible handler for GET requests. See
+    /// [`try_method`](Self::try_method).
+    pub fn try_get<F, Fut, T, E>(self, handler: F) -> Self
+    where
+        F: Fn(Request<Incoming>, Context<Extra>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<T, E>> + Send + 'static,
+        T: IntoResponse,
+        E: IntoResponse,
+    {
+        self.try_method(Method::GET, handler)
+    }
+
+    /// Registers a fallible handler for POST requests. See
+    /// [`try_method`](Self::try_method).
+    pub fn try_post<F, Fut, T, E>(self, handler: F) -> Self
+    where
+        F: Fn(Request<Incoming>, Context<Extra>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<T, E>> + Send + 'static,
+        T: IntoResponse,
+        E: IntoResponse,
+    {
+        self.try_method(Method::POST, handler)
+    }
+
     /// Registers a handler for GET requests.
-    pub fn get(self, handler: Handler<Extra>) -> Self {
-        self.register(Method::GET, handler)
+    pub fn get(self, handler: impl HandlerFn<Extra>) -> Self {
+        self.method(Method::GET, handler)
     }
 
     /// Registers a handler for POST requests.
-    pub fn post(self, handler: Handler<Extra>) -> Self {
-        self.register(Method::POST, handler)
+    pub fn post(self, handler: impl HandlerFn<Extra>) -> Self {
+        self.method(Method::POST, handler)
     }
 
     /// Registers a handler for PUT requests.
-    pub fn put(self, handler: Handler<Extra>) -> Self {
-        self.register(Method::PUT, handler)
+    pub fn put(self, handler: impl HandlerFn<Extra>) -> Self {
+        self.method(Method::PUT, handler)
     }
 
     /// Registers a handler for DELETE requests.
-    pub fn delete(self, handler: Handler<Extra>) -> Self {
-        self.register(Method::DELETE, handler)
+    pub fn delete(self, handler: impl HandlerFn<Extra>) -> Self {
+        self.method(Method::DELETE, handler)
     }
 
     /// Registers a handler for HEAD requests.
-    pub fn head(self, handler: Handler<Extra>) -> Self {
-        self.register(Method::HEAD, handler)
+    pub fn head(self, handler: impl HandlerFn<Extra>) -> Self {
+        self.method(Method::HEAD, handler)
     }
 
     /// Registers a handler for OPTIONS requests.
-    pub fn options(self, handler: Handler<Extra>) -> Self {
-        self.register(Method::OPTIONS, handler)
+    pub fn options(self, handler: impl HandlerFn<Extra>) -> Self {
+        self.method(Method::OPTIONS, handler)
     }
 
     /// Registers a handler for CONNECT requests.
-    pub fn connect(self, handler: Handler<Extra>) -> Self {
-        self.register(Method::CONNECT, handler)
+    pub fn connect(self, handler: impl HandlerFn<Extra>) -> Self {
+        self.method(Method::CONNECT, handler)
     }
 
     /// Registers a handler for PATCH requests.
-    pub fn patch(self, handler: Handler<Extra>) -> Self {
-        self.register(Method::PATCH, handler)
+    pub fn patch(self, handler: impl HandlerFn<Extra>) -> Self {
+        self.method(Method::PATCH, handler)
     }
 
     /// Registers a handler for TRACE requests.
-    pub fn trace(self, handler: Handler<Extra>) -> Self {
-        self.register(Method::TRACE, handler)
+    pub fn trace(self, handler: impl HandlerFn<Extra>) -> Self {
+        self.method(Method::TRACE, handler)
+    }
+
+    /// Registers a GET handler serving an embedded asset, as created by
+    /// [`static_file`](crate::static_file). The `ETag` is hashed once, and
+    /// requests carrying it in `If-None-Match` get `304 Not Modified`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `content_type` isn't a valid header value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::{Route, path};
+    ///
+    /// Route::<()>::new(path!("/favicon.ico")).serve_static(b"\0\0\x01\0", "image/x-icon");
+    /// ```
+    #[cfg(feature = "util")]
+    pub fn serve_static(self, bytes: &'static [u8], content_type: &str) -> Self {
+        let content_type = HeaderValue::from_str(content_type).expect("invalid Content-Type");
+        let etag = crate::util::static_etag(bytes);
+
+        self.get(move |req, _| {
+            let resp = if crate::middleware::if_none_match(req.headers(), &etag) {
+                hyper::Response::builder()
+                    .status(StatusCode::NOT_MODIFIED)
+                    .header(header::ETAG, etag.clone())
+                    .body(full(Bytes::new()))
+                    .unwrap()
+            } else {
+                crate::util::static_response(bytes, content_type.clone(), etag.clone())
+            };
+
+            Box::pin(async move { Ok(resp) })
+        })
+    }
+}
+
+/// A mutable view of a route already registered with a
+/// [`Router`](crate::Router), returned by
+/// [`Router::route_path`](crate::Router::route_path).
+///
+/// Handlers added through the view replace existing handlers for the same
+/// method and leave handlers for other methods in place.
+pub struct RouteMut<'a, Extra: Send + Sync>(pub(crate) &'a mut Route<Extra>);
+
+impl<Extra: Send + Sync> RouteMut<'_, Extra> {
+    /// Adds a handler for `method`, including extension methods.
+    pub fn method(self, method: Method, handler: impl HandlerFn<Extra>) -> Self {
+        self.0.set_handler(method, handler);
+        self
+    }
+
+    /// Adds a handler for GET requests.
+    pub fn get(self, handler: impl HandlerFn<Extra>) -> Self {
+        self.method(Method::GET, handler)
+    }
+
+    /// Adds a handler for POST requests.
+    pub fn post(self, handler: impl HandlerFn<Extra>) -> Self {
+        self.method(Method::POST, handler)
+    }
+
+    /// Adds a handler for PUT requests.
+    pub fn put(self, handler: impl HandlerFn<Extra>) -> Self {
+        self.method(Method::PUT, handler)
+    }
+
+    /// Adds a handler for DELETE requests.
+    pub fn delete(self, handler: impl HandlerFn<Extra>) -> Self {
+        self.method(Method::DELETE, handler)
+    }
+
+    /// Adds a handler for HEAD requests.
+    pub fn head(self, handler: impl HandlerFn<Extra>) -> Self {
+        self.method(Method::HEAD, handler)
+    }
+
+    /// Adds a handler for OPTIONS requests.
+    pub fn options(self, handler: impl HandlerFn<Extra>) -> Self {
+        self.method(Method::OPTIONS, handler)
+    }
+
+    /// Adds a handler for CONNECT requests.
+    pub fn connect(self, handler: impl HandlerFn<Extra>) -> Self {
+        self.method(Method::CONNECT, handler)
+    }
+
+    /// Adds a handler for PATCH requests.
+    pub fn patch(self, handler: impl HandlerFn<Extra>) -> Self {
+        self.method(Method::PATCH, handler)
+    }
+
+    /// Adds a handler for TRACE requests.
+    pub fn trace(self, handler: impl HandlerFn<Extra>) -> Self {
+        self.method(Method::TRACE, handler)
+    }
+}
+
+impl<Extra: Send + Sync> Debug for RouteMut<'_, Extra> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&self.0, f)
     }
 }
 
@@ -103,6 +736,13 @@ impl<Extra: Send + Sync> Clone for Route<Extra> {
         Self {
             path: Clone::clone(&self.path),
             handlers: Clone::clone(&self.handlers),
+            any: Clone::clone(&self.any),
+            guards: Clone::clone(&self.guards),
+            exact: self.exact,
+            cache_control: Clone::clone(&self.cache_control),
+            auto_head: self.auto_head,
+            name: Clone::clone(&self.name),
+            priority: self.priority,
         }
     }
 }
@@ -114,15 +754,208 @@ impl<Extra: Send + Sync> Debug for Route<Extra> {
         for method in self.handlers.keys() {
             debug.field(method);
         }
+        if self.any.is_some() {
+            debug.field(&format_args!("*"));
+        }
 
         debug.finish()
     }
 }
 
+/// The methods defined by RFC 9110 and RFC 5789, in the order they're
+/// listed in `Allow` headers.
+///
+/// [`HttpMethod::ALL_CASES`](macros::CaseIterable::ALL_CASES) is the
+/// canonical method order used by [`Route::allowed_methods`],
+/// [`Router::allowed_methods`](crate::Router::allowed_methods) and the
+/// `Allow` headers of `OPTIONS` and `405 Method Not Allowed` responses.
+#[derive(CaseIterable, Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum HttpMethod {
+    Get,
+    Head,
+    Post,
+    Put,
+    Patch,
+    Delete,
+    Options,
+    Connect,
+    Trace,
+}
+
+impl HttpMethod {
+    /// Returns the standard method `method` is, if it isn't an extension
+    /// method.
+    pub fn from_method(method: &Method) -> Option<Self> {
+        Self::all_cases()
+            .into_iter()
+            .find(|standard| Method::from(*standard) == method)
+    }
+
+    /// Sorts `methods` into canonical order: standard methods in the order of
+    /// `ALL_CASES`, followed by extension methods sorted by name.
+    pub(crate) fn sort(methods: &mut [Method]) {
+        methods.sort_by(|a, b| {
+            Self::position(a)
+                .cmp(&Self::position(b))
+                .then_with(|| a.as_str().cmp(b.as_str()))
+        });
+    }
+
+    /// Returns where `method` sorts, after every standard method if it's an
+    /// extension method.
+    fn position(method: &Method) -> usize {
+        Self::from_method(method).map_or(Self::ALL_CASES.len(), |standard| standard as usize)
+    }
+}
+
+impl From<HttpMethod> for Method {
+    fn from(method: HttpMethod) -> Self {
+        match method {
+            HttpMethod::Get => Method::GET,
+            HttpMethod::Head => Method::HEAD,
+            HttpMethod::Post => Method::POST,
+            HttpMethod::Put => Method::PUT,
+            HttpMethod::Patch => Method::PATCH,
+            HttpMethod::Delete => Method::DELETE,
+            HttpMethod::Options => Method::OPTIONS,
+            HttpMethod::Connect => Method::CONNECT,
+            HttpMethod::Trace => Method::TRACE,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[tokio::test]
+    async fn methods() {
+        use crate::testing::send;
+        use crate::{Router, full, path};
+
+        let route = Route::<()>::new(path!("/users/[id]")).methods(
+            &[Method::PUT, Method::PATCH],
+            |req, ctx| {
+                let body = format!("{} {}", req.method(), ctx.params[0]);
+                Box::pin(async move { Ok(hyper::Response::new(full(body))) })
+            },
+        );
+        assert_eq!(route.allowed_methods(), [Method::PUT, Method::PATCH]);
+
+        let router = Arc::new(Router::<()>::default().register(route));
+        for method in [Method::PUT, Method::PATCH] {
+            let req = Request::builder()
+                .method(method.clone())
+                .uri("/users/7")
+                .body(Default::default())
+                .unwrap();
+            let resp = send(Arc::clone(&router), req).await;
+            assert_eq!(resp.body(), &format!("{method} 7"));
+        }
+    }
+
+    #[tokio::test]
+    async fn async_handlers() {
+        use crate::testing::send;
+        use crate::{Router, path};
+
+        let greeting = Arc::new(String::from("hello"));
+        let router = Router::<()>::default().register(Route::new(path!("/greet/[name]")).get(
+            async_handler(move |_, ctx| {
+                let greeting = Arc::clone(&greeting);
+                async move {
+                    tokio::task::yield_now().await;
+                    Ok(hyper::Response::new(full(format!(
+                        "{greeting}, {}",
+                        ctx.params[0]
+                    ))))
+                }
+            }),
+        ));
+
+        let req = Request::get("/greet/ada").body(Default::default()).unwrap();
+        assert_eq!(send(router, req).await.body(), "hello, ada");
+    }
+
+    #[test]
+    fn method_classification() {
+        // The classification `auto_head` and `Router::timeout` rely on.
+        for (method, safe, idempotent) in [
+            (Method::GET, true, true),
+            (Method::HEAD, true, true),
+            (Method::OPTIONS, true, true),
+            (Method::TRACE, true, true),
+            (Method::PUT, false, true),
+            (Method::DELETE, false, true),
+            (Method::POST, false, false),
+            (Method::PATCH, false, false),
+            (Method::CONNECT, false, false),
+            (Method::from_bytes(b"PURGE").unwrap(), false, false),
+        ] {
+            assert_eq!(method.is_safe(), safe, "{method}");
+            assert_eq!(method.is_idempotent(), idempotent, "{method}");
+        }
+    }
+
+    #[test]
+    fn http_method_round_trips() {
+        for standard in HttpMethod::ALL_CASES {
+            let method = Method::from(*standard);
+            assert_eq!(HttpMethod::from_method(&method), Some(*standard));
+        }
+        let purge = Method::from_bytes(b"PURGE").unwrap();
+        assert_eq!(HttpMethod::from_method(&purge), None);
+    }
+
+    #[tokio::test]
+    async fn allow_follows_http_method_order() {
+        use crate::testing::send;
+        use crate::{Router, path};
+
+        let purge = Method::from_bytes(b"PURGE").unwrap();
+        // Registered in reverse so the order can't come from registration.
+        let mut route =
+            Route::<()>::new(path!("/item")).method(purge.clone(), |_, _| unimplemented!());
+        for method in HttpMethod::ALL_CASES.iter().rev() {
+            if *method != HttpMethod::Options {
+                route = route.method((*method).into(), |_, _| unimplemented!());
+            }
+        }
+
+        let mut router = Router::<()>::default().register(route);
+        router.auto_options(true);
+        let router = Arc::new(router);
+        let allow = |methods: &[HttpMethod]| {
+            methods
+                .iter()
+                .map(|method| Method::from(*method).to_string())
+                .chain([purge.to_string()])
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let mut without_options = HttpMethod::ALL_CASES.to_vec();
+        without_options.retain(|method| *method != HttpMethod::Options);
+
+        let req = Request::options("/item").body(Default::default()).unwrap();
+        let resp = send(Arc::clone(&router), req).await;
+        assert_eq!(
+            resp.headers()[hyper::header::ALLOW],
+            allow(HttpMethod::ALL_CASES).as_str()
+        );
+
+        let req = Request::builder()
+            .method(Method::from_bytes(b"REPORT").unwrap())
+            .uri("/item")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.status(), hyper::StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(
+            resp.headers()[hyper::header::ALLOW],
+            allow(&without_options).as_str()
+        );
+    }
+
     #[test]
     fn allowed_methods() {
         let mut route = Route::<()>::new(vec![]);
@@ -130,13 +963,14 @@ mod tests {
             .get(|_, _| unimplemented!())
             .patch(|_, _| unimplemented!());
 
-        let mut allowed_methods = route.allowed_methods()
+        let mut allowed_methods = route
+            .allowed_methods()
             .into_iter()
             .map(|m| m.to_string())
             .collect::<Vec<_>>();
         allowed_methods.sort();
         let allowed_methods = allowed_methods.join(", ");
-        
+
         assert_eq!(allowed_methods, "GET, PATCH");
     }
 
@@ -148,4 +982,302 @@ mod tests {
         route = route.get(|_, _| unimplemented!());
         assert!(route.handler(&Method::GET).is_some());
     }
+
+    #[test]
+    fn header_guard_case() {
+        let name = HeaderName::from_static("x-env");
+        let mut headers = HeaderMap::new();
+        headers.insert(&name, HeaderValue::from_static("Prod"));
+
+        let strict =
+            Route::<()>::new(vec![]).require_header(name.clone(), HeaderValue::from_static("prod"));
+        assert!(!strict.guards_match(&headers));
+
+        let lenient = Route::<()>::new(vec![])
+            .require_header_ignore_case(name.clone(), HeaderValue::from_static("prod"));
+        assert!(lenient.guards_match(&headers));
+
+        assert!(!lenient.guards_match(&HeaderMap::new()));
+    }
+
+    #[test]
+    fn clear_and_set_handlers() {
+        let mut route = Route::<()>::new(vec![])
+            .get(|_, _| unimplemented!())
+            .post(|_, _| unimplemented!());
+
+        route.clear_handlers();
+        assert!(route.allowed_methods().is_empty());
+
+        route.set_handler(Method::PUT, |_, _| unimplemented!());
+        assert_eq!(route.allowed_methods(), vec![Method::PUT]);
+    }
+
+    #[tokio::test]
+    async fn with_handlers() {
+        use crate::testing::send;
+        use crate::{Router, path};
+
+        let handler = |body: &'static str| -> Handler<()> {
+            Arc::new(move |_, _| Box::pin(async move { Ok(hyper::Response::new(full(body))) }))
+        };
+        let handlers = HashMap::from([
+            (Method::GET, handler("got")),
+            (Method::DELETE, handler("deleted")),
+        ]);
+        let router = std::sync::Arc::new(
+            Router::<()>::default().register(Route::with_handlers(path!("/item"), handlers)),
+        );
+
+        let req = hyper::Request::get("/item")
+            .body(Default::default())
+            .unwrap();
+        assert_eq!(send(router.clone(), req).await.body().as_ref(), b"got");
+        let req = hyper::Request::delete("/item")
+            .body(Default::default())
+            .unwrap();
+        assert_eq!(send(router.clone(), req).await.body().as_ref(), b"deleted");
+        let req = hyper::Request::post("/item")
+            .body(Default::default())
+            .unwrap();
+        assert_eq!(
+            send(router, req).await.status(),
+            hyper::StatusCode::METHOD_NOT_ALLOWED
+        );
+    }
+
+    #[test]
+    fn merge() {
+        let mut route = Route::<()>::new(vec![])
+            .get(|_, _| unimplemented!())
+            .put(|_, _| unimplemented!());
+        route.merge(
+            Route::new(vec![])
+                .post(|_, _| unimplemented!())
+                .put(|_, _| unimplemented!()),
+        );
+
+        let mut allowed_methods = route.allowed_methods();
+        allowed_methods.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        assert_eq!(
+            allowed_methods,
+            vec![Method::GET, Method::POST, Method::PUT]
+        );
+    }
+
+    #[test]
+    fn merge_guards() {
+        let name = HeaderName::from_static("x-env");
+        let guarded = || {
+            Route::<()>::new(vec![])
+                .require_header(name.clone(), HeaderValue::from_static("prod"))
+                .get(|_, _| unimplemented!())
+        };
+
+        let mut route = guarded();
+        route.merge(guarded().post(|_, _| unimplemented!()));
+        assert_eq!(route.allowed_methods(), vec![Method::GET, Method::POST]);
+
+        // The guard of one route mustn't end up restricting the other's
+        // handlers.
+        let panic = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            guarded().merge(Route::new(vec![]).post(|_, _| unimplemented!()));
+        }))
+        .unwrap_err();
+        assert_eq!(
+            panic.downcast_ref::<String>().unwrap(),
+            "cannot merge routes for `/` with different header guards"
+        );
+    }
+
+    #[tokio::test]
+    async fn box_future_handler() {
+        use crate::macros::box_future;
+        use crate::testing::send;
+        use crate::{Router, path};
+
+        #[box_future]
+        async fn handler(_: Request<Incoming>, ctx: Context<()>) -> Response {
+            Ok(hyper::Response::new(full(ctx.params[0].clone())))
+        }
+
+        let router = Router::<()>::default().register(Route::new(path!("/[id]")).get(handler));
+        let req = hyper::Request::get("/42").body(Default::default()).unwrap();
+        assert_eq!(send(router, req).await.body().as_ref(), b"42");
+    }
+
+    #[tokio::test]
+    async fn extension_method() {
+        use crate::testing::send;
+        use crate::{Router, path};
+
+        let report = Method::from_bytes(b"REPORT").unwrap();
+        let route = Route::new(path!("/calendar"))
+            .get(|_, _| Box::pin(async { Ok(hyper::Response::new(full("GET"))) }))
+            .method(report.clone(), |_, _| {
+                Box::pin(async { Ok(hyper::Response::new(full("REPORT"))) })
+            });
+        assert!(route.allowed_methods().contains(&report));
+        let router = Arc::new(Router::<()>::default().register(route));
+
+        let req = hyper::Request::builder()
+            .method(report)
+            .uri("/calendar")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(Arc::clone(&router), req).await;
+        assert_eq!(resp.body().as_ref(), b"REPORT");
+
+        let req = hyper::Request::delete("/calendar")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        let mut allow = resp.headers()[header::ALLOW]
+            .to_str()
+            .unwrap()
+            .split(", ")
+            .collect::<Vec<_>>();
+        allow.sort();
+        assert_eq!(allow, ["GET", "REPORT"]);
+    }
+
+    #[tokio::test]
+    async fn allow_header_order() {
+        use crate::testing::send;
+        use crate::{Router, path};
+
+        let route = || {
+            let mut route = Route::new(path!("/"));
+            for method in [
+                "REPORT", "OPTIONS", "DELETE", "MKCOL", "HEAD", "PUT", "PATCH", "POST",
+            ] {
+                route = route.method(
+                    Method::from_bytes(method.as_bytes()).unwrap(),
+                    |_, _| unreachable!(),
+                );
+            }
+            route.get(|_, _| unreachable!())
+        };
+
+        // Each route's handler map is seeded differently.
+        let expected = [
+            "GET", "HEAD", "POST", "PUT", "PATCH", "DELETE", "OPTIONS", "MKCOL", "REPORT",
+        ];
+        for _ in 0..16 {
+            let methods = route().allowed_methods();
+            assert_eq!(
+                methods.iter().map(Method::as_str).collect::<Vec<_>>(),
+                expected
+            );
+        }
+
+        let router = Router::<()>::default().register(route());
+        let req = hyper::Request::builder()
+            .method(Method::TRACE)
+            .uri("/")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(resp.headers()[header::ALLOW], expected.join(", "));
+    }
+
+    #[tokio::test]
+    async fn any_method() {
+        use crate::testing::send;
+        use crate::{Router, path};
+
+        let router = Arc::new(
+            Router::<()>::default().register(
+                Route::new(path!("/"))
+                    .get(|_, _| Box::pin(async { Ok(hyper::Response::new(full("GET"))) }))
+                    .any(|_, _| Box::pin(async { Ok(hyper::Response::new(full("any"))) })),
+            ),
+        );
+
+        let req = hyper::Request::get("/").body(Default::default()).unwrap();
+        let resp = send(Arc::clone(&router), req).await;
+        assert_eq!(resp.body().as_ref(), b"GET");
+
+        let req = hyper::Request::delete("/")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.body().as_ref(), b"any");
+    }
+
+    #[tokio::test]
+    async fn into_response_handlers() {
+        use crate::testing::send;
+        use crate::{Router, path};
+
+        let router = Router::<()>::default().register(
+            Route::new(path!("/items"))
+                .get_into(|_, _| async { "items" })
+                .post_into(|_, _| async { (StatusCode::CREATED, "done") }),
+        );
+        let router = Arc::new(router);
+
+        let req = hyper::Request::get("/items")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(Arc::clone(&router), req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.body().as_ref(), b"items");
+
+        let req = hyper::Request::post("/items")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        assert_eq!(resp.body().as_ref(), b"done");
+    }
+
+    #[tokio::test]
+    async fn fallible_handlers() {
+        use crate::testing::send;
+        use crate::{Router, path};
+
+        enum AppError {
+            NotFound,
+        }
+
+        impl IntoResponse for AppError {
+            fn into_response(self) -> crate::Response {
+                match self {
+                    AppError::NotFound => hyper::Response::builder()
+                        .status(StatusCode::NOT_FOUND)
+                        .header(header::CONTENT_TYPE, "application/json")
+                        .body(full(r#"{"error":"not found"}"#))
+                        .unwrap(),
+                }
+            }
+        }
+
+        let router = Arc::new(Router::<()>::default().register(
+            Route::new(path!("/users/[id]")).try_get(|_, ctx| async move {
+                match ctx.param("id") {
+                    Some("1") => Ok("alice"),
+                    _ => Err(AppError::NotFound),
+                }
+            }),
+        ));
+
+        let req = hyper::Request::get("/users/1")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(Arc::clone(&router), req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.body().as_ref(), b"alice");
+
+        let req = hyper::Request::get("/users/2")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(resp.headers()[header::CONTENT_TYPE], "application/json");
+        assert_eq!(resp.body().as_ref(), br#"{"error":"not found"}"#);
+    }
 }
@@ -1,30 +1,322 @@
-use crate::context::Context;
-use crate::node::Node;
-use crate::route::Route;
-use crate::segment::Segment;
+#[cfg(feature = "logging")]
+use crate::access_log::{AccessLogFormatter, LogRecord};
+use crate::body::full;
+use crate::context::{Context, MaxBodySize, Secure};
+use crate::encoding::{form_decode, percent_decode, percent_decode_utf8, percent_encode};
+use crate::error_page::{ErrorPage, ErrorPageRenderer, Format};
+use crate::middleware::{Endpoint, Middleware, Next, RateLimit};
+use crate::node::{LiteralKey, Node};
+use crate::path::Path;
+use crate::route::{Handler, HandlerFn, HttpMethod, Route, RouteMut};
+use crate::segment::{PatternPart, Segment};
 use bytes::Bytes;
-use futures::future::BoxFuture;
+use futures::FutureExt;
+use futures::future::{self, BoxFuture};
 use http_body_util::combinators::BoxBody;
-use http_body_util::{BodyExt, Full};
 use hyper::body::Incoming;
-use hyper::{Request, Response, StatusCode, header};
-use std::fmt::Debug;
+use hyper::header::{HeaderMap, HeaderValue};
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode, header};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
+use hyper_util::server::graceful::GracefulShutdown;
+use std::any::Any;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::net::SocketAddr;
+use std::panic::AssertUnwindSafe;
+use std::pin::pin;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, TcpStream};
+#[cfg(feature = "tls")]
+use tokio_rustls::rustls;
 
-#[cfg(feature = "logging")]
-use std::time::Instant;
+pub(crate) type RouteNotFoundHandler<Extra> =
+    Arc<dyn Fn(Request<Incoming>, Arc<Extra>) -> BoxFuture<'static, crate::Result> + Send + Sync>;
+type MethodNotAllowedHandler<Extra> = Arc<
+    dyn Fn(Route<Extra>, Request<Incoming>, Context<Extra>) -> BoxFuture<'static, crate::Result>
+        + Send
+        + Sync,
+>;
+type ConnectionErrorHandler = Arc<dyn Fn(Box<dyn Error + Send + Sync>) + Send + Sync>;
+type RequestCompleteHandler = Arc<dyn Fn(&RequestMetrics<'_>) + Send + Sync>;
+type ForceClosePredicate = Arc<dyn Fn(&crate::Response) -> bool + Send + Sync>;
+pub(crate) type PanicHandler<Extra> = Arc<
+    dyn Fn(Box<dyn Any + Send>, Context<Extra>) -> BoxFuture<'static, crate::Result> + Send + Sync,
+>;
+/// A matched route with its positional and named parameters.
+type RouteMatch<Extra> = (Arc<Route<Extra>>, Vec<String>, HashMap<String, String>);
+
+/// How long [`Router::serve`] waits before accepting again after failing to
+/// accept a connection.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Why a request path didn't produce a [`RouteMatch`].
+///
+/// A path that matches a route lacking a handler for the request method
+/// isn't an error here; it's answered with `405 Method Not Allowed` when
+/// dispatching.
+#[derive(Debug, PartialEq, Eq)]
+enum MatchError {
+    /// No node of the route tree matches the path.
+    NotFound,
+    /// The path leads to a node of the route tree, but only to one without a
+    /// route, such as `/users` when only `/users/[id]` is registered.
+    NoRoute,
+    /// A dynamic parameter decodes to invalid UTF-8, or to control
+    /// characters per [`Router::strict_params`].
+    InvalidParam,
+}
+
+/// A handled request, as reported to
+/// [`Router::on_request_complete`].
+#[derive(Clone, Debug)]
+pub struct RequestMetrics<'a> {
+    pub method: &'a Method,
+    /// The path of the matched route, or `None` if no route matched.
+    pub route: Option<&'a Path>,
+    /// The response status, or `None` if the handler failed.
+    pub status: Option<StatusCode>,
+    /// The time taken to produce the response.
+    pub elapsed: Duration,
+}
+
+/// The state of matching a single request path.
+struct MatchState<'a, Extra: Send + Sync> {
+    /// Looking up each literal segment reuses one key, so matching only
+    /// allocates for the segments and the parameters.
+    key: LiteralKey,
+    /// Whether the segments led to a node, with or without a route.
+    reached_node: bool,
+    /// Whether routes have [priorities](Route::priority), so matching visits
+    /// every candidate rather than stopping at the first.
+    prioritized: bool,
+    /// The highest-priority candidate so far, the most specific one among
+    /// equals.
+    best: Option<&'a Arc<Route<Extra>>>,
+}
+
+/// Which form of the request path is used for route matching.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MatchOn {
+    /// Match against the path exactly as received. An encoded slash (`%2F`)
+    /// stays inside its segment, so `/a%2Fb` is a single segment, captured by
+    /// a dynamic segment as `a/b`.
+    #[default]
+    Raw,
+    /// Percent-decode the path before splitting it into segments, so `/a%2Fb`
+    /// is treated as `/a/b`.
+    ///
+    /// This lets clients smuggle segment separators past anything that
+    /// inspects the raw path (proxies, access rules), so only use it when
+    /// every layer in front of the router agrees on the decoded form. `+` is
+    /// never treated as a space in paths.
+    Decoded,
+}
+
+/// Which HTTP versions [`Router::serve`] accepts on a connection, set with
+/// [`Router::protocol`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Protocol {
+    /// HTTP/1.0 and HTTP/1.1 only.
+    #[default]
+    Http1,
+    /// HTTP/2 only, e.g. behind a proxy speaking HTTP/2 to its upstreams.
+    #[cfg(feature = "http2")]
+    Http2,
+    /// HTTP/1 or HTTP/2, told apart by the HTTP/2 connection preface. This
+    /// accepts HTTP/2 with prior knowledge as well as connections where TLS
+    /// negotiated `h2` through ALPN.
+    #[cfg(feature = "http2")]
+    Auto,
+}
+
+/// How request paths with a trailing slash are matched, set with
+/// [`Router::trailing_slash`]. Registered paths never have one, as empty
+/// segments are ignored when parsing a [`Path`](crate::Path).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TrailingSlash {
+    /// Only `/foo` matches a route registered as `/foo`; `/foo/` doesn't.
+    Strict,
+    /// `/foo` and `/foo/` match the same route.
+    #[default]
+    Lenient,
+    /// `/foo/` is redirected to `/foo` with `308 Permanent Redirect`, keeping
+    /// the query string, if `/foo` matches a route.
+    RedirectToNoSlash,
+}
+
+/// A route rejected by [`Router::try_register`] since a route with an
+/// equivalent path, differing at most in dynamic segment names, is already
+/// registered.
+pub struct RouteConflict<Extra: Send + Sync> {
+    /// The route already registered.
+    pub existing: Box<Route<Extra>>,
+    /// The route that was not registered.
+    pub rejected: Box<Route<Extra>>,
+}
+
+impl<Extra: Send + Sync> Debug for RouteConflict<Extra> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RouteConflict")
+            .field("existing", &self.existing.path)
+            .field("rejected", &self.rejected.path)
+            .finish()
+    }
+}
+
+impl<Extra: Send + Sync> Display for RouteConflict<Extra> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "route `{}` conflicts with registered route `{}`",
+            self.rejected.path, self.existing.path
+        )
+    }
+}
+
+impl<Extra: Send + Sync> Error for RouteConflict<Extra> {}
+
+/// The handlers of a RESTful resource, registered with
+/// [`Router::resource`]. Handlers left unset aren't registered.
+pub struct ResourceHandlers<Extra> {
+    /// Lists the resources, for `GET /base`.
+    pub index: Option<Handler<Extra>>,
+    /// Creates a resource, for `POST /base`.
+    pub create: Option<Handler<Extra>>,
+    /// Returns a resource, for `GET /base/[id]`.
+    pub show: Option<Handler<Extra>>,
+    /// Replaces a resource, for `PUT /base/[id]`.
+    pub update: Option<Handler<Extra>>,
+    /// Deletes a resource, for `DELETE /base/[id]`.
+    pub destroy: Option<Handler<Extra>>,
+}
+
+impl<Extra> Default for ResourceHandlers<Extra> {
+    fn default() -> Self {
+        Self {
+            index: None,
+            create: None,
+            show: None,
+            update: None,
+            destroy: None,
+        }
+    }
+}
+
+impl<Extra> Clone for ResourceHandlers<Extra> {
+    fn clone(&self) -> Self {
+        Self {
+            index: Clone::clone(&self.index),
+            create: Clone::clone(&self.create),
+            show: Clone::clone(&self.show),
+            update: Clone::clone(&self.update),
+            destroy: Clone::clone(&self.destroy),
+        }
+    }
+}
+
+impl<Extra> ResourceHandlers<Extra> {
+    /// Sets the handler listing the resources.
+    pub fn index(mut self, handler: impl HandlerFn<Extra>) -> Self {
+        self.index = Some(Arc::new(handler));
+        self
+    }
+
+    /// Sets the handler creating a resource.
+    pub fn create(mut self, handler: impl HandlerFn<Extra>) -> Self {
+        self.create = Some(Arc::new(handler));
+        self
+    }
+
+    /// Sets the handler returning a resource.
+    pub fn show(mut self, handler: impl HandlerFn<Extra>) -> Self {
+        self.show = Some(Arc::new(handler));
+        self
+    }
 
-type RouteNotFoundHandler<Extra> =
-    fn(Request<Incoming>, Arc<Extra>) -> BoxFuture<'static, crate::Result>;
-type MethodNotAllowedHandler<Extra> =
-    fn(Route<Extra>, Request<Incoming>, Context<Extra>) -> BoxFuture<'static, crate::Result>;
+    /// Sets the handler replacing a resource.
+    pub fn update(mut self, handler: impl HandlerFn<Extra>) -> Self {
+        self.update = Some(Arc::new(handler));
+        self
+    }
+
+    /// Sets the handler deleting a resource.
+    pub fn destroy(mut self, handler: impl HandlerFn<Extra>) -> Self {
+        self.destroy = Some(Arc::new(handler));
+        self
+    }
+}
 
-#[derive(Debug)]
+/// Routes requests to the handlers of registered [`Route`]s.
+///
+/// # Route precedence
+///
+/// When several routes match a request path, the most specific one wins,
+/// regardless of registration order. Routes are compared segment by
+/// segment from the left, and the first segment where they differ decides,
+/// ranked from most to least specific:
+///
+/// 1. literal segments, e.g. `users`;
+/// 2. patterns, e.g. `[name].png`, those with more literal text first;
+/// 3. constrained segments, e.g. `[id:int]`;
+/// 4. other dynamic segments, e.g. `[id]`;
+/// 5. optional segments, e.g. `[page?]`;
+/// 6. catch-alls, e.g. `[...rest]`.
+///
+/// So `/a/b/[y]` beats `/a/[x]/c` for `/a/b/c`, since `b` is more specific
+/// than `[x]`. A more specific branch that doesn't lead to a matching route
+/// is passed over, unless a route in it is [exact](Route::exact).
+///
+/// In particular, a literal always beats a catch-all at the same node:
+/// with `/files/readme` and `/files/[...path]` registered, `/files/readme`
+/// matches the former and `/files/a/b` the latter. A catch-all captures the
+/// rest of the path without leading or trailing slashes, `a/b` here, and
+/// captures nothing for `/files` itself when no route is registered there,
+/// or for `/files/` unless trailing slashes are [strict](TrailingSlash::Strict).
+///
+/// A route's [priority](Route::priority) overrides all of this: the matching
+/// route with the highest priority wins, and specificity only decides
+/// between routes of equal priority.
 pub struct Router<Extra: Send + Sync> {
     ex: Arc<Extra>,
     root: Node<Extra>,
-    route_not_found: RouteNotFoundHandler<Extra>,
-    method_not_allowed: MethodNotAllowedHandler<Extra>,
+    route_not_found: Option<RouteNotFoundHandler<Extra>>,
+    error_pages: Option<Arc<dyn ErrorPageRenderer>>,
+    method_not_allowed: Option<MethodNotAllowedHandler<Extra>>,
+    error_format: Format,
+    match_on: MatchOn,
+    trailing_slash: TrailingSlash,
+    index_route: Option<String>,
+    normalize_slashes: bool,
+    case_insensitive: bool,
+    layers: Arc<Vec<Middleware<Extra>>>,
+    force_close_after: Option<ForceClosePredicate>,
+    default_headers: HeaderMap,
+    timeout: Option<Duration>,
+    timeout_status: StatusCode,
+    max_body_size: Option<usize>,
+    max_path_segments: usize,
+    trust_forwarded_proto: bool,
+    strict_query: bool,
+    strict_params: bool,
+    auto_options: bool,
+    on_connection_error: Option<ConnectionErrorHandler>,
+    on_request_complete: Option<RequestCompleteHandler>,
+    protocol: Protocol,
+    #[cfg(feature = "logging")]
+    access_log: Option<AccessLogFormatter>,
+    /// Paths of [named](Route::name) routes.
+    names: HashMap<String, Path>,
+    /// Routers for requests to specific hosts, as lowercase host patterns.
+    hosts: Vec<(String, Arc<Router<Extra>>)>,
+    /// The router consulted for requests matching no route, per
+    /// [`Router::or_else`].
+    fallback: Option<Arc<Router<Extra>>>,
 }
 
 impl<Extra: Send + Sync> Clone for Router<Extra> {
@@ -33,45 +325,157 @@ impl<Extra: Send + Sync> Clone for Router<Extra> {
             ex: Clone::clone(&self.ex),
             root: Clone::clone(&self.root),
             route_not_found: Clone::clone(&self.route_not_found),
+            error_pages: Clone::clone(&self.error_pages),
             method_not_allowed: Clone::clone(&self.method_not_allowed),
+            error_format: self.error_format,
+            match_on: self.match_on,
+            trailing_slash: self.trailing_slash,
+            index_route: Clone::clone(&self.index_route),
+            normalize_slashes: self.normalize_slashes,
+            case_insensitive: self.case_insensitive,
+            layers: Arc::clone(&self.layers),
+            force_close_after: Clone::clone(&self.force_close_after),
+            default_headers: Clone::clone(&self.default_headers),
+            timeout: self.timeout,
+            timeout_status: self.timeout_status,
+            max_body_size: self.max_body_size,
+            max_path_segments: self.max_path_segments,
+            trust_forwarded_proto: self.trust_forwarded_proto,
+            strict_query: self.strict_query,
+            strict_params: self.strict_params,
+            auto_options: self.auto_options,
+            on_connection_error: Clone::clone(&self.on_connection_error),
+            on_request_complete: Clone::clone(&self.on_request_complete),
+            protocol: self.protocol,
+            #[cfg(feature = "logging")]
+            access_log: Clone::clone(&self.access_log),
+            names: Clone::clone(&self.names),
+            hosts: Clone::clone(&self.hosts),
+            fallback: Clone::clone(&self.fallback),
         }
     }
 }
 
-impl<Extra: Default + Send + Sync> Default for Router<Extra> {
+impl<Extra: Debug + Send + Sync> Debug for Router<Extra> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut f = f.debug_struct("Router");
+        f.field("ex", &self.ex)
+            .field("root", &self.root)
+            .field("route_not_found", &self.route_not_found.is_some())
+            .field("error_pages", &self.error_pages.is_some())
+            .field("method_not_allowed", &self.method_not_allowed.is_some())
+            .field("error_format", &self.error_format)
+            .field("match_on", &self.match_on)
+            .field("trailing_slash", &self.trailing_slash)
+            .field("index_route", &self.index_route)
+            .field("normalize_slashes", &self.normalize_slashes)
+            .field("case_insensitive", &self.case_insensitive)
+            .field("layers", &self.layers.len())
+            .field("force_close_after", &self.force_close_after.is_some())
+            .field("default_headers", &self.default_headers)
+            .field("timeout", &self.timeout)
+            .field("timeout_status", &self.timeout_status)
+            .field("max_body_size", &self.max_body_size)
+            .field("max_path_segments", &self.max_path_segments)
+            .field("trust_forwarded_proto", &self.trust_forwarded_proto)
+            .field("strict_query", &self.strict_query)
+            .field("strict_params", &self.strict_params)
+            .field("auto_options", &self.auto_options)
+            .field("on_connection_error", &self.on_connection_error.is_some())
+            .field("on_request_complete", &self.on_request_complete.is_some())
+            .field("protocol", &self.protocol)
+            .field("names", &self.names)
+            .field(
+                "hosts",
+                &self.hosts.iter().map(|(host, _)| host).collect::<Vec<_>>(),
+            )
+            .field("fallback", &self.fallback);
+        #[cfg(feature = "logging")]
+        f.field("access_log", &self.access_log.is_some());
+        f.finish()
+    }
+}
+
+impl<Extra: Default + Send + Sync + 'static> Default for Router<Extra> {
     fn default() -> Self {
         Self::new(Arc::new(Default::default()))
     }
 }
 
-impl<Extra: Send + Sync> Router<Extra> {
+impl<Extra: Send + Sync + 'static> Router<Extra> {
     pub fn new(ex: Arc<Extra>) -> Self {
+        Self::with_capacity(ex, 0)
+    }
+
+    /// Constructs a router sharing `ex` with every handler as
+    /// [`Context::ex`].
+    ///
+    /// Handlers only get shared access to `ex`, so state they change needs
+    /// interior mutability, e.g. a `Mutex` field. Services held in an `Arc`
+    /// field can also be extracted on their own with [`State`](crate::State)
+    /// by implementing [`FromExtra`](crate::FromExtra).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::Router;
+    /// use std::sync::Mutex;
+    ///
+    /// struct App {
+    ///     visits: Mutex<u64>,
+    /// }
+    ///
+    /// Router::with_state(App {
+    ///     visits: Mutex::new(0),
+    /// });
+    /// ```
+    pub fn with_state(ex: Extra) -> Self {
+        Self::new(Arc::new(ex))
+    }
+
+    /// Constructs a router with space reserved for `routes` top-level
+    /// segments, avoiding rehashing while registering many routes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::Router;
+    /// use std::sync::Arc;
+    ///
+    /// Router::with_capacity(Arc::new(()), 1000);
+    /// ```
+    pub fn with_capacity(ex: Arc<Extra>, routes: usize) -> Self {
         Self {
             ex,
-            root: Node::default(),
-            route_not_found: |_, _| {
-                Box::pin(async {
-                    Ok(Response::builder()
-                        .status(StatusCode::NOT_FOUND)
-                        .body(full("Not Found"))
-                        .unwrap())
-                })
-            },
-            method_not_allowed: |route, _, _| {
-                let allowed_methods = route.allowed_methods()
-                    .into_iter()
-                    .map(|m| m.to_string())
-                    .collect::<Vec<_>>()
-                    .join(", ");
-
-                Box::pin(async move {
-                    Ok(Response::builder()
-                        .status(StatusCode::METHOD_NOT_ALLOWED)
-                        .header(header::ALLOW, allowed_methods)
-                        .body(full("Method Not Allowed"))
-                        .unwrap())
-                })
-            },
+            root: Node::with_capacity(routes),
+            route_not_found: None,
+            error_pages: None,
+            method_not_allowed: None,
+            error_format: Format::default(),
+            match_on: MatchOn::default(),
+            trailing_slash: TrailingSlash::default(),
+            index_route: None,
+            normalize_slashes: true,
+            case_insensitive: false,
+            layers: Default::default(),
+            force_close_after: None,
+            default_headers: HeaderMap::new(),
+            timeout: None,
+            timeout_status: StatusCode::SERVICE_UNAVAILABLE,
+            max_body_size: None,
+            max_path_segments: 64,
+            trust_forwarded_proto: false,
+            strict_query: false,
+            strict_params: false,
+            auto_options: false,
+            on_connection_error: None,
+            on_request_complete: None,
+            protocol: Protocol::default(),
+            #[cfg(feature = "logging")]
+            access_log: None,
+            names: HashMap::new(),
+            hosts: vec![],
+            fallback: None,
         }
     }
 
@@ -95,8 +499,144 @@ impl<Extra: Send + Sync> Router<Extra> {
     ///         })
     ///     });
     /// ```
-    pub fn route_not_found(&mut self, handler: RouteNotFoundHandler<Extra>) -> &mut Self {
-        self.route_not_found = handler;
+    pub fn route_not_found<H>(&mut self, handler: H) -> &mut Self
+    where
+        H: Fn(Request<Incoming>, Arc<Extra>) -> BoxFuture<'static, crate::Result>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.route_not_found = Some(Arc::new(handler));
+        self
+    }
+
+    /// Registers a handler for unmatched paths under `prefix`, taking
+    /// precedence over [`Router::route_not_found`]. The handler of the closest
+    /// enclosing prefix is used.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::{Router, full, path};
+    /// use hyper::Response;
+    ///
+    /// Router::<()>::default()
+    ///     .fallback_under(path!("/api"), |_, _| {
+    ///         Box::pin(async {
+    ///             Ok(Response::builder()
+    ///                 .status(404)
+    ///                 .header("Content-Type", "application/json")
+    ///                 .body(full(r#"{"error":"not found"}"#))
+    ///                 .unwrap())
+    ///         })
+    ///     });
+    /// ```
+    pub fn fallback_under<H>(&mut self, prefix: impl Into<Path>, handler: H) -> &mut Self
+    where
+        H: Fn(Request<Incoming>, Arc<Extra>) -> BoxFuture<'static, crate::Result>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.root.descendant_mut(&prefix.into()).route_not_found = Some(Arc::new(handler));
+        self
+    }
+
+    /// Renders the default `404 Not Found` and `500 Internal Server Error`
+    /// responses with `renderer`. Handlers registered with
+    /// [`route_not_found`](Self::route_not_found) and
+    /// [`on_panic`](Self::on_panic) take precedence.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::{DefaultErrorPages, Router};
+    ///
+    /// Router::<()>::default()
+    ///     .error_pages(DefaultErrorPages);
+    /// ```
+    pub fn error_pages(&mut self, renderer: impl ErrorPageRenderer + 'static) -> &mut Self {
+        self.error_pages = Some(Arc::new(renderer));
+        self
+    }
+
+    /// Responds to a request matching no route, through the
+    /// [fallback](Self::or_else) router if there is one and no
+    /// [`fallback_under`](Self::fallback_under) handler covers the path.
+    async fn unmatched(
+        &self,
+        req: Request<Incoming>,
+        addr: Option<SocketAddr>,
+        pattern: &mut Option<Path>,
+    ) -> crate::Result {
+        let path = self.match_path(req.uri().path());
+        if let Some(fallback) = &self.fallback
+            && self
+                .root
+                .route_not_found_handler(
+                    &split_segments(&path),
+                    self.case_insensitive,
+                    self.decode_segments(),
+                )
+                .is_none()
+        {
+            return Box::pin(fallback.respond(req, addr, pattern)).await;
+        }
+
+        let resp = self.not_found(req).await;
+        self.finish(resp)
+    }
+
+    async fn not_found(&self, req: Request<Incoming>) -> crate::Result {
+        let path = self.match_path(req.uri().path());
+        let scoped = self.root.route_not_found_handler(
+            &split_segments(&path),
+            self.case_insensitive,
+            self.decode_segments(),
+        );
+
+        match scoped.or_else(|| self.route_not_found.clone()) {
+            Some(route_not_found) => route_not_found(req, Arc::clone(&self.ex)).await,
+            None => Ok(self.error_page(StatusCode::NOT_FOUND, req.method(), req.uri().path())),
+        }
+    }
+
+    /// Responds to a request whose path parameters can't be decoded.
+    fn bad_request(&self, req: &Request<Incoming>) -> crate::Response {
+        self.error_page(StatusCode::BAD_REQUEST, req.method(), req.uri().path())
+    }
+
+    /// Generates a built-in error response, rendered with the configured
+    /// [`ErrorPageRenderer`] if any.
+    fn error_page(&self, status: StatusCode, method: &Method, path: &str) -> crate::Response {
+        match &self.error_pages {
+            Some(renderer) => renderer.render(ErrorPage {
+                status,
+                method,
+                path,
+            }),
+            None => self.error_format.render(status),
+        }
+    }
+
+    /// Sets the body format of the built-in error responses, such as
+    /// `404 Not Found` and `405 Method Not Allowed`. Defaults to
+    /// [`Format::Text`].
+    ///
+    /// Doesn't affect responses from `route_not_found` and
+    /// `method_not_allowed` handlers, or pages rendered by an
+    /// [`ErrorPageRenderer`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::{Format, Router};
+    ///
+    /// Router::<()>::default()
+    ///     .error_format(Format::Json);
+    /// ```
+    pub fn error_format(&mut self, format: Format) -> &mut Self {
+        self.error_format = format;
         self
     }
 
@@ -128,172 +668,5185 @@ impl<Extra: Send + Sync> Router<Extra> {
     ///         })
     ///     });
     /// ```
-    pub fn method_not_allowed(&mut self, handler: MethodNotAllowedHandler<Extra>) -> &mut Self {
-        self.method_not_allowed = handler;
+    pub fn method_not_allowed<H>(&mut self, handler: H) -> &mut Self
+    where
+        H: Fn(Route<Extra>, Request<Incoming>, Context<Extra>) -> BoxFuture<'static, crate::Result>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.method_not_allowed = Some(Arc::new(handler));
         self
     }
 
-    /// Registers a route, replacing an existing route with an equivalent path.
+    /// Registers a handler called with the error when serving a connection
+    /// fails, e.g. on malformed request bytes or an I/O error. Used by
+    /// [`Router::serve`]; the connection is closed afterwards. Also called
+    /// when accepting a connection fails, after which the server pauses
+    /// briefly and keeps accepting.
+    ///
+    /// Defaults to a `tracing` warning with the `logging` feature, and to
+    /// ignoring the error without it.
     ///
     /// # Example
     ///
     /// ```
-    /// use hyper::Response;
-    /// use radmin_router::{full, path, Route, Router};
+    /// use radmin_router::Router;
     ///
-    /// let route = Route::new(path!("/"))
-    ///     .get(|req, ctx| {
-    ///     Box::pin(async move {
-    ///         Ok(Response::builder()
-    ///             .status(200)
-    ///             .body(full("OK"))
-    ///             .unwrap())
-    ///     })
+    /// Router::<()>::default()
+    ///     .on_connection_error(|err| eprintln!("connection failed: {}", err));
+    /// ```
+    pub fn on_connection_error<H>(&mut self, handler: H) -> &mut Self
+    where
+        H: Fn(Box<dyn Error + Send + Sync>) + Send + Sync + 'static,
+    {
+        self.on_connection_error = Some(Arc::new(handler));
+        self
+    }
+
+    /// Registers a hook called with the method, matched route, status and
+    /// duration of each request once its response is ready, e.g. to record
+    /// metrics. It's called for every response, including `404 Not Found`,
+    /// `405 Method Not Allowed`, panics and timeouts, with or without the
+    /// `logging` feature. The response body may still be streaming.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::Router;
+    ///
+    /// Router::<()>::default().on_request_complete(|metrics| {
+    ///     let route = metrics.route.map(ToString::to_string);
+    ///     eprintln!("{} {:?} {:?} {:?}", metrics.method, route, metrics.status, metrics.elapsed);
     /// });
+    /// ```
+    pub fn on_request_complete<H>(&mut self, hook: H) -> &mut Self
+    where
+        H: Fn(&RequestMetrics<'_>) + Send + Sync + 'static,
+    {
+        self.on_request_complete = Some(Arc::new(hook));
+        self
+    }
+
+    /// Sets which HTTP versions [`Router::serve`] accepts. Defaults to
+    /// [`Protocol::Http1`]; HTTP/2 needs the `http2` feature.
+    ///
+    /// Handlers are unaffected, as requests of either version share a body
+    /// type. `Connection` headers, such as those set through
+    /// [`force_close_after`](Self::force_close_after), are dropped from
+    /// HTTP/2 responses.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::{Protocol, Router};
     ///
     /// Router::<()>::default()
-    ///     .register(route);
-    pub fn register(mut self, route: Route<Extra>) -> Self {
-        self.root.append(route);
+    ///     .protocol(Protocol::Auto);
+    /// ```
+    pub fn protocol(&mut self, protocol: Protocol) -> &mut Self {
+        self.protocol = protocol;
         self
     }
 
-    pub fn register_many(&mut self, routes: impl IntoIterator<Item = Route<Extra>>) -> &mut Self {
-        for route in routes {
-            println!("Added route: {}", route.path);
-            self.root.append(route);
-        }
-
+    /// Registers a handler to generate a response when a route handler panics.
+    /// It receives the panic payload and the context of the request.
+    ///
+    /// Defaults to an empty `500 Internal Server Error`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::{Router, full};
+    /// use hyper::Response;
+    ///
+    /// Router::<()>::default()
+    ///     .on_panic(|_, _| {
+    ///         Box::pin(async {
+    ///             Ok(Response::builder()
+    ///                 .status(500)
+    ///                 .body(full("Something went wrong"))
+    ///                 .unwrap())
+    ///         })
+    ///     });
+    /// ```
+    pub fn on_panic<H>(&mut self, handler: H) -> &mut Self
+    where
+        H: Fn(Box<dyn Any + Send>, Context<Extra>) -> BoxFuture<'static, crate::Result>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.root.on_panic = Some(Arc::new(handler));
         self
     }
 
-    fn match_route(&self, path: impl AsRef<str>) -> Option<(Route<Extra>, Vec<String>)> {
-        let segments = path
-            .as_ref()
-            .trim_start_matches('/')
-            .split('/')
-            .collect::<Vec<_>>();
+    /// Registers a panic handler for routes under `prefix`, taking precedence
+    /// over [`Router::on_panic`]. The handler of the closest enclosing prefix
+    /// is used.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::{Router, full, path};
+    /// use hyper::Response;
+    ///
+    /// Router::<()>::default()
+    ///     .on_panic_under(path!("/api"), |_, _| {
+    ///         Box::pin(async {
+    ///             Ok(Response::builder()
+    ///                 .status(500)
+    ///                 .header("Content-Type", "application/json")
+    ///                 .body(full(r#"{"error":"internal"}"#))
+    ///                 .unwrap())
+    ///         })
+    ///     });
+    /// ```
+    pub fn on_panic_under<H>(&mut self, prefix: impl Into<Path>, handler: H) -> &mut Self
+    where
+        H: Fn(Box<dyn Any + Send>, Context<Extra>) -> BoxFuture<'static, crate::Result>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.root.descendant_mut(&prefix.into()).on_panic = Some(Arc::new(handler));
+        self
+    }
 
-        let mut candidates = vec![&self.root];
+    /// Closes the connection after any response for which `predicate` returns
+    /// `true`, e.g. to drop clients after server errors.
+    ///
+    /// This adds `Connection: close` to the response, which hyper honours by
+    /// closing the connection once the response is written. Handlers may also
+    /// set the header themselves.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::Router;
+    ///
+    /// Router::<()>::default()
+    ///     .force_close_after(|resp| resp.status().is_server_error());
+    /// ```
+    pub fn force_close_after<P>(&mut self, predicate: P) -> &mut Self
+    where
+        P: Fn(&crate::Response) -> bool + Send + Sync + 'static,
+    {
+        self.force_close_after = Some(Arc::new(predicate));
+        self
+    }
 
-        for segment in segments.iter() {
-            if *segment == "" {
-                continue;
-            }
+    /// Adds `headers` to every response, including the router's own 404,
+    /// 405 and 500 responses, e.g. for security headers. Headers a response
+    /// already has are left as they are, so handlers and middleware can
+    /// override the defaults.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hyper::header::{self, HeaderMap, HeaderValue};
+    /// use radmin_router::Router;
+    ///
+    /// let mut headers = HeaderMap::new();
+    /// headers.insert(header::X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff"));
+    /// headers.insert(header::X_FRAME_OPTIONS, HeaderValue::from_static("DENY"));
+    ///
+    /// Router::<()>::default()
+    ///     .default_headers(headers);
+    /// ```
+    pub fn default_headers(&mut self, headers: HeaderMap) -> &mut Self {
+        self.default_headers = headers;
+        self
+    }
 
-            let mut new_candidates = vec![];
-            for candidate in candidates {
-                if let Some(literal) = candidate.children.get(&Segment::literal(*segment)) {
-                    new_candidates.push(literal);
-                }
+    /// Limits how long a matched route's middleware and handler may run,
+    /// responding with `503 Service Unavailable`, or the status set with
+    /// [`timeout_status`](Self::timeout_status), once `timeout` elapses. This
+    /// includes reading the request body in the handler, but not streaming
+    /// the response body.
+    ///
+    /// Responses to timed-out idempotent requests, per
+    /// [`Method::is_idempotent`], carry a `Retry-After` header of `timeout`
+    /// rounded up to whole seconds. That includes HEAD requests answered per
+    /// [`Route::auto_head`]. Other requests, such as POST, may have been cut
+    /// off partway through their side effects, so clients aren't invited to
+    /// repeat them.
+    ///
+    /// The resulting deadline is exposed to handlers as [`Context::deadline`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::Router;
+    /// use std::time::Duration;
+    ///
+    /// Router::<()>::default()
+    ///     .timeout(Duration::from_secs(30));
+    /// ```
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = Some(timeout);
+        self
+    }
 
-                if let Some(dynamic) = candidate.children.get(&Segment::dynamic("")) {
-                    new_candidates.push(dynamic);
-                }
-            }
+    /// Sets the status of responses to requests exceeding the
+    /// [`timeout`](Self::timeout). Defaults to `503 Service Unavailable`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hyper::StatusCode;
+    /// use radmin_router::Router;
+    /// use std::time::Duration;
+    ///
+    /// Router::<()>::default()
+    ///     .timeout(Duration::from_secs(30))
+    ///     .timeout_status(StatusCode::GATEWAY_TIMEOUT);
+    /// ```
+    pub fn timeout_status(&mut self, status: StatusCode) -> &mut Self {
+        self.timeout_status = status;
+        self
+    }
 
-            if new_candidates.is_empty() {
-                return None;
+    /// Limits request bodies to `limit` bytes.
+    ///
+    /// Requests with a larger `Content-Length` are answered with
+    /// `413 Payload Too Large` before reaching their handler. Body readers
+    /// such as `read_multipart` stop with an error once they've read more
+    /// than `limit` bytes of a body without a length. The limit is carried in
+    /// the request's extensions as [`MaxBodySize`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::Router;
+    ///
+    /// Router::<()>::default()
+    ///     .max_body_size(10 * 1024 * 1024);
+    /// ```
+    pub fn max_body_size(&mut self, limit: usize) -> &mut Self {
+        self.max_body_size = Some(limit);
+        self
+    }
+
+    /// Limits request paths to `limit` non-empty segments, answering longer
+    /// paths with `414 URI Too Long` before matching them, so deeply nested
+    /// paths can't make matching expensive. Defaults to 64.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::Router;
+    ///
+    /// Router::<()>::default()
+    ///     .max_path_segments(16);
+    /// ```
+    pub fn max_path_segments(&mut self, limit: usize) -> &mut Self {
+        self.max_path_segments = limit;
+        self
+    }
+
+    /// Sets whether `X-Forwarded-Proto: https` marks requests as secure for
+    /// [`Context::is_secure`]. Only enable this behind a proxy that sets or
+    /// strips the header, as clients can otherwise forge it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::Router;
+    ///
+    /// Router::<()>::default()
+    ///     .trust_forwarded_proto(true);
+    /// ```
+    pub fn trust_forwarded_proto(&mut self, trust: bool) -> &mut Self {
+        self.trust_forwarded_proto = trust;
+        self
+    }
+
+    /// Returns whether `req` arrived over TLS, directly or via a trusted proxy.
+    fn is_secure(&self, req: &Request<Incoming>) -> bool {
+        if req.extensions().get::<Secure>().is_some() {
+            return true;
+        }
+
+        self.trust_forwarded_proto
+            && req
+                .headers()
+                .get("x-forwarded-proto")
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.split(',').next())
+                .is_some_and(|proto| proto.trim().eq_ignore_ascii_case("https"))
+    }
+
+    /// Sets whether requests whose query string contains malformed percent
+    /// escapes or invalid UTF-8 are rejected with `400 Bad Request`. When
+    /// disabled (the default), such query strings are decoded lossily.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::Router;
+    ///
+    /// Router::<()>::default()
+    ///     .strict_query(true);
+    /// ```
+    pub fn strict_query(&mut self, strict: bool) -> &mut Self {
+        self.strict_query = strict;
+        self
+    }
+
+    /// Sets whether requests with path parameters that decode to control
+    /// characters, such as NUL from `%00`, are rejected with
+    /// `400 Bad Request`. Disabled by default.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::Router;
+    ///
+    /// Router::<()>::default()
+    ///     .strict_params(true);
+    /// ```
+    pub fn strict_params(&mut self, strict: bool) -> &mut Self {
+        self.strict_params = strict;
+        self
+    }
+
+    /// Sets whether OPTIONS requests to routes without an OPTIONS handler are
+    /// answered with `204 No Content` and an `Allow` header listing the
+    /// route's methods, instead of going to `method_not_allowed`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::Router;
+    ///
+    /// Router::<()>::default()
+    ///     .auto_options(true);
+    /// ```
+    pub fn auto_options(&mut self, enabled: bool) -> &mut Self {
+        self.auto_options = enabled;
+        self
+    }
+
+    /// Sets whether routes are matched against the raw or percent-decoded
+    /// request path. Defaults to [`MatchOn::Raw`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::{MatchOn, Router};
+    ///
+    /// Router::<()>::default()
+    ///     .match_on(MatchOn::Decoded);
+    /// ```
+    pub fn match_on(&mut self, mode: MatchOn) -> &mut Self {
+        self.match_on = mode;
+        self
+    }
+
+    /// Sets how request paths with a trailing slash are matched. Defaults to
+    /// [`TrailingSlash::Lenient`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::{Router, TrailingSlash};
+    ///
+    /// Router::<()>::default()
+    ///     .trailing_slash(TrailingSlash::RedirectToNoSlash);
+    /// ```
+    pub fn trailing_slash(&mut self, policy: TrailingSlash) -> &mut Self {
+        self.trailing_slash = policy;
+        self
+    }
+
+    /// Resolves request paths ending in a slash, such as `/docs/`, to the
+    /// route of their child literal `name`, such as `/docs/index`, when no
+    /// other route matches them. Disabled by default.
+    ///
+    /// Index routes are found regardless of the
+    /// [`trailing_slash`](Self::trailing_slash) policy, since the slash is
+    /// what asks for them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::Router;
+    ///
+    /// Router::<()>::default()
+    ///     .index_route("index");
+    /// ```
+    pub fn index_route(&mut self, name: impl Into<String>) -> &mut Self {
+        self.index_route = Some(name.into());
+        self
+    }
+
+    /// Returns the path of the index route for `path`, if it ends in a slash
+    /// and no other route takes it, per
+    /// [`index_route`](Self::index_route).
+    fn index_path(&self, path: &str) -> Option<String> {
+        let index = self.index_route.as_ref()?;
+        if !path.ends_with('/') {
+            return None;
+        }
+        if self.trailing_slash != TrailingSlash::Strict && self.match_route(path).is_ok() {
+            return None;
+        }
+
+        let index_path = format!("{}{}", path, index);
+        self.match_route(&index_path).is_ok().then_some(index_path)
+    }
+
+    /// Sets whether empty segments in request paths, from leading, internal or
+    /// repeated trailing slashes, are ignored when matching, so `/a//b`
+    /// matches a route registered as `/a/b`. Enabled by default.
+    ///
+    /// Registered paths are always collapsed when parsed into a
+    /// [`Path`](crate::Path); this only controls how the router splits request
+    /// paths. A single trailing slash is governed by
+    /// [`trailing_slash`](Self::trailing_slash) instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::Router;
+    ///
+    /// Router::<()>::default()
+    ///     .normalize_slashes(false);
+    /// ```
+    pub fn normalize_slashes(&mut self, enabled: bool) -> &mut Self {
+        self.normalize_slashes = enabled;
+        self
+    }
+
+    /// Sets whether literal segments match regardless of ASCII case, so e.g.
+    /// `/API/Users` matches a route registered as `/api/users`. Disabled by
+    /// default.
+    ///
+    /// Only whole literal segments are affected: the literal text of
+    /// patterns such as `[name].png` still matches exactly, and parameters
+    /// are captured as sent. Non-ASCII characters must match exactly.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::Router;
+    ///
+    /// Router::<()>::default()
+    ///     .case_insensitive(true);
+    /// ```
+    pub fn case_insensitive(&mut self, enabled: bool) -> &mut Self {
+        self.case_insensitive = enabled;
+        self
+    }
+
+    /// Sets the layout of the line logged for each request, expanding the
+    /// placeholders described in [`LogRecord::format`], e.g.
+    /// `{method} {path} {status} {elapsed}`.
+    ///
+    /// By default, each request is logged as an event with a field per
+    /// detail. With a format, the event only carries the formatted line as
+    /// its message, and the error of failed requests.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::Router;
+    ///
+    /// Router::<()>::default()
+    ///     .access_log_format("{remote_addr} \"{method} {path}\" {status} {elapsed}");
+    /// ```
+    #[cfg(feature = "logging")]
+    pub fn access_log_format(&mut self, template: impl Into<String>) -> &mut Self {
+        let template = template.into();
+        self.access_log_formatter(move |record| record.format(&template))
+    }
+
+    /// Sets a function producing the line logged for each request, like
+    /// [`access_log_format`](Self::access_log_format) but free-form, e.g. to
+    /// log JSON.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::Router;
+    ///
+    /// Router::<()>::default().access_log_formatter(|record| {
+    ///     format!(
+    ///         r#"{{"method":"{}","status":{}}}"#,
+    ///         record.method,
+    ///         record.status.map_or(0, |status| status.as_u16())
+    ///     )
+    /// });
+    /// ```
+    #[cfg(feature = "logging")]
+    pub fn access_log_formatter(
+        &mut self,
+        formatter: impl Fn(&LogRecord<'_>) -> String + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.access_log = Some(Arc::new(formatter));
+        self
+    }
+
+    /// Adds a middleware around the handler of every matched route, including
+    /// the `method_not_allowed` fallback. Middleware runs in registration order,
+    /// so the first layer added is the outermost.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::Router;
+    ///
+    /// Router::<()>::default()
+    ///     .layer(|req, ctx, next| {
+    ///         Box::pin(async move {
+    ///             let mut resp = next.run(req, ctx).await?;
+    ///             resp.headers_mut().insert("X-Powered-By", "radmin".parse().unwrap());
+    ///             Ok(resp)
+    ///         })
+    ///     });
+    /// ```
+    pub fn layer<M>(&mut self, middleware: M) -> &mut Self
+    where
+        M: Fn(Request<Incoming>, Context<Extra>, Next<Extra>) -> BoxFuture<'static, crate::Result>
+            + Send
+            + Sync
+            + 'static,
+    {
+        Arc::make_mut(&mut self.layers).push(Arc::new(middleware));
+        self
+    }
+
+    /// Limits the router to `requests` requests in each window of length
+    /// `per`, answering the rest with `429 Too Many Requests`.
+    ///
+    /// Adds a [`RateLimit`] layer counting each client IP address
+    /// separately; add one with [`layer`](Self::layer) to key clients another
+    /// way.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::Router;
+    /// use std::time::Duration;
+    ///
+    /// Router::<()>::default()
+    ///     .rate_limit(1000, Duration::from_secs(1));
+    /// ```
+    pub fn rate_limit(&mut self, requests: u32, per: Duration) -> &mut Self {
+        self.layer(RateLimit::new(requests, per).build())
+    }
+
+    /// Registers a route. If a route with an equivalent path already exists,
+    /// the handlers are merged into it, replacing existing handlers for the
+    /// same method. [`try_register`](Self::try_register) refuses such routes
+    /// instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the existing route has different header guards, see
+    /// [`Route::merge`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hyper::Response;
+    /// use radmin_router::{full, path, Route, Router};
+    ///
+    /// let route = Route::new(path!("/"))
+    ///     .get(|req, ctx| {
+    ///     Box::pin(async move {
+    ///         Ok(Response::builder()
+    ///             .status(200)
+    ///             .body(full("OK"))
+    ///             .unwrap())
+    ///     })
+    /// });
+    ///
+    /// Router::<()>::default()
+    ///     .register(route);
+    pub fn register(mut self, route: Route<Extra>) -> Self {
+        self.insert(route);
+        self
+    }
+
+    /// Adds `route` to the route table, merging it into an existing route
+    /// with an equivalent path, and records its path if it's named. Shared by
+    /// every way of registering routes.
+    fn insert(&mut self, route: Route<Extra>) {
+        if let Some(name) = route.route_name() {
+            self.names.insert(name.to_string(), route.path.clone());
+        }
+        self.root.append(route);
+    }
+
+    /// Registers a route, unless a route with an equivalent path already
+    /// exists. Paths are equivalent if they only differ in the names of their
+    /// dynamic segments, e.g. `/users/[id]` and `/users/[name]`. Otherwise
+    /// the route is added like with [`register`](Self::register).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::{path, Route, Router};
+    ///
+    /// let conflict = Router::<()>::default()
+    ///     .try_register(Route::new(path!("/users/[id]")))
+    ///     .and_then(|router| router.try_register(Route::new(path!("/users/[name]"))))
+    ///     .unwrap_err();
+    /// assert_eq!(conflict.existing.path.to_string(), "/users/[id]");
+    /// ```
+    pub fn try_register(mut self, route: Route<Extra>) -> Result<Self, RouteConflict<Extra>> {
+        let existing = self
+            .root
+            .descendant(&route.path)
+            .and_then(|node| node.route.as_deref());
+
+        if let Some(existing) = existing {
+            return Err(RouteConflict {
+                existing: Box::new(existing.clone()),
+                rejected: Box::new(route),
+            });
+        }
+
+        self.insert(route);
+        Ok(self)
+    }
+
+    /// Registers the routes of a RESTful resource at `base`, e.g.
+    /// `/widgets`: `GET` and `POST` at `base`, and `GET`, `PUT` and `DELETE`
+    /// at `base/[id]`. Handlers are merged into existing routes like
+    /// [`register`](Self::register).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `base` isn't a valid [`Path`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hyper::Response;
+    /// use radmin_router::{ResourceHandlers, Router, full};
+    ///
+    /// Router::<()>::default().resource(
+    ///     "/widgets",
+    ///     ResourceHandlers::default()
+    ///         .index(|_, _| Box::pin(async { Ok(Response::new(full("[]"))) }))
+    ///         .show(|_, ctx| {
+    ///             let id = ctx.params[0].clone();
+    ///             Box::pin(async move { Ok(Response::new(full(id))) })
+    ///         }),
+    /// );
+    /// ```
+    pub fn resource(&mut self, base: &str, handlers: ResourceHandlers<Extra>) -> &mut Self {
+        let base = base.trim_end_matches('/');
+        let routes = [
+            (
+                base.to_string(),
+                vec![
+                    (Method::GET, handlers.index),
+                    (Method::POST, handlers.create),
+                ],
+            ),
+            (
+                format!("{}/[id]", base),
+                vec![
+                    (Method::GET, handlers.show),
+                    (Method::PUT, handlers.update),
+                    (Method::DELETE, handlers.destroy),
+                ],
+            ),
+        ];
+
+        for (path, handlers) in routes {
+            let handlers: HashMap<_, _> = handlers
+                .into_iter()
+                .filter_map(|(method, handler)| Some((method, handler?)))
+                .collect();
+            if !handlers.is_empty() {
+                self.insert(Route::with_handlers(path.as_str(), handlers));
+            }
+        }
+
+        self
+    }
+
+    /// Registers the health checks expected by operational tooling: `GET
+    /// /healthz`, which always responds `200 OK` while the server is up, and
+    /// `GET /readyz`, which calls `probe` with the router's shared state and
+    /// responds `200 OK` if it resolves to `true` and
+    /// `503 Service Unavailable` otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::Router;
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    ///
+    /// Router::<AtomicBool>::default()
+    ///     .with_health_checks(|ready| Box::pin(async move { ready.load(Ordering::Relaxed) }));
+    /// ```
+    pub fn with_health_checks<H>(&mut self, probe: H) -> &mut Self
+    where
+        H: Fn(Arc<Extra>) -> BoxFuture<'static, bool> + Send + Sync + 'static,
+    {
+        let probe = Arc::new(probe);
+        let liveness = Route::new(Path::from("/healthz"))
+            .get(|_, _| Box::pin(async { Ok(Response::new(full("OK"))) }));
+        let readiness = Route::new(Path::from("/readyz")).get(move |_, ctx| {
+            let ready = probe(ctx.ex);
+            Box::pin(async move {
+                Ok(if ready.await {
+                    Response::new(full("OK"))
+                } else {
+                    Response::builder()
+                        .status(StatusCode::SERVICE_UNAVAILABLE)
+                        .body(full("Service Unavailable"))
+                        .unwrap()
+                })
+            })
+        });
+
+        self.register_many([liveness, readiness])
+    }
+
+    /// Removes and returns the route at `path`, or a route with an
+    /// equivalent path. Nodes of the route tree left without routes,
+    /// children or scoped handlers are pruned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::{path, Route, Router};
+    ///
+    /// let mut router = Router::<()>::default().register(Route::new(path!("/beta")));
+    /// assert!(router.unregister(&path!("/beta")).is_some());
+    /// assert!(router.unregister(&path!("/beta")).is_none());
+    /// ```
+    pub fn unregister(&mut self, path: &Path) -> Option<Route<Extra>> {
+        let route = self.root.remove(&path.0)?;
+        if let Some(name) = route.route_name() {
+            self.names.remove(name);
+        }
+        Some(Arc::unwrap_or_clone(route))
+    }
+
+    /// Returns a mutable view of the route at `path`, registering an empty
+    /// route there first if none exists. Allows adding handlers to a path
+    /// across separate calls.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hyper::Response;
+    /// use radmin_router::{full, path, Router};
+    ///
+    /// let mut router = Router::<()>::default();
+    ///
+    /// router.route_path(path!("/users")).get(|_, _| {
+    ///     Box::pin(async { Ok(Response::new(full("list"))) })
+    /// });
+    /// router.route_path(path!("/users")).post(|_, _| {
+    ///     Box::pin(async { Ok(Response::new(full("create"))) })
+    /// });
+    /// ```
+    pub fn route_path(&mut self, path: impl Into<Path>) -> RouteMut<'_, Extra> {
+        let path = path.into();
+        let node = self.root.descendant_mut(&path);
+
+        RouteMut(Arc::make_mut(
+            node.route.get_or_insert_with(|| Arc::new(Route::new(path))),
+        ))
+    }
+
+    /// Merges the routes of `other` under `prefix`, keeping its
+    /// route-not-found handler, panic handlers and middleware scoped to that
+    /// subtree. `other`'s middleware runs inside this router's middleware.
+    ///
+    /// Other settings of `other`, including its `Extra` and
+    /// `method_not_allowed` handler, are discarded.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hyper::Response;
+    /// use radmin_router::{full, path, Route, Router};
+    ///
+    /// let api = Router::<()>::default().register(Route::new(path!("/status")).get(|_, _| {
+    ///     Box::pin(async { Ok(Response::new(full("OK"))) })
+    /// }));
+    ///
+    /// // Serves `/v2/status`.
+    /// Router::<()>::default()
+    ///     .merge_at(path!("/v2"), api);
+    /// ```
+    pub fn merge_at(&mut self, prefix: impl Into<Path>, other: Router<Extra>) -> &mut Self {
+        let prefix = prefix.into();
+        let mut root = other.root;
+
+        root.route_not_found = other.route_not_found.or(root.route_not_found);
+        let mut layers = Arc::unwrap_or_clone(other.layers);
+        layers.append(&mut root.layers);
+        root.layers = layers;
+
+        for (name, path) in other.names {
+            let path = Path(prefix.0.iter().chain(&path.0).cloned().collect());
+            self.names.insert(name, path);
+        }
+
+        self.root.graft(&prefix, root);
+        self
+    }
+
+    /// Mounts the routes of `sub` under `prefix`, e.g. an API router at
+    /// `/api/v1`. Dynamic segments of `prefix` are captured before those of
+    /// the mounted routes.
+    ///
+    /// Both routers share the same `Extra` type, and `sub`'s is discarded. See
+    /// [`merge_at`](Router::merge_at), which this is an alias for.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::{path, Route, Router};
+    ///
+    /// let api = Router::<()>::default().register(Route::new(path!("/users/[id]")));
+    ///
+    /// // Serves `/api/users/[id]`.
+    /// Router::<()>::default()
+    ///     .mount(path!("/api"), api);
+    /// ```
+    pub fn mount(&mut self, prefix: impl Into<Path>, sub: Router<Extra>) -> &mut Self {
+        self.merge_at(prefix, sub)
+    }
+
+    /// Adds the routes of `other` to this router, along with its handlers and
+    /// middleware scoped to a prefix. Its router-wide handlers, middleware and
+    /// settings, such as `route_not_found`, `method_not_allowed` and
+    /// [`on_panic`](Self::on_panic), are discarded in favor of this router's.
+    ///
+    /// As with [`register`](Self::register), a handler of `other` replaces one
+    /// for the same method and path, which
+    /// [`assert_no_conflicts`](Self::assert_no_conflicts) reports and, with the
+    /// `logging` feature, is logged as a warning.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::{Route, Router, path};
+    ///
+    /// let users = Router::<()>::default().register(Route::new(path!("/users")));
+    /// let posts = Router::<()>::default().register(Route::new(path!("/posts")));
+    ///
+    /// let mut router = Router::<()>::default();
+    /// router.merge(users).merge(posts);
+    /// ```
+    pub fn merge(&mut self, other: Router<Extra>) -> &mut Self {
+        let mut root = other.root;
+        root.on_panic = None;
+        root.route_not_found = None;
+        root.layers.clear();
+
+        #[cfg(feature = "logging")]
+        {
+            let mut routes = vec![];
+            root.routes(&mut routes);
+
+            for (path, methods) in routes {
+                let Some(existing) = self
+                    .root
+                    .descendant(&path)
+                    .and_then(|node| node.route.as_deref())
+                else {
+                    continue;
+                };
+                let route = root
+                    .descendant(&path)
+                    .and_then(|node| node.route.as_deref());
+
+                for method in methods {
+                    if existing.has_handler(&method)
+                        && route.is_some_and(|route| route.has_handler(&method))
+                    {
+                        tracing::warn!(%method, %path, "merged route replaces a handler");
+                    }
+                }
+            }
+        }
+
+        self.names.extend(other.names);
+        self.root.graft(&Path(vec![]), root);
+        self
+    }
+
+    /// Routes requests for hosts matching `pattern` to `router`, based on the
+    /// `Host` header or the authority of the request URI, ignoring the port
+    /// and case. Requests for other hosts are routed by this router.
+    ///
+    /// `pattern` is either an exact host, or `*.` followed by a domain to
+    /// match its subdomains, e.g. `*.example.com` matches `app.example.com`,
+    /// but not `example.com`. Exact hosts take precedence over wildcards,
+    /// then the first matching pattern is used.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pattern` contains a `*` anywhere but as its first label.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::{Route, Router, path};
+    ///
+    /// let admin = Router::<()>::default().register(Route::new(path!("/")));
+    /// let tenants = Router::<()>::default().register(Route::new(path!("/")));
+    ///
+    /// Router::<()>::default()
+    ///     .host("admin.example.com", admin)
+    ///     .host("*.example.com", tenants);
+    /// ```
+    pub fn host(&mut self, pattern: &str, router: Router<Extra>) -> &mut Self {
+        let pattern = pattern.to_ascii_lowercase();
+        assert!(
+            !pattern.strip_prefix("*.").unwrap_or(&pattern).contains('*'),
+            "invalid host pattern `{}`",
+            pattern
+        );
+
+        self.hosts.push((pattern, Arc::new(router)));
+        self
+    }
+
+    /// Routes requests matching none of this router's routes to `fallback`,
+    /// e.g. a legacy router during a migration, instead of responding
+    /// `404 Not Found`. Calling this again appends to the chain, so each
+    /// fallback is tried in turn and the last responds to requests none of
+    /// them route, with its own [`route_not_found`](Self::route_not_found)
+    /// handler if any.
+    ///
+    /// Only unmatched paths fall through: requests for a route without a
+    /// handler for their method still get `405 Method Not Allowed`, and
+    /// handler errors are returned as is. Paths under a prefix given to
+    /// [`fallback_under`](Self::fallback_under) don't fall through either,
+    /// they're answered by its handler, while this router's own
+    /// `route_not_found` handler is only used without fallbacks.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::{Route, Router, path};
+    ///
+    /// let legacy = Router::<()>::default().register(Route::new(path!("/old/[...path]")));
+    ///
+    /// Router::<()>::default()
+    ///     .register(Route::new(path!("/users")))
+    ///     .or_else(legacy);
+    /// ```
+    pub fn or_else(&mut self, fallback: Router<Extra>) -> &mut Self {
+        match &mut self.fallback {
+            Some(existing) => {
+                Arc::make_mut(existing).or_else(fallback);
+            }
+            None => self.fallback = Some(Arc::new(fallback)),
+        }
+        self
+    }
+
+    /// Returns the router registered with [`Router::host`] for the request's
+    /// host, if any.
+    fn host_router(&self, req: &Request<Incoming>) -> Option<&Arc<Router<Extra>>> {
+        if self.hosts.is_empty() {
+            return None;
+        }
+
+        let host = req
+            .headers()
+            .get(header::HOST)
+            .and_then(|host| host.to_str().ok())
+            .or_else(|| req.uri().host())?;
+        // Strip the port, keeping bracketed IPv6 addresses intact.
+        let host = match host.rsplit_once(':') {
+            Some((name, port)) if !port.contains(']') => name,
+            _ => host,
+        }
+        .to_ascii_lowercase();
+
+        let exact = self.hosts.iter().find(|(pattern, _)| *pattern == host);
+        let wildcard = || {
+            self.hosts.iter().find(|(pattern, _)| {
+                pattern.strip_prefix('*').is_some_and(|domain| {
+                    host.strip_suffix(domain)
+                        .is_some_and(|subdomain| !subdomain.is_empty())
+                })
+            })
+        };
+
+        exact.or_else(wildcard).map(|(_, router)| router)
+    }
+
+    /// Registers each of `routes`, as with [`register`](Self::register).
+    ///
+    /// With the `logging` feature, each route is logged at the debug level.
+    pub fn register_many(&mut self, routes: impl IntoIterator<Item = Route<Extra>>) -> &mut Self {
+        for route in routes {
+            #[cfg(feature = "logging")]
+            tracing::debug!(path = %route.path, "registered route");
+            self.insert(route);
+        }
+
+        self
+    }
+
+    /// Panics, listing every problem found, if the route table has conflicts:
+    /// a handler replaced by a later registration of the same method and path,
+    /// routes naming a shared parameter differently (e.g. `/users/[id]` and
+    /// `/users/[name]/posts`), or a catch-all followed by more segments.
+    ///
+    /// Meant for use in tests.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::{Route, Router, path};
+    ///
+    /// Router::<()>::default()
+    ///     .register(Route::new(path!("/users/[id]")))
+    ///     .register(Route::new(path!("/users/[id]/posts")))
+    ///     .assert_no_conflicts();
+    /// ```
+    pub fn assert_no_conflicts(&self) {
+        let mut problems = vec![];
+        self.root.conflicts(&mut vec![], &mut problems);
+        problems.sort();
+
+        assert!(
+            problems.is_empty(),
+            "conflicting routes:\n{}",
+            problems.join("\n")
+        );
+    }
+
+    /// Returns the path and allowed methods of every registered route, e.g.
+    /// for generating API documentation. Parameter names are those the route
+    /// was registered with. The order of routes is unspecified.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hyper::{Method, Response};
+    /// use radmin_router::{Route, Router, full, path};
+    ///
+    /// let router = Router::<()>::default().register(
+    ///     Route::new(path!("/users/[id]"))
+    ///         .get(|_, _| Box::pin(async { Ok(Response::new(full("user"))) })),
+    /// );
+    ///
+    /// assert_eq!(router.routes(), vec![(path!("/users/[id]"), vec![Method::GET])]);
+    /// ```
+    pub fn routes(&self) -> Vec<(Path, Vec<Method>)> {
+        let mut routes = vec![];
+        self.root.routes(&mut routes);
+        routes
+    }
+
+    /// Returns the methods allowed by any registered route, in the canonical
+    /// order of [`HttpMethod`]. These are listed in the `Allow` header of the `204 No Content`
+    /// response to `OPTIONS *`, along with OPTIONS itself.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hyper::{Method, Response};
+    /// use radmin_router::{ResponseFut, Route, Router, full, path};
+    /// use std::pin::Pin;
+    ///
+    /// let ok = |_, _| -> Pin<Box<ResponseFut>> {
+    ///     Box::pin(async { Ok(Response::new(full("OK"))) })
+    /// };
+    /// let router = Router::<()>::default()
+    ///     .register(Route::new(path!("/users")).get(ok).post(ok))
+    ///     .register(Route::new(path!("/users/[id]")).delete(ok));
+    ///
+    /// assert_eq!(router.allowed_methods(), [Method::GET, Method::POST, Method::DELETE]);
+    /// ```
+    pub fn allowed_methods(&self) -> Vec<Method> {
+        let mut methods: Vec<_> = self
+            .routes()
+            .into_iter()
+            .flat_map(|(_, methods)| methods)
+            .collect();
+        HttpMethod::sort(&mut methods);
+        methods.dedup();
+        methods
+    }
+
+    /// Builds the path of the route [named](Route::name) `name`, substituting
+    /// its dynamic segments, patterns and catch-all with the values of the
+    /// parameters of the same name in `params`. Values are percent-encoded,
+    /// except for the slashes of catch-all values.
+    ///
+    /// Returns `None` if no route has the name, or a parameter of its path is
+    /// missing from `params` or empty.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::{Route, Router, path};
+    ///
+    /// let router = Router::<()>::default()
+    ///     .register(Route::new(path!("/users/[id]")).name("users.show"));
+    ///
+    /// assert_eq!(
+    ///     router.url_for("users.show", &[("id", "42")]).as_deref(),
+    ///     Some("/users/42")
+    /// );
+    /// assert_eq!(router.url_for("users.show", &[]), None);
+    /// ```
+    pub fn url_for(&self, name: &str, params: &[(&str, &str)]) -> Option<String> {
+        let path = self.names.get(name)?;
+        let param = |name: &str| {
+            params
+                .iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| *value)
+                .filter(|value| !value.is_empty())
+        };
+
+        let mut url = String::new();
+        for segment in &path.0 {
+            if let Segment::Optional(name) = segment {
+                if let Some(value) = param(name) {
+                    url.push('/');
+                    url += &percent_encode(value);
+                }
+                continue;
+            }
+
+            url.push('/');
+
+            match segment {
+                Segment::Literal(literal) => url += &percent_encode(literal),
+                Segment::Dynamic(name) | Segment::Constrained(name, _) => {
+                    url += &percent_encode(param(name)?)
+                }
+                Segment::Pattern(parts) => {
+                    for part in parts {
+                        url += &match part {
+                            PatternPart::Literal(literal) => percent_encode(literal),
+                            PatternPart::Placeholder(name) => percent_encode(param(name)?),
+                        };
+                    }
+                }
+                Segment::CatchAll(name) => {
+                    let pieces: Vec<_> = param(name)?.split('/').map(percent_encode).collect();
+                    url += &pieces.join("/");
+                }
+                Segment::Optional(_) => unreachable!("optional segments are handled above"),
+            }
+        }
+
+        if url.is_empty() {
+            url.push('/');
+        }
+
+        Some(url)
+    }
+
+    /// Finds the route matching `path`, along with its positional and named
+    /// parameters.
+    ///
+    /// Dynamic parameters are percent-decoded, unless the whole path already
+    /// was per [`MatchOn::Decoded`]. Catch-all parameters are kept as received.
+    fn match_route(&self, path: impl AsRef<str>) -> Result<RouteMatch<Extra>, MatchError> {
+        let path = self.match_path(path.as_ref());
+        if !self.normalize_slashes && has_empty_segments(&path) {
+            return Err(MatchError::NotFound);
+        }
+        let segments = split_segments(&path);
+
+        let mut state = MatchState {
+            key: LiteralKey::with_capacity(path.len()),
+            reached_node: false,
+            prioritized: self.root.prioritized,
+            best: None,
+        };
+        let route = self
+            .find_route(&self.root, &segments, &mut state)
+            .or(state.best);
+        #[cfg(feature = "logging")]
+        match route {
+            Some(route) => tracing::trace!(
+                path = %path,
+                route = %route.path,
+                priority = route.priority_value(),
+                specificity = ?specificity(&route.path),
+                "matched route"
+            ),
+            None => tracing::trace!(path = %path, "no route matched"),
+        }
+        let route = route.ok_or(if state.reached_node {
+            MatchError::NoRoute
+        } else {
+            MatchError::NotFound
+        })?;
+
+        let mut params = vec![];
+        let mut params_by_name = HashMap::new();
+        for (idx, seg) in route.path.0.iter().enumerate() {
+            let captures = match seg {
+                // Literals may have matched percent-decoded or ignoring case.
+                Segment::Literal(_) => continue,
+                Segment::CatchAll(name) => {
+                    let value = self.check_param(segments[idx..].join("/"))?;
+                    params_by_name.insert(name.clone(), value.clone());
+                    params.push(value);
+                    continue;
+                }
+                _ => match segments.get(idx) {
+                    Some(segment) => seg
+                        .matches_with(segment, self.decode_segments())
+                        .expect("matched segments match their route"),
+                    // Optional segments at the end may be absent.
+                    None => continue,
+                },
+            };
+
+            for (name, value) in captures {
+                let value = self.decode_param(value)?;
+                params_by_name.insert(name.to_string(), value.clone());
+                params.push(value);
+            }
+        }
+
+        Ok((Arc::clone(route), params, params_by_name))
+    }
+
+    /// Decodes a dynamic parameter captured from the matched path.
+    fn decode_param(&self, segment: &str) -> Result<String, MatchError> {
+        let value = match self.match_on {
+            MatchOn::Raw => percent_decode_utf8(segment)
+                .map(Cow::into_owned)
+                .ok_or(MatchError::InvalidParam)?,
+            MatchOn::Decoded => segment.to_string(),
+        };
+        self.check_param(value)
+    }
+
+    /// Rejects parameters containing control characters, per
+    /// [`strict_params`](Self::strict_params).
+    fn check_param(&self, value: String) -> Result<String, MatchError> {
+        if self.strict_params && value.chars().any(char::is_control) {
+            return Err(MatchError::InvalidParam);
+        }
+        Ok(value)
+    }
+
+    /// Returns the form of `path` used for matching, per [`MatchOn`].
+    fn match_path<'a>(&self, path: &'a str) -> Cow<'a, str> {
+        match self.match_on {
+            MatchOn::Raw => Cow::Borrowed(path),
+            MatchOn::Decoded => percent_decode(path),
+        }
+    }
+
+    /// Whether segments are percent-decoded when compared with literals. With
+    /// [`MatchOn::Decoded`], [`match_path`](Self::match_path) already decoded
+    /// them, and decoding again would let `%2525` match a literal `%25`.
+    fn decode_segments(&self) -> bool {
+        self.match_on == MatchOn::Raw
+    }
+
+    /// Finds the route under `node` matching `segments`, preferring literal
+    /// segments over patterns, patterns over constrained segments,
+    /// constrained segments over other dynamic ones, dynamic segments over
+    /// optional ones and optional segments over catch-alls. The pattern with
+    /// the most literal text is tried first. Falls back to the next
+    /// alternative only when the preferred branch doesn't lead to a route,
+    /// unless that branch is [exact](Route::exact). Literal chains merged
+    /// into a node when compacting must match in full.
+    ///
+    /// When routes have priorities, every alternative is tried and the best
+    /// candidate is left in `state` instead.
+    fn find_route<'a>(
+        &self,
+        node: &'a Node<Extra>,
+        segments: &[&str],
+        state: &mut MatchState<'a, Extra>,
+    ) -> Option<&'a Arc<Route<Extra>>> {
+        let Some(segments) =
+            node.strip_chain(segments, self.case_insensitive, self.decode_segments())
+        else {
+            #[cfg(feature = "logging")]
+            tracing::trace!(segments = ?segments, "literal chain doesn't match");
+            // Paths ending partway through a chain lead to one of its nodes.
+            state.reached_node |=
+                node.chain_starts_with(segments, self.case_insensitive, self.decode_segments());
+            return None;
+        };
+        let Some((segment, rest)) = segments.split_first() else {
+            state.reached_node = true;
+            if let Some(route) = &node.route
+                && let Some(route) = Self::found(route, state)
+            {
+                return Some(route);
+            }
+            // Optional segments left at the end of a route may be absent, and
+            // catch-alls may capture nothing.
+            if let Some(optional) = node.children.get(&Segment::optional(""))
+                && let Some(route) = self.find_route(optional, &[], state)
+            {
+                return Some(route);
+            }
+            let catch_all = node.children.get(&Segment::catch_all(""))?;
+            return Self::found(catch_all.route.as_ref()?, state);
+        };
+
+        let literal = state.key.child(node, segment).or_else(|| {
+            // Literals are stored decoded, so e.g. `%5B` matches an escaped `[`.
+            match percent_decode(segment) {
+                Cow::Owned(decoded) if self.decode_segments() => state.key.child(node, &decoded),
+                _ => None,
+            }
+        });
+        let literal = literal.or_else(|| {
+            self.case_insensitive
+                .then(|| node.literal_child_ignore_case(segment, self.decode_segments()))
+                .flatten()
+        });
+
+        if let Some(literal) = literal {
+            trace_branch(segment, "literal");
+            if let Some(route) = self.find_route(literal, rest, state) {
+                return Some(route);
+            }
+
+            if literal.exact {
+                #[cfg(feature = "logging")]
+                tracing::trace!(
+                    segment,
+                    "exact literal branch has no route, not falling back"
+                );
+                return None;
+            }
+        }
+
+        for pattern in node.pattern_children(segment, self.decode_segments()) {
+            trace_branch(segment, "pattern");
+            if let Some(route) = self.find_route(pattern, rest, state) {
+                return Some(route);
+            }
+        }
+
+        for constrained in node.constrained_children(segment) {
+            trace_branch(segment, "constrained");
+            if let Some(route) = self.find_route(constrained, rest, state) {
+                return Some(route);
+            }
+        }
+
+        if let Some(dynamic) = node.children.get(&Segment::dynamic("")) {
+            trace_branch(segment, "dynamic");
+            if let Some(route) = self.find_route(dynamic, rest, state) {
+                return Some(route);
+            }
+        }
+
+        if let Some(optional) = node.children.get(&Segment::optional("")) {
+            trace_branch(segment, "optional");
+            if let Some(route) = self.find_route(optional, rest, state) {
+                return Some(route);
+            }
+        }
+
+        let catch_all = node.children.get(&Segment::catch_all(""))?;
+        trace_branch(segment, "catch-all");
+        Self::found(catch_all.route.as_ref()?, state)
+    }
+
+    /// Records `route` as matching, returning it to end the search unless
+    /// routes have priorities. Then it only replaces the best candidate if it
+    /// has a higher priority, as candidates are found most specific first.
+    fn found<'a>(
+        route: &'a Arc<Route<Extra>>,
+        state: &mut MatchState<'a, Extra>,
+    ) -> Option<&'a Arc<Route<Extra>>> {
+        if !state.prioritized {
+            return Some(route);
+        }
+        if state
+            .best
+            .is_none_or(|best| route.priority_value() > best.priority_value())
+        {
+            state.best = Some(route);
+        }
+        None
+    }
+
+    /// Runs the middleware chain and the handler of `route` for `req`.
+    fn dispatch(
+        &self,
+        route: Arc<Route<Extra>>,
+        req: Request<Incoming>,
+        ctx: Context<Extra>,
+    ) -> BoxFuture<'static, crate::Result> {
+        let scoped = self.root.layers(&route.path);
+        let layers = if scoped.is_empty() {
+            Arc::clone(&self.layers)
+        } else {
+            Arc::new(self.layers.iter().cloned().chain(scoped).collect())
+        };
+
+        let endpoint: Endpoint<Extra> = match route.handler(req.method()) {
+            Some(handler) => match route.cache_control_value().cloned() {
+                Some(cache_control) => Arc::new(move |req, ctx| {
+                    let resp = handler(req, ctx);
+                    let cache_control = cache_control.clone();
+
+                    Box::pin(async move {
+                        let mut resp = resp.await?;
+                        resp.headers_mut()
+                            .entry(header::CACHE_CONTROL)
+                            .or_insert(cache_control);
+                        Ok(resp)
+                    })
+                }),
+                None => Arc::new(move |req, ctx| handler(req, ctx)),
+            },
+            None if self.auto_options && req.method() == Method::OPTIONS => {
+                let mut allowed_methods = route.allowed_methods();
+                allowed_methods.push(Method::OPTIONS);
+                HttpMethod::sort(&mut allowed_methods);
+                let allowed_methods = allowed_methods
+                    .iter()
+                    .map(Method::as_str)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let allow = HeaderValue::from_str(&allowed_methods).unwrap();
+
+                Arc::new(move |_, _| {
+                    let resp = Response::builder()
+                        .status(StatusCode::NO_CONTENT)
+                        .header(header::ALLOW, allow.clone())
+                        .body(full(""))
+                        .unwrap();
+                    Box::pin(future::ready(Ok(resp)))
+                })
+            }
+            None => match &self.method_not_allowed {
+                Some(method_not_allowed) => {
+                    let method_not_allowed = Arc::clone(method_not_allowed);
+                    Arc::new(move |req, ctx| method_not_allowed(Route::clone(&route), req, ctx))
+                }
+                None => {
+                    let allowed_methods = route
+                        .allowed_methods()
+                        .into_iter()
+                        .map(|m| m.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    let allow = HeaderValue::from_str(&allowed_methods).unwrap();
+                    let format = self.error_format;
+
+                    Arc::new(move |_, _| {
+                        let mut resp = format.render(StatusCode::METHOD_NOT_ALLOWED);
+                        resp.headers_mut().insert(header::ALLOW, allow.clone());
+                        Box::pin(future::ready(Ok(resp)))
+                    })
+                }
+            },
+        };
+
+        Next::new(layers, endpoint).run(req, ctx)
+    }
+
+    /// Runs the route matching the request's path and method with a
+    /// caller-supplied context, bypassing parameter extraction. Intended for
+    /// white-box testing of handlers and middleware.
+    ///
+    /// Middleware still runs around the handler. Responds via `route_not_found`
+    /// when no route matches the request path. The context's headers and
+    /// method are taken from the request.
+    #[cfg(feature = "test-util")]
+    pub async fn route_with_context(
+        self: Arc<Self>,
+        req: Request<Incoming>,
+        mut ctx: Context<Extra>,
+    ) -> crate::Result {
+        ctx.headers = Arc::new(req.headers().clone());
+        ctx.method = req.method().clone();
+        match self.match_route(req.uri().path()) {
+            Ok((route, ..)) => self.dispatch(route, req, ctx).await,
+            Err(MatchError::NotFound | MatchError::NoRoute) => self.not_found(req).await,
+            Err(MatchError::InvalidParam) => Ok(self.bad_request(&req)),
+        }
+    }
+
+    /// Routes `req`, which may have any body type, through the router over
+    /// an in-memory connection, without binding a socket. Intended for
+    /// integration tests.
+    ///
+    /// The whole pipeline runs, including matching, middleware and error
+    /// handling, and handlers receive an ordinary `Request<Incoming>`.
+    /// [`Context::remote_addr`] is `None`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use http_body_util::{BodyExt, Empty};
+    /// use hyper::Request;
+    /// use hyper::body::Bytes;
+    /// use radmin_router::{Route, Router, path};
+    /// use std::sync::Arc;
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let router = Arc::new(Router::<()>::default().register(
+    ///     Route::new(path!("/hello/[name]"))
+    ///         .get_into(|_, ctx| async move { format!("Hello, {}!", ctx.params[0]) }),
+    /// ));
+    ///
+    /// let req = Request::get("/hello/world").body(Empty::<Bytes>::new()).unwrap();
+    /// let resp = router.oneshot(req).await.unwrap();
+    /// let body = resp.into_body().collect().await.unwrap().to_bytes();
+    /// assert_eq!(body, "Hello, world!");
+    /// # }
+    /// ```
+    #[cfg(feature = "test-util")]
+    pub async fn oneshot<B>(self: &Arc<Self>, req: Request<B>) -> crate::Result
+    where
+        B: hyper::body::Body + Send + 'static,
+        B::Data: Send,
+        B::Error: Into<Box<dyn Error + Send + Sync>>,
+    {
+        use http_body_util::BodyExt;
+
+        let (client, server) = tokio::io::duplex(64 * 1024);
+
+        let router = Arc::clone(self);
+        let svc = service_fn(move |req| Router::route(Arc::clone(&router), req));
+        tokio::spawn(
+            hyper::server::conn::http1::Builder::new()
+                .serve_connection(TokioIo::new(server), svc)
+                .with_upgrades(),
+        );
+
+        let (mut sender, conn) =
+            hyper::client::conn::http1::handshake(TokioIo::new(client)).await?;
+        tokio::spawn(conn);
+
+        let resp = sender.send_request(req).await?;
+        Ok(resp.map(|body| body.map_err(Into::into).boxed()))
+    }
+
+    /// Returns the route matching `path` and its positional parameters,
+    /// without running it. Intended for benchmarking route matching.
+    #[cfg(feature = "test-util")]
+    pub fn recognize(&self, path: &str) -> Option<(Arc<Route<Extra>>, Vec<String>)> {
+        let (route, params, _) = self.match_route(path).ok()?;
+        Some((route, params))
+    }
+
+    /// Consumes the router, locking its route table.
+    ///
+    /// Chains of path segments leading to a single literal segment are merged
+    /// into one step of the route tree, so deep static paths are matched in
+    /// fewer steps. Matching results are unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::{Route, Router, path};
+    ///
+    /// let router = Router::<()>::default()
+    ///     .register(Route::new(path!("/")))
+    ///     .finalize();
+    /// let handle = router.clone();
+    /// ```
+    pub fn finalize(mut self) -> FinalizedRouter<Extra> {
+        self.root.compact();
+        FinalizedRouter {
+            inner: Arc::new(self),
+        }
+    }
+
+    /// Applies router-wide post-processing to an outgoing response.
+    fn finish(&self, mut resp: crate::Result) -> crate::Result {
+        if let (Ok(resp), Some(predicate)) = (&mut resp, &self.force_close_after)
+            && predicate(resp)
+        {
+            resp.headers_mut()
+                .insert(header::CONNECTION, HeaderValue::from_static("close"));
+        }
+
+        resp
+    }
+
+    /// Processes an incoming request and generates a response for hyper.
+    ///
+    /// A panic in a handler or middleware, whether while creating its future or
+    /// while polling it, is caught and answered by the
+    /// [`on_panic`](Self::on_panic) handler, or with
+    /// `500 Internal Server Error`, so the connection stays usable. Handlers
+    /// needn't be `UnwindSafe`: state they share, such as a `Mutex` in
+    /// `Extra`, may be left inconsistent by the panicking handler.
+    pub async fn route(
+        self: Arc<Self>,
+        req: Request<Incoming>,
+    ) -> Result<Response<BoxBody<Bytes, crate::BodyError>>, crate::BodyError> {
+        self.route_with_addr(req, None).await
+    }
+
+    /// Like [`route`](Self::route), for a request from the client at `addr`,
+    /// which handlers can read as [`Context::remote_addr`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hyper::service::service_fn;
+    /// use radmin_router::Router;
+    /// use std::sync::Arc;
+    ///
+    /// # async fn accept(router: Arc<Router<()>>, listener: tokio::net::TcpListener) {
+    /// let (stream, addr) = listener.accept().await.unwrap();
+    /// let svc = service_fn(move |req| {
+    ///     Router::route_with_addr(Arc::clone(&router), req, Some(addr))
+    /// });
+    /// # }
+    /// ```
+    pub async fn route_with_addr(
+        self: Arc<Self>,
+        req: Request<Incoming>,
+        addr: Option<SocketAddr>,
+    ) -> crate::Result {
+        let before = Instant::now();
+        let method = req.method().clone();
+        #[cfg(feature = "logging")]
+        let path = req.uri().path().to_string();
+
+        let mut pattern = None;
+        let mut resp = self.respond(req, addr, &mut pattern).await;
+        if let Ok(resp) = &mut resp {
+            let headers = resp.headers_mut();
+            for name in self.default_headers.keys() {
+                if !headers.contains_key(name) {
+                    for value in self.default_headers.get_all(name) {
+                        headers.append(name, value.clone());
+                    }
+                }
+            }
+        }
+
+        if let Some(on_request_complete) = &self.on_request_complete {
+            on_request_complete(&RequestMetrics {
+                method: &method,
+                route: pattern.as_ref(),
+                status: resp.as_ref().ok().map(Response::status),
+                elapsed: before.elapsed(),
+            });
+        }
+
+        #[cfg(feature = "logging")]
+        {
+            let elapsed = before.elapsed();
+            // The route's pattern keeps the field's cardinality low.
+            let route = pattern.map_or_else(|| path.clone(), |pattern| pattern.to_string());
+            let request_id = resp.as_ref().ok().and_then(|resp| {
+                resp.extensions()
+                    .get::<crate::middleware::RequestId>()
+                    .and_then(|id| id.0.to_str().ok())
+            });
+
+            if let Some(format) = &self.access_log {
+                let line = format(&LogRecord {
+                    method: &method,
+                    path: &path,
+                    route: &route,
+                    status: resp.as_ref().ok().map(Response::status),
+                    elapsed,
+                    remote_addr: addr,
+                    request_id,
+                });
+                match resp {
+                    Ok(_) => tracing::info!("{}", line),
+                    Err(ref err) => tracing::error!(error = %err, "{}", line),
+                }
+            } else {
+                match resp {
+                    Ok(ref resp) => tracing::info!(
+                        %method,
+                        path,
+                        route,
+                        status = resp.status().as_u16(),
+                        request_id,
+                        ?elapsed,
+                        "request"
+                    ),
+                    Err(ref err) => tracing::error!(
+                        %method,
+                        path,
+                        route,
+                        ?elapsed,
+                        error = %err,
+                        "request failed"
+                    ),
+                }
+            }
+        }
+
+        resp
+    }
+
+    /// Generates the response to `req` from `addr`, before logging, setting
+    /// `pattern` to the path of the matched route.
+    async fn respond(
+        &self,
+        mut req: Request<Incoming>,
+        addr: Option<SocketAddr>,
+        pattern: &mut Option<Path>,
+    ) -> crate::Result {
+        if let Some(router) = self.host_router(&req) {
+            return Box::pin(router.respond(req, addr, pattern)).await;
+        }
+
+        // `OPTIONS *` asks about the server as a whole rather than a route.
+        if req.method() == Method::OPTIONS && req.uri().path() == "*" {
+            let mut methods = self.allowed_methods();
+            if !methods.contains(&Method::OPTIONS) {
+                methods.push(Method::OPTIONS);
+                HttpMethod::sort(&mut methods);
+            }
+            let allow = methods
+                .iter()
+                .map(Method::as_str)
+                .collect::<Vec<_>>()
+                .join(", ");
+            let resp = Response::builder()
+                .status(StatusCode::NO_CONTENT)
+                .header(header::ALLOW, allow)
+                .body(full(""))
+                .unwrap();
+            return self.finish(Ok(resp));
+        }
+
+        if let Some(limit) = self.max_body_size {
+            let too_large = req
+                .headers()
+                .get(header::CONTENT_LENGTH)
+                .and_then(|len| len.to_str().ok()?.parse::<u64>().ok())
+                .is_some_and(|len| len > limit as u64);
+            if too_large {
+                let resp = self.error_page(
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    req.method(),
+                    req.uri().path(),
+                );
+                return self.finish(Ok(resp));
+            }
+
+            req.extensions_mut().insert(MaxBodySize(limit));
+        }
+
+        let path = req.uri().path().to_string();
+        if path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .count()
+            > self.max_path_segments
+        {
+            let resp = self.error_page(StatusCode::URI_TOO_LONG, req.method(), &path);
+            return self.finish(Ok(resp));
+        }
+        let path = self.index_path(&path).unwrap_or(path);
+
+        let query = req.uri().query().unwrap_or_default();
+        let Some(query) = form_decode(query, self.strict_query) else {
+            let resp = self.bad_request(&req);
+            return self.finish(Ok(resp));
+        };
+
+        let trailing_slash = path.len() > 1 && path.ends_with('/');
+        match self.trailing_slash {
+            TrailingSlash::Strict if trailing_slash => {
+                return self.unmatched(req, addr, pattern).await;
+            }
+            TrailingSlash::RedirectToNoSlash
+                if trailing_slash && self.match_route(&path).is_ok() =>
+            {
+                // Collapsing leading slashes keeps `//evil.com/` from
+                // redirecting to the protocol-relative `//evil.com`.
+                let mut location = format!("/{}", path.trim_matches('/'));
+                if let Some(query) = req.uri().query() {
+                    location = format!("{}?{}", location, query);
+                }
+
+                let resp = Response::builder()
+                    .status(StatusCode::PERMANENT_REDIRECT)
+                    .header(header::LOCATION, location)
+                    .body(full(""))
+                    .unwrap();
+                return self.finish(Ok(resp));
+            }
+            _ => {}
+        }
+
+        let (route, params, params_by_name) = match self.match_route(&path) {
+            Ok(route_match) if route_match.0.guards_match(req.headers()) => route_match,
+            Ok(_) | Err(MatchError::NotFound | MatchError::NoRoute) => {
+                return self.unmatched(req, addr, pattern).await;
+            }
+            Err(MatchError::InvalidParam) => {
+                let resp = self.bad_request(&req);
+                return self.finish(Ok(resp));
+            }
+        };
+
+        *pattern = Some(route.path.clone());
+        let mut ctx = Context {
+            params,
+            params_by_name,
+            query,
+            ex: Arc::clone(&self.ex),
+            extensions: Default::default(),
+            deadline: self.timeout.map(|timeout| Instant::now() + timeout),
+            matched_pattern: Some(route.path.clone()),
+            headers: Arc::new(req.headers().clone()),
+            method: req.method().clone(),
+            remote_addr: addr,
+            request_id: None,
+        };
+        if self.is_secure(&req) {
+            ctx.extensions.insert(Secure);
+        }
+
+        let on_panic = self.root.panic_handler(&route.path);
+        let panic_ctx = ctx.clone();
+        let request_method = req.method().clone();
+
+        let dispatch = async {
+            let resp = self.dispatch(route, req, ctx);
+
+            match self.timeout {
+                Some(timeout) => tokio::time::timeout(timeout, resp)
+                    .await
+                    .unwrap_or_else(|_| {
+                        let status = self.timeout_status;
+                        let mut resp = Response::builder().status(status);
+                        if request_method.is_idempotent() {
+                            let secs = timeout.as_secs() + u64::from(timeout.subsec_nanos() > 0);
+                            resp = resp.header(header::RETRY_AFTER, secs);
+                        }
+                        Ok(resp
+                            .body(full(status.canonical_reason().unwrap_or_default()))
+                            .unwrap())
+                    }),
+                None => resp.await,
+            }
+        };
+
+        // Handlers are not required to be `UnwindSafe`; state observed by the
+        // panic handler may be left inconsistent by the panicking handler.
+        let resp = match AssertUnwindSafe(dispatch).catch_unwind().await {
+            Ok(resp) => resp,
+            Err(panic) => match on_panic {
+                Some(on_panic) => on_panic(panic, panic_ctx).await,
+                None => {
+                    Ok(self.error_page(StatusCode::INTERNAL_SERVER_ERROR, &request_method, &path))
+                }
+            },
+        };
+        self.finish(resp)
+    }
+
+    /// Binds `addr` and serves connections with the router, speaking the
+    /// HTTP versions set with [`protocol`](Self::protocol).
+    ///
+    /// Only returns if binding or accepting a connection fails. Errors on
+    /// individual connections are logged with the `logging` feature, and
+    /// otherwise ignored.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use radmin_router::Router;
+    /// use std::net::SocketAddr;
+    /// use std::sync::Arc;
+    ///
+    /// # async fn run() -> std::io::Result<()> {
+    /// let router = Arc::new(Router::<()>::default());
+    /// router.serve(SocketAddr::from(([0, 0, 0, 0], 3030))).await
+    /// # }
+    /// ```
+    pub async fn serve(self: Arc<Self>, addr: SocketAddr) -> std::io::Result<()> {
+        self.serve_with_shutdown(addr, future::pending()).await
+    }
+
+    /// Like [`serve`](Router::serve), but stops accepting connections once
+    /// `shutdown` resolves, then waits for in-flight connections to finish
+    /// their current request.
+    pub async fn serve_with_shutdown(
+        self: Arc<Self>,
+        addr: SocketAddr,
+        shutdown: impl Future<Output = ()>,
+    ) -> std::io::Result<()> {
+        self.serve_streams(addr, shutdown, false, |stream| future::ready(Ok(stream)))
+            .await
+    }
+
+    /// Like [`serve`](Router::serve), but over TLS. Requests are marked
+    /// [`Secure`].
+    ///
+    /// If `config` has no ALPN protocols, those of the router's
+    /// [`protocol`](Self::protocol) are offered, so HTTP/2 is negotiated with
+    /// [`Protocol::Auto`]. Failed handshakes are reported like other
+    /// connection errors.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use radmin_router::{Router, load_tls_config};
+    /// use std::net::SocketAddr;
+    /// use std::sync::Arc;
+    ///
+    /// # async fn run() -> std::io::Result<()> {
+    /// let config = load_tls_config("cert.pem", "key.pem")?;
+    /// let router = Arc::new(Router::<()>::default());
+    /// router.serve_tls(SocketAddr::from(([0, 0, 0, 0], 443)), config).await
+    /// # }
+    /// ```
+    #[cfg(feature = "tls")]
+    pub async fn serve_tls(
+        self: Arc<Self>,
+        addr: SocketAddr,
+        config: rustls::ServerConfig,
+    ) -> std::io::Result<()> {
+        self.serve_tls_with_shutdown(addr, config, future::pending())
+            .await
+    }
+
+    /// Like [`serve_tls`](Router::serve_tls), but shuts down like
+    /// [`serve_with_shutdown`](Router::serve_with_shutdown).
+    #[cfg(feature = "tls")]
+    pub async fn serve_tls_with_shutdown(
+        self: Arc<Self>,
+        addr: SocketAddr,
+        mut config: rustls::ServerConfig,
+        shutdown: impl Future<Output = ()>,
+    ) -> std::io::Result<()> {
+        if config.alpn_protocols.is_empty() {
+            config.alpn_protocols = match self.protocol {
+                Protocol::Http1 => vec![b"http/1.1".to_vec()],
+                #[cfg(feature = "http2")]
+                Protocol::Http2 => vec![b"h2".to_vec()],
+                #[cfg(feature = "http2")]
+                Protocol::Auto => vec![b"h2".to_vec(), b"http/1.1".to_vec()],
+            };
+        }
+
+        let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(config));
+        self.serve_streams(addr, shutdown, true, move |stream| acceptor.accept(stream))
+            .await
+    }
+
+    /// Serves the connections accepted on `addr`, each set up by `accept`
+    /// first, until `shutdown` resolves. With `secure`, requests are marked
+    /// [`Secure`].
+    async fn serve_streams<A, Fut, S>(
+        self: Arc<Self>,
+        addr: SocketAddr,
+        shutdown: impl Future<Output = ()>,
+        secure: bool,
+        accept: A,
+    ) -> std::io::Result<()>
+    where
+        A: Fn(TcpStream) -> Fut,
+        Fut: Future<Output = std::io::Result<S>> + Send + 'static,
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let listener = TcpListener::bind(addr).await?;
+        let graceful = GracefulShutdown::new();
+        let mut shutdown = pin!(shutdown);
+
+        loop {
+            let (stream, addr) = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok(accepted) => accepted,
+                    // Failures such as running out of file descriptors tend
+                    // to pass, so keep accepting after a pause.
+                    Err(err) => {
+                        report_connection_error(self.on_connection_error.as_ref(), err.into());
+                        tokio::time::sleep(ACCEPT_BACKOFF).await;
+                        continue;
+                    }
+                },
+                () = &mut shutdown => break,
+            };
+
+            let router = Arc::clone(&self);
+            let svc = service_fn(move |mut req: Request<Incoming>| {
+                if secure {
+                    req.extensions_mut().insert(Secure);
+                }
+                Router::route_with_addr(Arc::clone(&router), req, Some(addr))
+            });
+            let accepting = accept(stream);
+            let watcher = graceful.watcher();
+            let on_error = self.on_connection_error.clone();
+            let protocol = self.protocol;
+
+            tokio::spawn(async move {
+                let report = |err| report_connection_error(on_error.as_ref(), err);
+                let stream = match accepting.await {
+                    Ok(stream) => stream,
+                    Err(err) => return report(err.into()),
+                };
+
+                let builder = auto::Builder::new(TokioExecutor::new());
+                let builder = match protocol {
+                    Protocol::Http1 => builder.http1_only(),
+                    #[cfg(feature = "http2")]
+                    Protocol::Http2 => builder.http2_only(),
+                    #[cfg(feature = "http2")]
+                    Protocol::Auto => builder,
+                };
+
+                // Upgrades are used by WebSocket handlers.
+                let conn = builder.serve_connection_with_upgrades(TokioIo::new(stream), svc);
+                if let Err(err) = watcher.watch(conn).await {
+                    report(err);
+                }
+            });
+        }
+
+        graceful.shutdown().await;
+        Ok(())
+    }
+}
+
+/// An immutable, cheaply cloneable router produced by [`Router::finalize`].
+///
+/// No routes, handlers or middleware can be added once finalized:
+///
+/// ```compile_fail
+/// use radmin_router::{Route, Router, path};
+///
+/// Router::<()>::default()
+///     .finalize()
+///     .register(Route::new(path!("/")));
+/// ```
+pub struct FinalizedRouter<Extra: Send + Sync> {
+    inner: Arc<Router<Extra>>,
+}
+
+impl<Extra: Send + Sync> Clone for FinalizedRouter<Extra> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<Extra: Debug + Send + Sync> Debug for FinalizedRouter<Extra> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("FinalizedRouter").field(&self.inner).finish()
+    }
+}
+
+impl<Extra: Send + Sync + 'static> FinalizedRouter<Extra> {
+    /// Processes an incoming request and generates a response for hyper.
+    pub fn route(
+        &self,
+        req: Request<Incoming>,
+    ) -> impl Future<Output = crate::Result> + Send + use<Extra> {
+        Router::route(Arc::clone(&self.inner), req)
+    }
+
+    /// See [`Router::route_with_addr`].
+    pub fn route_with_addr(
+        &self,
+        req: Request<Incoming>,
+        addr: Option<SocketAddr>,
+    ) -> impl Future<Output = crate::Result> + Send + use<Extra> {
+        Router::route_with_addr(Arc::clone(&self.inner), req, addr)
+    }
+
+    /// See [`Router::recognize`].
+    #[cfg(feature = "test-util")]
+    pub fn recognize(&self, path: &str) -> Option<(Arc<Route<Extra>>, Vec<String>)> {
+        self.inner.recognize(path)
+    }
+}
+
+/// Passes a connection error to the [`Router::on_connection_error`] hook, or
+/// logs it without one.
+fn report_connection_error(
+    on_error: Option<&ConnectionErrorHandler>,
+    err: Box<dyn Error + Send + Sync>,
+) {
+    match on_error {
+        Some(on_error) => on_error(err),
+        #[cfg(feature = "logging")]
+        None => tracing::warn!(error = %err, "connection failed"),
+        #[cfg(not(feature = "logging"))]
+        None => drop(err),
+    }
+}
+
+/// Splits a request path into its non-empty segments.
+fn split_segments(path: &str) -> Vec<&str> {
+    path.split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect()
+}
+
+/// Records trying the `branch` child of a node for `segment` while matching,
+/// with the `logging` feature.
+#[inline(always)]
+fn trace_branch(segment: &str, branch: &'static str) {
+    #[cfg(feature = "logging")]
+    tracing::trace!(segment, branch, "trying branch");
+    #[cfg(not(feature = "logging"))]
+    let _ = (segment, branch);
+}
+
+/// Ranks the segments of a matched route's path, from 0 for literals to 5
+/// for catch-alls. Comparing the ranks in order ranks overlapping routes the
+/// way matching does.
+#[cfg(feature = "logging")]
+fn specificity(path: &Path) -> Vec<u8> {
+    path.0
+        .iter()
+        .map(|segment| match segment {
+            Segment::Literal(_) => 0,
+            Segment::Pattern(_) => 1,
+            Segment::Constrained(..) => 2,
+            Segment::Dynamic(_) => 3,
+            Segment::Optional(_) => 4,
+            Segment::CatchAll(_) => 5,
+        })
+        .collect()
+}
+
+/// Returns whether a request path has empty segments besides the root and a
+/// single trailing slash.
+fn has_empty_segments(path: &str) -> bool {
+    if path == "/" {
+        return false;
+    }
+
+    let path = path.strip_prefix('/').unwrap_or(path);
+    let path = path.strip_suffix('/').unwrap_or(path);
+    path.split('/').any(str::is_empty)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path;
+    use crate::segment::Constraint;
+    use http_body_util::{BodyExt, Full};
+    use macros::CaseIterable;
+
+    #[test]
+    fn match_catch_all() {
+        let router = Router::<()>::default()
+            .register(Route::new(path!("/a")))
+            .register(Route::new(path!("/a/[...rest]")));
+
+        let (route, params, _) = router.match_route("/a/b/c").unwrap();
+        assert_eq!(route.path, path!("/a/[...rest]"));
+        assert_eq!(params, vec!["b/c".to_string()]);
+
+        let (route, params, _) = router.match_route("/a").unwrap();
+        assert_eq!(route.path, path!("/a"));
+        assert!(params.is_empty());
+    }
+
+    #[tokio::test]
+    async fn literals_shadow_catch_alls() {
+        use crate::testing::send;
+
+        let handler = |name: &'static str| {
+            move |_, ctx: Context<()>| -> std::pin::Pin<Box<crate::ResponseFut>> {
+                let body = format!("{} {:?}", name, ctx.param("path"));
+                Box::pin(async move { Ok(Response::new(full(body))) })
+            }
+        };
+        let mut router = Router::<()>::default()
+            .register(Route::new(path!("/files/[...path]")).get(handler("catch-all")))
+            .register(Route::new(path!("/files/readme")).get(handler("readme")));
+
+        for (path, body) in [
+            ("/files/readme", "readme None"),
+            ("/files/a/b", r#"catch-all Some("a/b")"#),
+            ("/files/a/b/", r#"catch-all Some("a/b")"#),
+            ("/files/readme/old", r#"catch-all Some("readme/old")"#),
+            ("/files", r#"catch-all Some("")"#),
+            ("/files/", r#"catch-all Some("")"#),
+        ] {
+            let req = Request::get(path).body(Default::default()).unwrap();
+            let resp = send(router.clone(), req).await;
+            assert_eq!(resp.body(), body, "{path}");
+        }
+
+        router.trailing_slash(TrailingSlash::Strict);
+        let req = Request::get("/files/").body(Default::default()).unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn route_priorities() {
+        let matched = |router: &Router<()>, path: &str| {
+            let (route, _) = router.recognize(path).unwrap();
+            route.path.to_string()
+        };
+
+        let maintenance = |priority| {
+            Router::<()>::default()
+                .register(Route::new(path!("/users/admin")))
+                .register(Route::new(path!("/users/[id]")))
+                .register(Route::new(path!("/[...path]")).priority(priority))
+        };
+
+        let router = maintenance(10);
+        assert_eq!(matched(&router, "/users/admin"), "/[...path]");
+        assert_eq!(matched(&router, "/users/7"), "/[...path]");
+        let finalized = router.finalize();
+        let (route, _) = finalized.recognize("/users/admin").unwrap();
+        assert_eq!(route.path, path!("/[...path]"));
+
+        // Equal priorities fall back to specificity.
+        let router = maintenance(0)
+            .register(Route::new(path!("/users/admin")).priority(10))
+            .register(Route::new(path!("/users/[id]")).priority(10))
+            .register(Route::new(path!("/[...path]")).priority(10));
+        assert_eq!(matched(&router, "/users/admin"), "/users/admin");
+        assert_eq!(matched(&router, "/users/7"), "/users/[id]");
+        assert_eq!(matched(&router, "/posts"), "/[...path]");
+
+        let router = maintenance(0).register(Route::new(path!("/users/admin")).priority(-1));
+        assert_eq!(matched(&router, "/users/admin"), "/users/[id]");
+    }
+
+    #[test]
+    fn register_many_prints_nothing() {
+        // Registers routes in a child test process, whose output isn't
+        // captured, and checks nothing was printed between the markers.
+        if std::env::var_os("RADMIN_ROUTER_REGISTER_CHILD").is_some() {
+            println!("<registering>");
+            Router::<()>::default()
+                .register_many([Route::new(path!("/a")), Route::new(path!("/b/[id]"))]);
+            println!("</registering>");
+            return;
+        }
+
+        let output = std::process::Command::new(std::env::current_exe().unwrap())
+            .args(["router::tests::register_many_prints_nothing", "--exact"])
+            .arg("--nocapture")
+            .env("RADMIN_ROUTER_REGISTER_CHILD", "1")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(
+            stdout.contains("<registering>\n</registering>\n"),
+            "{stdout}"
+        );
+    }
+
+    #[test]
+    fn case_insensitive_literals() {
+        let router = || {
+            Router::<()>::default()
+                .register(Route::new(path!("/api/users/[id]")))
+                .register(Route::new(path!("/api/café")))
+        };
+
+        let strict = router();
+        assert!(strict.match_route("/API/Users/Bob").is_err());
+        let (route, ..) = strict.match_route("/api/users/Bob").unwrap();
+        assert_eq!(route.path, path!("/api/users/[id]"));
+
+        let mut relaxed = router();
+        relaxed.case_insensitive(true);
+        let finalized = relaxed.clone().finalize();
+        for router in [&relaxed, &*finalized.inner] {
+            let (route, params, _) = router.match_route("/API/Users/Bob").unwrap();
+            assert_eq!(route.path, path!("/api/users/[id]"));
+            assert_eq!(params, vec!["Bob".to_string()]);
+
+            let (route, ..) = router.match_route("/Api/caf%C3%A9").unwrap();
+            assert_eq!(route.path, path!("/api/café"));
+            assert!(router.match_route("/api/CAFÉ").is_err());
+        }
+    }
+
+    /// The matcher as it was before pattern children were ordered ahead of
+    /// time and literal lookups shared a key, collecting and sorting the
+    /// candidates at each segment.
+    fn reference_find_route<'a>(
+        router: &Router<()>,
+        node: &'a Node<()>,
+        segments: &[&str],
+    ) -> Option<&'a Arc<Route<()>>> {
+        let segments =
+            node.strip_chain(segments, router.case_insensitive, router.decode_segments())?;
+        let Some((segment, rest)) = segments.split_first() else {
+            return node
+                .route
+                .as_ref()
+                .or_else(|| {
+                    let optional = node.children.get(&Segment::optional(""))?;
+                    reference_find_route(router, optional, &[])
+                })
+                .or_else(|| node.children.get(&Segment::catch_all(""))?.route.as_ref());
+        };
+
+        let literal =
+            node.children.get(&Segment::literal(*segment)).or_else(|| {
+                match percent_decode(segment) {
+                    Cow::Owned(decoded) if router.decode_segments() => {
+                        node.children.get(&Segment::literal(decoded))
+                    }
+                    _ => None,
+                }
+            });
+        let literal = literal.or_else(|| {
+            router
+                .case_insensitive
+                .then(|| node.literal_child_ignore_case(segment, router.decode_segments()))
+                .flatten()
+        });
+
+        if let Some(literal) = literal {
+            if let Some(route) = reference_find_route(router, literal, rest) {
+                return Some(route);
+            }
+
+            if literal.exact {
+                return None;
+            }
+        }
+
+        let mut patterns: Vec<_> = node
+            .children
+            .iter()
+            .filter(|(pattern, _)| {
+                matches!(pattern, Segment::Pattern(_))
+                    && pattern.is_match_with(segment, router.decode_segments())
+            })
+            .collect();
+        patterns.sort_by_cached_key(|(pattern, _)| {
+            let Segment::Pattern(parts) = pattern else {
+                unreachable!("only patterns have captures")
+            };
+            let literal_len: usize = parts
+                .iter()
+                .map(|part| match part {
+                    PatternPart::Literal(literal) => literal.len(),
+                    PatternPart::Placeholder(_) => 0,
+                })
+                .sum();
+            (std::cmp::Reverse(literal_len), format!("{pattern:?}"))
+        });
+        for (_, pattern) in patterns {
+            if let Some(route) = reference_find_route(router, pattern, rest) {
+                return Some(route);
+            }
+        }
+
+        for &constraint in Constraint::ALL_CASES {
+            if constraint.matches(segment)
+                && let Some(constrained) = node.children.get(&Segment::constrained("", constraint))
+                && let Some(route) = reference_find_route(router, constrained, rest)
+            {
+                return Some(route);
+            }
+        }
+
+        if let Some(dynamic) = node.children.get(&Segment::dynamic(""))
+            && let Some(route) = reference_find_route(router, dynamic, rest)
+        {
+            return Some(route);
+        }
+
+        if let Some(optional) = node.children.get(&Segment::optional(""))
+            && let Some(route) = reference_find_route(router, optional, rest)
+        {
+            return Some(route);
+        }
+
+        node.children
+            .get(&Segment::catch_all(""))
+            .and_then(|catch_all| catch_all.route.as_ref())
+    }
+
+    #[test]
+    fn matches_like_reference_implementation() {
+        const ROUTE_SEGMENTS: &[&str] = &[
+            "a",
+            "b",
+            "A",
+            "c%20d",
+            "[x]",
+            "[n:int]",
+            "[o?]",
+            "r-[y].csv",
+            "r-[y]",
+            "[s].[t]",
+            "[...rest]",
+        ];
+        const REQUEST_SEGMENTS: &[&str] = &[
+            "a", "b", "A", "c%20d", "c d", "1", "r-2.csv", "r-x", "x.y", "zz",
+        ];
+
+        // A fixed xorshift sequence keeps failures reproducible.
+        struct Rng(u64);
+
+        impl Rng {
+            fn below(&mut self, n: usize) -> usize {
+                self.0 ^= self.0 << 13;
+                self.0 ^= self.0 >> 7;
+                self.0 ^= self.0 << 17;
+                (self.0 % n as u64) as usize
+            }
+
+            fn path(&mut self, segments: &[&str], max_len: usize) -> String {
+                let len = self.below(max_len + 1);
+                let path: String = (0..len)
+                    .map(|_| format!("/{}", segments[self.below(segments.len())]))
+                    .collect();
+                if path.is_empty() {
+                    "/".to_string()
+                } else {
+                    path
+                }
+            }
+        }
+
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        let mut matched = 0;
+        for _ in 0..300 {
+            let mut router = Router::<()>::default();
+            router.case_insensitive(rng.below(2) == 0);
+            for _ in 0..12 {
+                let Ok(path) = rng.path(ROUTE_SEGMENTS, 4).parse::<Path>() else {
+                    continue;
+                };
+                let route = Route::new(path);
+                router = router.register(if rng.below(4) == 0 {
+                    route.exact()
+                } else {
+                    route
+                });
+            }
+
+            let requests: Vec<_> = (0..40).map(|_| rng.path(REQUEST_SEGMENTS, 5)).collect();
+            for compacted in [false, true] {
+                if compacted {
+                    router.root.compact();
+                }
+
+                for request in &requests {
+                    let segments = split_segments(request);
+                    let expected = reference_find_route(&router, &router.root, &segments);
+                    let actual = router.match_route(request).ok();
+                    matched += usize::from(expected.is_some());
+                    assert_eq!(
+                        actual.map(|(route, ..)| route.path.clone()),
+                        expected.map(|route| route.path.clone()),
+                        "{request} in {:?}",
+                        router.routes()
+                    );
+                }
+            }
+        }
+        assert!(matched > 1000, "only {matched} requests matched");
+    }
+
+    #[tokio::test]
+    async fn registers_resources() {
+        use crate::testing::send;
+
+        let handler = |name: &'static str| {
+            move |_, ctx: Context<()>| -> std::pin::Pin<Box<crate::ResponseFut>> {
+                let body = format!("{} {}", name, ctx.params.join(" "));
+                Box::pin(async move { Ok(Response::new(full(body.trim_end().to_string()))) })
+            }
+        };
+        let mut router = Router::<()>::default();
+        router.resource(
+            "/widgets/",
+            ResourceHandlers::default()
+                .index(handler("index"))
+                .create(handler("create"))
+                .show(handler("show"))
+                .update(handler("update"))
+                .destroy(handler("destroy")),
+        );
+        router.resource(
+            "/gadgets",
+            ResourceHandlers::default().show(handler("gadget")),
+        );
+        let router = Arc::new(router);
+
+        for (method, path, body) in [
+            (Method::GET, "/widgets", "index"),
+            (Method::POST, "/widgets", "create"),
+            (Method::GET, "/widgets/7", "show 7"),
+            (Method::PUT, "/widgets/7", "update 7"),
+            (Method::DELETE, "/widgets/7", "destroy 7"),
+            (Method::GET, "/gadgets/1", "gadget 1"),
+        ] {
+            let req = Request::builder()
+                .method(method.clone())
+                .uri(path)
+                .body(Default::default())
+                .unwrap();
+            let resp = send(Arc::clone(&router), req).await;
+            assert_eq!(resp.body(), body, "{method} {path}");
+        }
+
+        let req = Request::get("/gadgets").body(Default::default()).unwrap();
+        let resp = send(Arc::clone(&router), req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        let req = Request::delete("/gadgets/1")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    #[tokio::test]
+    async fn default_headers() {
+        use crate::testing::send;
+        use hyper::header::{X_CONTENT_TYPE_OPTIONS, X_FRAME_OPTIONS};
+
+        let mut headers = HeaderMap::new();
+        headers.insert(X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff"));
+        headers.insert(X_FRAME_OPTIONS, HeaderValue::from_static("DENY"));
+        let mut router = Router::<()>::default()
+            .register(
+                Route::new(path!("/plain"))
+                    .get(|_, _| Box::pin(async { Ok(Response::new(full("OK"))) })),
+            )
+            .register(Route::new(path!("/embeddable")).get(|_, _| {
+                Box::pin(async {
+                    Ok(Response::builder()
+                        .header(X_FRAME_OPTIONS, "SAMEORIGIN")
+                        .body(full("OK"))
+                        .unwrap())
+                })
+            }))
+            .register(Route::new(path!("/panics")).get(|_, _| panic!("handler bug")));
+        router.default_headers(headers);
+        let router = Arc::new(router);
+
+        for (path, status, frame_options) in [
+            ("/plain", StatusCode::OK, "DENY"),
+            ("/missing", StatusCode::NOT_FOUND, "DENY"),
+            ("/panics", StatusCode::INTERNAL_SERVER_ERROR, "DENY"),
+            ("/embeddable", StatusCode::OK, "SAMEORIGIN"),
+        ] {
+            let req = Request::get(path).body(Default::default()).unwrap();
+            let resp = send(Arc::clone(&router), req).await;
+            assert_eq!(resp.status(), status, "{path}");
+            assert_eq!(resp.headers()[X_CONTENT_TYPE_OPTIONS], "nosniff", "{path}");
+            let values: Vec<_> = resp
+                .headers()
+                .get_all(X_FRAME_OPTIONS)
+                .iter()
+                .map(|value| value.to_str().unwrap())
+                .collect();
+            assert_eq!(values, [frame_options], "{path}");
+        }
+
+        let req = Request::post("/plain").body(Default::default()).unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(resp.headers()[X_FRAME_OPTIONS], "DENY");
+    }
+
+    #[tokio::test]
+    async fn options_asterisk() {
+        use crate::testing::send;
+
+        let ok = |_, _| -> std::pin::Pin<Box<crate::ResponseFut>> {
+            Box::pin(async { Ok(Response::new(full("OK"))) })
+        };
+        let router = Arc::new(
+            Router::<()>::default()
+                .register(Route::new(path!("/users")).get(ok).post(ok))
+                .register(Route::new(path!("/users/[id]")).put(ok).delete(ok))
+                .register(
+                    Route::new(path!("/cache")).method(Method::from_bytes(b"PURGE").unwrap(), ok),
+                ),
+        );
+
+        let req = Request::options("*").body(Default::default()).unwrap();
+        let resp = send(Arc::clone(&router), req).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert_eq!(
+            resp.headers()[header::ALLOW],
+            "GET, POST, PUT, DELETE, OPTIONS, PURGE"
+        );
+
+        // Other methods with an asterisk target aren't special.
+        let req = Request::get("*").body(Default::default()).unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn max_path_segments() {
+        use crate::testing::send;
+
+        let mut router = Router::<()>::default().register(
+            Route::new(path!("/[...rest]"))
+                .get(|_, _| Box::pin(async { Ok(Response::new(full("OK"))) })),
+        );
+        router.max_path_segments(8);
+        let router = Arc::new(router);
+
+        for (segments, status) in [
+            (8, StatusCode::OK),
+            (9, StatusCode::URI_TOO_LONG),
+            (1000, StatusCode::URI_TOO_LONG),
+        ] {
+            let path = "/a".repeat(segments);
+            let req = Request::get(&path).body(Default::default()).unwrap();
+            let resp = send(Arc::clone(&router), req).await;
+            assert_eq!(resp.status(), status, "{segments} segments");
+        }
+
+        // Empty segments don't count.
+        let req = Request::get("/a//a//a//a//a//a//a//a//")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn health_checks() {
+        use crate::testing::send;
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let mut router = Router::<AtomicBool>::default();
+        router.with_health_checks(|ready| Box::pin(async move { ready.load(Ordering::SeqCst) }));
+        let router = Arc::new(router);
+        let get = |path| Request::get(path).body(Default::default()).unwrap();
+
+        for ready in [false, true, false] {
+            router.ex.store(ready, Ordering::SeqCst);
+
+            let resp = send(Arc::clone(&router), get("/healthz")).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+
+            let resp = send(Arc::clone(&router), get("/readyz")).await;
+            let status = if ready {
+                StatusCode::OK
+            } else {
+                StatusCode::SERVICE_UNAVAILABLE
+            };
+            assert_eq!(resp.status(), status);
+        }
+    }
+
+    #[test]
+    fn unregister_prunes_empty_nodes() {
+        let mut router = Router::<()>::default()
+            .register(Route::new(path!("/a/b")).name("b"))
+            .register(Route::new(path!("/a/b/c")))
+            .register(Route::new(path!("/x/[id]")));
+
+        let removed = router.unregister(&path!("/x/[other]")).unwrap();
+        assert_eq!(removed.path, path!("/x/[id]"));
+        assert!(router.root.descendant(&path!("/x")).is_none());
+
+        let removed = router.unregister(&path!("/a/b")).unwrap();
+        assert_eq!(removed.path, path!("/a/b"));
+        let interior = router.root.descendant(&path!("/a/b")).unwrap();
+        assert!(interior.route.is_none());
+        assert_eq!(interior.children.len(), 1);
+        assert!(router.match_route("/a/b").is_err());
+        assert!(router.match_route("/a/b/c").is_ok());
+        assert_eq!(router.url_for("b", &[]), None);
+
+        assert!(router.unregister(&path!("/a/b")).is_none());
+        assert!(router.unregister(&path!("/a")).is_none());
+        assert!(router.unregister(&path!("/missing")).is_none());
+        assert!(router.root.descendant(&path!("/a/b/c")).is_some());
+    }
+
+    #[test]
+    fn resolves_overlapping_routes_by_specificity() {
+        let paths = [
+            path!("/a/[x]/c"),
+            path!("/a/b/[y]"),
+            path!("/a/[x]/[...rest]"),
+            path!("/files/[id]/raw"),
+            path!("/files/[...rest]"),
+            path!("/users/[name]"),
+            path!("/users/[id:int]"),
+            path!("/img/[file]"),
+            path!("/img/[name].png"),
+            path!("/img/[name].[ext]"),
+        ];
+        let forward = paths.iter().fold(Router::<()>::default(), |router, path| {
+            router.register(Route::new(path.clone()))
+        });
+        let reverse = paths
+            .iter()
+            .rev()
+            .fold(Router::<()>::default(), |router, path| {
+                router.register(Route::new(path.clone()))
+            });
+
+        for (request, winner) in [
+            ("/a/b/c", path!("/a/b/[y]")),
+            ("/a/z/c", path!("/a/[x]/c")),
+            ("/a/z/d", path!("/a/[x]/[...rest]")),
+            ("/a/b/c/d", path!("/a/[x]/[...rest]")),
+            ("/files/1/raw", path!("/files/[id]/raw")),
+            ("/files/1/thumb", path!("/files/[...rest]")),
+            ("/users/42", path!("/users/[id:int]")),
+            ("/users/bob", path!("/users/[name]")),
+            ("/img/logo.png", path!("/img/[name].png")),
+            ("/img/logo.svg", path!("/img/[name].[ext]")),
+            ("/img/logo", path!("/img/[file]")),
+        ] {
+            for router in [&forward, &reverse] {
+                let (route, ..) = router.match_route(request).unwrap();
+                assert_eq!(route.path, winner, "{request}");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn distinguishes_unmatched_paths() {
+        use crate::testing::send;
+
+        let mut router = Router::<()>::default().register(
+            Route::new(path!("/users/[id]/posts"))
+                .get(|_, _| Box::pin(async { Ok(Response::new(full("posts"))) })),
+        );
+
+        for finalized in [false, true] {
+            if finalized {
+                router.root.compact();
+            }
+
+            assert_eq!(
+                router.match_route("/missing").unwrap_err(),
+                MatchError::NotFound
+            );
+            assert_eq!(
+                router.match_route("/users/7/comments").unwrap_err(),
+                MatchError::NotFound
+            );
+            assert_eq!(
+                router.match_route("/users").unwrap_err(),
+                MatchError::NoRoute
+            );
+            assert_eq!(
+                router.match_route("/users/7").unwrap_err(),
+                MatchError::NoRoute
+            );
+            assert!(router.match_route("/users/7/posts").is_ok());
+        }
+
+        let router = Arc::new(router);
+        for path in ["/missing", "/users/7"] {
+            let req = Request::get(path).body(Default::default()).unwrap();
+            let resp = send(Arc::clone(&router), req).await;
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND, "{path}");
+        }
+
+        let req = Request::delete("/users/7/posts")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(resp.headers()[header::ALLOW], "GET");
+    }
+
+    #[test]
+    fn matches_root_beside_dynamic_segment() {
+        let router = Router::<()>::default()
+            .register(Route::new(path!("/[slug]")))
+            .register(Route::new(path!("/")));
+
+        for _ in 0..8 {
+            let (route, params, _) = router.match_route("/").unwrap();
+            assert_eq!(route.path, path!("/"));
+            assert!(params.is_empty());
+
+            let (route, params, _) = router.match_route("/x").unwrap();
+            assert_eq!(route.path, path!("/[slug]"));
+            assert_eq!(params, vec!["x".to_string()]);
+        }
+
+        let router = Router::<()>::default().register(Route::new(path!("/[slug]")));
+        assert!(matches!(router.match_route("/"), Err(MatchError::NoRoute)));
+    }
+
+    #[tokio::test]
+    async fn routes_by_host() {
+        use crate::testing::send;
+
+        let text = |body: &'static str| {
+            Router::<()>::default().register(
+                Route::new(path!("/"))
+                    .get(move |_, _| Box::pin(async move { Ok(Response::new(full(body))) })),
+            )
+        };
+        let mut router = text("default");
+        router
+            .host("*.example.com", text("tenant"))
+            .host("Admin.Example.com", text("admin"));
+        let router = Arc::new(router);
+
+        for (host, expected) in [
+            (Some("admin.example.com"), "admin"),
+            (Some("ADMIN.example.com:8080"), "admin"),
+            (Some("acme.example.com"), "tenant"),
+            (Some("a.b.example.com"), "tenant"),
+            (Some("example.com"), "default"),
+            (Some("admin.example.org"), "default"),
+            (None, "default"),
+        ] {
+            let mut req = Request::get("/");
+            if let Some(host) = host {
+                req = req.header(header::HOST, host);
+            }
+            let resp = send(Arc::clone(&router), req.body(Default::default()).unwrap()).await;
+            assert_eq!(resp.body().as_ref(), expected.as_bytes(), "{host:?}");
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid host pattern")]
+    fn rejects_inner_host_wildcards() {
+        Router::<()>::default().host("app.*.com", Router::default());
+    }
+
+    #[tokio::test]
+    async fn optional_segments() {
+        use crate::testing::send;
+
+        let router = Arc::new(
+            Router::<()>::default()
+                .register(
+                    Route::new(path!("/posts/[page?]"))
+                        .name("posts")
+                        .get(|_, ctx| {
+                            let body = format!("page {:?}", ctx.param("page"));
+                            Box::pin(async move { Ok(Response::new(full(body))) })
+                        }),
+                )
+                .register(
+                    Route::new(path!("/posts/latest"))
+                        .get(|_, _| Box::pin(async { Ok(Response::new(full("latest"))) })),
+                ),
+        );
+
+        for (path, body) in [
+            ("/posts", "page None"),
+            ("/posts/3", r#"page Some("3")"#),
+            ("/posts/latest", "latest"),
+        ] {
+            let req = Request::get(path).body(Default::default()).unwrap();
+            let resp = send(Arc::clone(&router), req).await;
+            assert_eq!(resp.body().as_ref(), body.as_bytes(), "{}", path);
+        }
+
+        let req = Request::get("/posts/3/4").body(Default::default()).unwrap();
+        let resp = send(Arc::clone(&router), req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        assert_eq!(router.url_for("posts", &[]).as_deref(), Some("/posts"));
+        assert_eq!(
+            router.url_for("posts", &[("page", "2")]).as_deref(),
+            Some("/posts/2")
+        );
+    }
+
+    #[test]
+    fn url_for_named_routes() {
+        let mut router = Router::<()>::default()
+            .register(Route::new(path!("/")).name("home"))
+            .register(Route::new(path!("/users/[id]")).name("users.show"))
+            .register(Route::new(path!("/reports/report-[year].csv")).name("reports.csv"))
+            .register(Route::new(path!("/files/[...path]")).name("files"));
+        router.merge_at(
+            path!("/teams/[team]"),
+            Router::default().register(Route::new(path!("/members/[id:int]")).name("members")),
+        );
+
+        assert_eq!(router.url_for("home", &[]).as_deref(), Some("/"));
+        assert_eq!(
+            router.url_for("users.show", &[("id", "42")]).as_deref(),
+            Some("/users/42")
+        );
+        assert_eq!(
+            router.url_for("users.show", &[("id", "a/b c")]).as_deref(),
+            Some("/users/a%2Fb%20c")
+        );
+        assert_eq!(
+            router
+                .url_for("reports.csv", &[("year", "2024")])
+                .as_deref(),
+            Some("/reports/report-2024.csv")
+        );
+        assert_eq!(
+            router
+                .url_for("files", &[("path", "docs/read me.txt")])
+                .as_deref(),
+            Some("/files/docs/read%20me.txt")
+        );
+        assert_eq!(
+            router
+                .url_for("members", &[("id", "7"), ("team", "core")])
+                .as_deref(),
+            Some("/teams/core/members/7")
+        );
+
+        assert_eq!(router.url_for("users.show", &[]), None);
+        assert_eq!(router.url_for("users.show", &[("id", "")]), None);
+        assert_eq!(router.url_for("members", &[("id", "7")]), None);
+        assert_eq!(router.url_for("unknown", &[]), None);
+    }
+
+    #[test]
+    fn compaction_keeps_matches() {
+        let mut router = Router::<()>::default()
+            .register(Route::new(path!("/a/b/c")))
+            .register(Route::new(path!("/a/b/c/[id]")))
+            .register(Route::new(path!("/a/b/d/e")))
+            .register(Route::new(path!("/deep/one/two/three")))
+            .register(Route::new(path!("/deep/one/[id]/three")))
+            .register(Route::new(path!("/x/y/z")).exact())
+            .register(Route::new(path!("/x/[id]/z")))
+            .register(Route::new(path!("/files/[...rest]")))
+            .register(Route::new(path!("/api/v1/users/[id]")))
+            .register(Route::new(path!("/100%25/off")));
+        router.on_panic_under(path!("/api/v1"), |_, _| {
+            Box::pin(async { Ok(Response::new(full("panicked"))) })
+        });
+        let compacted = router.clone().finalize();
+
+        let root = &compacted.inner.root;
+        assert_eq!(root.children.len(), 6);
+        assert_eq!(root.children[&Segment::literal("a")].chain, ["b"]);
+        assert_eq!(root.children[&Segment::literal("api")].chain, ["v1"]);
+
+        for path in [
+            "/a/b/c",
+            "/a/b/c/42",
+            "/a/b/d/e",
+            "/a/b/d",
+            "/a/b",
+            "/a/c/c",
+            "/deep/one/two/three",
+            "/deep/one/two/four",
+            "/deep/one/2/three",
+            "/deep/one",
+            "/x/y/z",
+            "/x/w/z",
+            "/x/y/w",
+            "/files/a/b",
+            "/api/v1/users/7",
+            "/api/v2/users/7",
+            "/100%25/off",
+            "/100%/off",
+            "/a/%62/c",
+        ] {
+            let expected = router
+                .match_route(path)
+                .map(|(route, params, by_name)| (route.path.clone(), params, by_name));
+            let actual = compacted
+                .inner
+                .match_route(path)
+                .map(|(route, params, by_name)| (route.path.clone(), params, by_name));
+            assert_eq!(actual, expected, "{path}");
+        }
+
+        assert!(root.panic_handler(&path!("/api/v1/users/[id]")).is_some());
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn route_with_context_injects_extensions() {
+        use crate::testing::send_with;
+
+        #[derive(Clone)]
+        struct User(&'static str);
+
+        let router = Arc::new(
+            Router::<()>::default().register(Route::new(path!("/me/[id]")).get(|_, ctx| {
+                Box::pin(async move {
+                    let user = ctx.extensions.get::<User>().unwrap();
+                    Ok(Response::new(full(format!("{} {}", user.0, ctx.params[0]))))
+                })
+            })),
+        );
+
+        let resp = send_with(
+            move |req| {
+                let ctx = Context::builder(Arc::new(()))
+                    .param("id", "injected")
+                    .extension(User("alice"))
+                    .build();
+
+                Router::route_with_context(Arc::clone(&router), req, ctx)
+            },
+            Request::get("/me/42").body(Default::default()).unwrap(),
+        )
+        .await;
+
+        assert_eq!(resp.body(), "alice injected");
+    }
+
+    #[tokio::test]
+    async fn finalized_router_routes() {
+        use crate::testing::send_with;
+
+        let router = Router::<()>::default()
+            .register(Route::new(path!("/[slug]")).get(|_, ctx| {
+                Box::pin(async move { Ok(Response::new(full(ctx.params[0].clone()))) })
+            }))
+            .finalize();
+
+        let handle = router.clone();
+        let resp = send_with(
+            move |req| handle.route(req),
+            Request::get("/hello").body(Default::default()).unwrap(),
+        )
+        .await;
+        assert_eq!(resp.body(), "hello");
+
+        let resp = send_with(
+            move |req| router.route(req),
+            Request::get("/a/b").body(Default::default()).unwrap(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn header_guard_not_found() {
+        use crate::testing::send;
+        use hyper::header::{HeaderName, HeaderValue};
+
+        let router = Router::<()>::default().register(
+            Route::new(path!("/"))
+                .require_header(
+                    HeaderName::from_static("x-env"),
+                    HeaderValue::from_static("prod"),
+                )
+                .get(|_, _| Box::pin(async { Ok(Response::new(full("OK"))) })),
+        );
+        let router = Arc::new(router);
+
+        let req = Request::get("/")
+            .header("X-Env", "prod")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(Arc::clone(&router), req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = Request::get("/")
+            .header("X-Env", "Prod")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn scoped_panic_handler() {
+        use crate::testing::send;
+
+        let mut router = Router::<()>::default()
+            .register(Route::new(path!("/api/x")).get(|_, _| panic!("api")))
+            .register(Route::new(path!("/x")).get(|_, _| panic!("site")));
+        // Panic handlers may capture configuration, such as an error body.
+        let error = Bytes::from_static(br#"{"error":"internal"}"#);
+        router.on_panic_under(path!("/api"), move |_, _| {
+            let error = error.clone();
+            Box::pin(async move {
+                Ok(Response::builder()
+                    .status(StatusCode::INTERNAL_SERVER_ERROR)
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(full(error))
+                    .unwrap())
+            })
+        });
+        let router = Arc::new(router);
+
+        let req = Request::get("/api/x").body(Default::default()).unwrap();
+        let resp = send(Arc::clone(&router), req).await;
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(resp.headers()[header::CONTENT_TYPE], "application/json");
+        assert_eq!(resp.body(), r#"{"error":"internal"}"#);
+
+        let req = Request::get("/x").body(Default::default()).unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(resp.body(), "Internal Server Error");
+    }
+
+    #[tokio::test]
+    async fn force_close_after_closes_connection() {
+        use hyper::client::conn::http1 as client;
+        use hyper::server::conn::http1 as server;
+        use hyper::service::service_fn;
+        use hyper_util::rt::TokioIo;
+
+        let mut router = Router::<()>::default();
+        let closing = StatusCode::NOT_FOUND;
+        router.force_close_after(move |resp| resp.status() == closing);
+        let router = Arc::new(router);
+
+        let (client_io, server_io) = tokio::io::duplex(1024);
+        let conn = tokio::spawn(async move {
+            let svc = service_fn(move |req| Router::route(Arc::clone(&router), req));
+            server::Builder::new()
+                .serve_connection(TokioIo::new(server_io), svc)
+                .await
+        });
+
+        let (mut sender, client_conn) =
+            client::handshake::<_, http_body_util::Empty<Bytes>>(TokioIo::new(client_io))
+                .await
+                .unwrap();
+        tokio::spawn(client_conn);
+
+        let req = Request::get("/missing").body(Default::default()).unwrap();
+        let resp = sender.send_request(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(resp.headers()[header::CONNECTION], "close");
+        drop(resp);
+
+        let served = tokio::time::timeout(std::time::Duration::from_secs(1), conn).await;
+        assert!(served.unwrap().unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn timeout_responds_unavailable() {
+        use crate::testing::send;
+
+        let mut router = Router::<()>::default()
+            .register(Route::new(path!("/slow")).get(|_, _| {
+                Box::pin(async {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    Ok(Response::new(full("late")))
+                })
+            }))
+            .register(
+                Route::new(path!("/fast"))
+                    .get(|_, _| Box::pin(async { Ok(Response::new(full("OK"))) })),
+            );
+        router.timeout(Duration::from_millis(50));
+        let router = Arc::new(router);
+
+        let req = Request::get("/slow").body(Default::default()).unwrap();
+        let resp = send(Arc::clone(&router), req).await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(resp.headers()[header::RETRY_AFTER], "1");
+
+        let req = Request::get("/fast").body(Default::default()).unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn timeout_status() {
+        use crate::testing::send;
+
+        let mut router =
+            Router::<()>::default().register(Route::new(path!("/upload")).post(|req, _| {
+                Box::pin(async move {
+                    // The deadline also covers reading the body.
+                    let body = req.into_body().collect().await?.to_bytes();
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    Ok(Response::new(full(body)))
+                })
+            }));
+        router
+            .timeout(Duration::from_millis(50))
+            .timeout_status(StatusCode::GATEWAY_TIMEOUT);
+
+        let req = Request::post("/upload").body("data".into()).unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.status(), StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(resp.body().as_ref(), b"Gateway Timeout");
+        assert!(!resp.headers().contains_key(header::RETRY_AFTER));
+    }
+
+    #[tokio::test]
+    async fn timeout_retry_after_by_method() {
+        use crate::testing::send;
+
+        let slow = |_, _| -> std::pin::Pin<Box<crate::ResponseFut>> {
+            Box::pin(async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                Ok(Response::new(full("late")))
+            })
+        };
+        let mut router = Router::<()>::default()
+            .register(Route::new(path!("/slow")).get(slow).auto_head().any(slow));
+        router.timeout(Duration::from_millis(50));
+        let router = Arc::new(router);
+
+        for (method, retry) in [
+            (Method::GET, true),
+            // Answered by the GET handler.
+            (Method::HEAD, true),
+            (Method::OPTIONS, true),
+            (Method::TRACE, true),
+            (Method::PUT, true),
+            (Method::DELETE, true),
+            (Method::POST, false),
+            (Method::PATCH, false),
+            (Method::from_bytes(b"PURGE").unwrap(), false),
+        ] {
+            let req = Request::builder()
+                .method(method.clone())
+                .uri("/slow")
+                .body(Default::default())
+                .unwrap();
+            let resp = send(Arc::clone(&router), req).await;
+            assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE, "{method}");
+            assert_eq!(
+                resp.headers()
+                    .get(header::RETRY_AFTER)
+                    .map(|value| value.to_str().unwrap()),
+                retry.then_some("1"),
+                "{method}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn deadline_propagates_to_context() {
+        use crate::testing::send;
+
+        let mut router = Router::<()>::default().register(Route::new(path!("/")).get(|_, ctx| {
+            Box::pin(async move {
+                let before = ctx.remaining().unwrap();
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                let after = ctx.remaining().unwrap();
+
+                assert!(ctx.deadline.unwrap() > Instant::now());
+                assert!(before <= Duration::from_secs(10));
+                assert!(before - after >= Duration::from_millis(20));
+                Ok(Response::new(full("OK")))
+            })
+        }));
+        router.timeout(Duration::from_secs(10));
+
+        let req = Request::get("/").body(Default::default()).unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn no_deadline_without_timeout() {
+        use crate::testing::send;
+
+        let router = Router::<()>::default().register(Route::new(path!("/")).get(|_, ctx| {
+            Box::pin(async move {
+                assert!(ctx.deadline.is_none());
+                Ok(Response::new(full("OK")))
+            })
+        }));
+
+        let req = Request::get("/").body(Default::default()).unwrap();
+        assert_eq!(send(router, req).await.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn route_path_adds_handlers() {
+        use crate::testing::send;
+
+        let mut router = Router::<()>::default();
+        router
+            .route_path(path!("/users"))
+            .get(|_, _| Box::pin(async { Ok(Response::new(full("list"))) }));
+        router
+            .route_path(path!("/users"))
+            .post(|_, _| Box::pin(async { Ok(Response::new(full("create"))) }));
+        let router = Arc::new(router);
+
+        let req = Request::get("/users").body(Default::default()).unwrap();
+        let resp = send(Arc::clone(&router), req).await;
+        assert_eq!(resp.body().as_ref(), b"list");
+
+        let req = Request::post("/users").body(Default::default()).unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.body().as_ref(), b"create");
+    }
+
+    #[tokio::test]
+    async fn default_error_pages() {
+        use crate::DefaultErrorPages;
+        use crate::testing::send;
+
+        let mut router = Router::<()>::default()
+            .register(Route::new(path!("/panic")).get(|_, _| panic!("boom")));
+        router.error_pages(DefaultErrorPages);
+        let router = Arc::new(router);
+
+        let req = Request::get("/missing/it's")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(Arc::clone(&router), req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            resp.headers()[header::CONTENT_TYPE],
+            "text/html; charset=utf-8"
+        );
+        let body = std::str::from_utf8(resp.body()).unwrap();
+        assert!(body.contains("404 Not Found"));
+        assert!(body.contains("/missing/it&#39;s"));
+
+        let req = Request::get("/panic").body(Default::default()).unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(std::str::from_utf8(resp.body()).unwrap().contains("/panic"));
+    }
+
+    #[tokio::test]
+    async fn panicking_handler_keeps_connection() {
+        use crate::testing::serve;
+        use http_body_util::BodyExt;
+        use hyper::client::conn::http1 as client;
+
+        let router = Arc::new(
+            Router::<()>::default()
+                .register(Route::new(path!("/panic")).get(|_, _| {
+                    Box::pin(async {
+                        tokio::task::yield_now().await;
+                        panic!("boom")
+                    })
+                }))
+                .register(
+                    Route::new(path!("/ok"))
+                        .get(|_, _| Box::pin(async { Ok(Response::new(full("OK"))) })),
+                ),
+        );
+        let io = serve(move |req| Router::route(Arc::clone(&router), req));
+        let (mut sender, conn) = client::handshake(TokioIo::new(io)).await.unwrap();
+        tokio::spawn(conn);
+
+        let req = Request::get("/panic")
+            .body(http_body_util::Empty::<Bytes>::new())
+            .unwrap();
+        let resp = sender.send_request(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        resp.into_body().collect().await.unwrap();
+
+        let req = Request::get("/ok")
+            .body(http_body_util::Empty::<Bytes>::new())
+            .unwrap();
+        let resp = sender.send_request(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn pipelined_responses_in_order() {
+        use crate::testing::serve;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let router = Arc::new(
+            Router::<()>::default()
+                .register(Route::new(path!("/slow")).get(|_, _| {
+                    Box::pin(async {
+                        tokio::time::sleep(Duration::from_millis(50)).await;
+                        Ok(Response::new(full("slow")))
+                    })
+                }))
+                .register(
+                    Route::new(path!("/fast"))
+                        .get(|_, _| Box::pin(async { Ok(Response::new(full("fast"))) })),
+                ),
+        );
+
+        let mut io = serve(move |req| Router::route(Arc::clone(&router), req));
+        io.write_all(
+            b"GET /slow HTTP/1.1\r\nHost: test\r\n\r\n\
+              GET /fast HTTP/1.1\r\nHost: test\r\nConnection: close\r\n\r\n",
+        )
+        .await
+        .unwrap();
+
+        let mut raw = String::new();
+        tokio::time::timeout(Duration::from_secs(1), io.read_to_string(&mut raw))
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(raw.matches("HTTP/1.1 200 OK").count(), 2);
+        let slow = raw.find("slow").unwrap();
+        let fast = raw.find("fast").unwrap();
+        assert!(slow < fast, "responses out of order: {raw:?}");
+    }
+
+    fn secure_router() -> Router<()> {
+        Router::<()>::default().register(Route::new(path!("/")).get(|_, ctx| {
+            Box::pin(async move {
+                let body = if ctx.is_secure() { "https" } else { "http" };
+                Ok(Response::new(full(body)))
+            })
+        }))
+    }
+
+    #[tokio::test]
+    async fn is_secure_over_tls() {
+        use crate::testing::send_with;
+
+        let router = Arc::new(secure_router());
+        let resp = send_with(
+            move |mut req| {
+                req.extensions_mut().insert(Secure);
+                Router::route(Arc::clone(&router), req)
+            },
+            Request::get("/").body(Default::default()).unwrap(),
+        )
+        .await;
+
+        assert_eq!(resp.body().as_ref(), b"https");
+    }
+
+    #[tokio::test]
+    async fn is_secure_forwarded_proto() {
+        use crate::testing::send;
+
+        let forwarded = || {
+            Request::get("/")
+                .header("X-Forwarded-Proto", "HTTPS, http")
+                .body(Default::default())
+                .unwrap()
+        };
+
+        let resp = send(secure_router(), forwarded()).await;
+        assert_eq!(resp.body().as_ref(), b"http");
+
+        let mut router = secure_router();
+        router.trust_forwarded_proto(true);
+        let resp = send(router, forwarded()).await;
+        assert_eq!(resp.body().as_ref(), b"https");
+    }
+
+    #[tokio::test]
+    async fn is_secure_plaintext() {
+        use crate::testing::send;
+
+        let mut router = secure_router();
+        router.trust_forwarded_proto(true);
+        let resp = send(router, Request::get("/").body(Default::default()).unwrap()).await;
+        assert_eq!(resp.body().as_ref(), b"http");
+    }
+
+    #[test]
+    fn exact_route_disables_fallback() {
+        let router = |settings: Route<()>| {
+            Router::<()>::default()
+                .register(settings)
+                .register(Route::new(path!("/users/[id]/profile")))
+                .register(Route::new(path!("/files/[...rest]")))
+                .register(Route::new(path!("/files/public/index")).exact())
+        };
+
+        let fallback = router(Route::new(path!("/users/admin/settings")));
+        let (route, params, _) = fallback.match_route("/users/admin/profile").unwrap();
+        assert_eq!(route.path, path!("/users/[id]/profile"));
+        assert_eq!(params, vec!["admin"]);
+        assert!(fallback.match_route("/users/admin/settings").is_ok());
+
+        let exact = router(Route::new(path!("/users/admin/settings")).exact());
+        assert!(exact.match_route("/users/admin/profile").is_err());
+        assert!(exact.match_route("/users/admin/settings").is_ok());
+        assert!(exact.match_route("/users/alice/profile").is_ok());
+
+        assert!(exact.match_route("/files/public/other").is_err());
+        assert!(exact.match_route("/files/private/other").is_ok());
+    }
+
+    #[tokio::test]
+    async fn register_merges_handlers() {
+        use crate::testing::send;
+
+        let router = Arc::new(
+            Router::<()>::default()
+                .register(
+                    Route::new(path!("/x"))
+                        .get(|_, _| Box::pin(async { Ok(Response::new(full("get"))) })),
+                )
+                .register(
+                    Route::new(path!("/x"))
+                        .post(|_, _| Box::pin(async { Ok(Response::new(full("post"))) })),
+                ),
+        );
+
+        let req = Request::get("/x").body(Default::default()).unwrap();
+        let resp = send(Arc::clone(&router), req).await;
+        assert_eq!(resp.body().as_ref(), b"get");
+
+        let req = Request::post("/x").body(Default::default()).unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.body().as_ref(), b"post");
+    }
+
+    #[tokio::test]
+    async fn named_params() {
+        use crate::testing::send;
+
+        let router = Router::<()>::default().register(
+            Route::new(path!("/users/[id]/posts/[post]")).get(|_, ctx| {
+                Box::pin(async move {
+                    let body = format!(
+                        "{} {} {:?}",
+                        ctx.param("id").unwrap(),
+                        ctx.param("post").unwrap(),
+                        ctx.param("missing")
+                    );
+                    Ok(Response::new(full(body)))
+                })
+            }),
+        );
+
+        let req = Request::get("/users/42/posts/hello")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.body().as_ref(), b"42 hello None");
+    }
+
+    #[tokio::test]
+    async fn decoded_params() {
+        use crate::testing::send;
+
+        let router = Arc::new(Router::<()>::default().register(
+            Route::new(path!("/users/[name]")).get(|_, ctx| {
+                Box::pin(async move {
+                    let body = format!("{} {}", ctx.params[0], ctx.param("name").unwrap());
+                    Ok(Response::new(full(body)))
+                })
+            }),
+        ));
+
+        for (path, expected) in [
+            ("/users/john%20doe", "john doe"),
+            ("/users/a%2Fb", "a/b"),
+            ("/users/100%zz", "100%zz"),
+        ] {
+            let req = Request::get(path).body(Default::default()).unwrap();
+            let resp = send(Arc::clone(&router), req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert_eq!(
+                resp.body().as_ref(),
+                format!("{expected} {expected}").as_bytes()
+            );
+        }
+
+        let req = Request::get("/users/%ff").body(Default::default()).unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    /// Records the fields of the crate's `tracing` events up to a level.
+    #[cfg(feature = "logging")]
+    #[derive(Clone)]
+    struct Capture(
+        tracing::Level,
+        Arc<std::sync::Mutex<Vec<HashMap<String, String>>>>,
+    );
+
+    #[cfg(feature = "logging")]
+    impl Capture {
+        fn new(level: tracing::Level) -> Self {
+            Self(level, Default::default())
+        }
+    }
+
+    #[cfg(feature = "logging")]
+    impl tracing::Subscriber for Capture {
+        fn enabled(&self, metadata: &tracing::Metadata<'_>) -> bool {
+            *metadata.level() <= self.0
+        }
+
+        fn new_span(&self, _: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            tracing::span::Id::from_u64(1)
+        }
+
+        fn record(&self, _: &tracing::span::Id, _: &tracing::span::Record<'_>) {}
+
+        fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+
+        fn event(&self, event: &tracing::Event<'_>) {
+            use tracing::field::{Field, Visit};
+
+            struct Fields(HashMap<String, String>);
+
+            impl Visit for Fields {
+                fn record_str(&mut self, field: &Field, value: &str) {
+                    self.0.insert(field.name().into(), value.into());
+                }
+
+                fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+                    self.0.insert(field.name().into(), format!("{value:?}"));
+                }
+            }
+
+            if event.metadata().target().starts_with("radmin_router") {
+                let mut fields = Fields(HashMap::new());
+                event.record(&mut fields);
+                self.1.lock().unwrap().push(fields.0);
+            }
+        }
+
+        fn enter(&self, _: &tracing::span::Id) {}
+
+        fn exit(&self, _: &tracing::span::Id) {}
+    }
+
+    #[cfg(feature = "logging")]
+    #[tokio::test]
+    async fn logs_each_request() {
+        use crate::testing::send;
+
+        let capture = Capture::new(tracing::Level::DEBUG);
+        let _guard = tracing::subscriber::set_default(capture.clone());
+
+        let mut router = Router::<()>::default().register(
+            Route::new(path!("/")).get(|_, _| Box::pin(async { Ok(Response::new(full("ok"))) })),
+        );
+        router.layer(crate::request_id());
+        let router = Arc::new(router);
+        send(
+            Arc::clone(&router),
+            Request::get("/").body(Default::default()).unwrap(),
+        )
+        .await;
+        send(
+            Arc::clone(&router),
+            Request::get("/missing").body(Default::default()).unwrap(),
+        )
+        .await;
+        send(
+            router,
+            Request::get("/")
+                .header("x-request-id", "abc-123")
+                .body(Default::default())
+                .unwrap(),
+        )
+        .await;
+
+        let mut formatted = Router::<()>::default().register(
+            Route::new(path!("/users/[id]"))
+                .get(|_, _| Box::pin(async { Ok(Response::new(full("ok"))) })),
+        );
+        formatted
+            .layer(crate::request_id())
+            .access_log_format("{method} {route} {status} {request_id}");
+        send(
+            formatted,
+            Request::get("/users/7")
+                .header("x-request-id", "req-7")
+                .body(Default::default())
+                .unwrap(),
+        )
+        .await;
+
+        let events = capture.1.lock().unwrap();
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[0]["method"], "GET");
+        assert_eq!(events[0]["path"], "/");
+        assert_eq!(events[0]["status"], "200");
+        assert!(events[0].contains_key("elapsed"));
+        assert_eq!(events[1]["path"], "/missing");
+        assert_eq!(events[1]["status"], "404");
+        assert_eq!(events[0]["request_id"].len(), 36);
+        assert!(!events[1].contains_key("request_id"));
+        assert_eq!(events[2]["request_id"], "abc-123");
+        assert_eq!(events[3]["message"], "GET /users/[id] 200 req-7");
+        assert!(!events[3].contains_key("status"));
+    }
+
+    #[cfg(feature = "logging")]
+    #[test]
+    fn traces_matching() {
+        let capture = Capture::new(tracing::Level::TRACE);
+        let _guard = tracing::subscriber::set_default(capture.clone());
+
+        let router = Router::<()>::default()
+            .register(Route::new(path!("/a/b/d")))
+            .register(Route::new(path!("/a/[x]/c")));
+        router.match_route("/a/b/c").unwrap();
+        router.match_route("/a/z").unwrap_err();
+
+        let events = capture.1.lock().unwrap();
+        let descent: Vec<_> = events
+            .iter()
+            .map(|event| match event["message"].as_str() {
+                "trying branch" => format!("{} {}", event["segment"], event["branch"]),
+                "matched route" => format!("{} {}", event["route"], event["specificity"]),
+                message => format!("{} {}", message, event["path"]),
+            })
+            .collect();
+        assert_eq!(
+            descent,
+            [
+                "a literal",
+                "b literal",
+                // `/a/b` has no route for `c`, so matching falls back.
+                "b dynamic",
+                "c literal",
+                "/a/[x]/c [0, 3, 0]",
+                "a literal",
+                "z dynamic",
+                "no route matched /a/z",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn route_cache_control() {
+        use crate::testing::send;
+
+        let router = Arc::new(
+            Router::<()>::default()
+                .register(
+                    Route::new(path!("/default"))
+                        .get(|_, _| Box::pin(async { Ok(Response::new(full("OK"))) }))
+                        .cache_control(HeaderValue::from_static("max-age=60")),
+                )
+                .register(
+                    Route::new(path!("/custom"))
+                        .get(|_, _| {
+                            Box::pin(async {
+                                Ok(Response::builder()
+                                    .header(header::CACHE_CONTROL, "no-store")
+                                    .body(full("OK"))
+                                    .unwrap())
+                            })
+                        })
+                        .cache_control(HeaderValue::from_static("max-age=60")),
+                ),
+        );
+
+        let req = Request::get("/default").body(Default::default()).unwrap();
+        let resp = send(Arc::clone(&router), req).await;
+        assert_eq!(resp.headers()[header::CACHE_CONTROL], "max-age=60");
+
+        let req = Request::get("/custom").body(Default::default()).unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.headers()[header::CACHE_CONTROL], "no-store");
+    }
+
+    #[test]
+    fn literal_precedence() {
+        let router = Router::<()>::default()
+            .register(Route::new(path!("/users/[id]")))
+            .register(Route::new(path!("/users/me")))
+            .register(Route::new(path!("/users/[id]/posts")))
+            .register(Route::new(path!("/users/me/settings/privacy")))
+            .register(Route::new(path!("/files/[id]/meta")))
+            .register(Route::new(path!("/files/[...rest]")));
+
+        for _ in 0..16 {
+            let (route, params, _) = router.match_route("/users/me").unwrap();
+            assert_eq!(route.path, path!("/users/me"));
+            assert!(matches!(route.path.0[1], Segment::Literal(_)));
+            assert!(params.is_empty());
+        }
+
+        // The literal branch dead-ends, so matching falls back to `[id]`.
+        let (route, params, _) = router.match_route("/users/me/posts").unwrap();
+        assert!(matches!(route.path.0[1], Segment::Dynamic(_)));
+        assert_eq!(params, vec!["me"]);
+
+        // `/users/me/settings` has no route of its own.
+        assert!(router.match_route("/users/me/settings").is_err());
+
+        let (route, params, _) = router.match_route("/files/1/meta").unwrap();
+        assert_eq!(route.path, path!("/files/[id]/meta"));
+        assert_eq!(params, vec!["1"]);
+
+        let (route, params, _) = router.match_route("/files/1/other").unwrap();
+        assert_eq!(route.path, path!("/files/[...rest]"));
+        assert_eq!(params, vec!["1/other"]);
+    }
+
+    #[test]
+    fn constrained_segments() {
+        let router = Router::<()>::default()
+            .register(Route::new(path!("/users/[id:int]")))
+            .register(Route::new(path!("/users/[name]")))
+            .register(Route::new(path!("/tags/[tag:alpha]")));
+
+        let (route, params, _) = router.match_route("/users/123").unwrap();
+        assert_eq!(route.path, path!("/users/[id:int]"));
+        assert_eq!(params, vec!["123"]);
+
+        let (route, params, _) = router.match_route("/users/abc").unwrap();
+        assert_eq!(route.path, path!("/users/[name]"));
+        assert_eq!(params, vec!["abc"]);
+
+        assert!(router.match_route("/tags/abc").is_ok());
+        assert!(router.match_route("/tags/123").is_err());
+    }
+
+    #[tokio::test]
+    async fn pattern_segments() {
+        use crate::testing::send;
+
+        let router = Router::<()>::default()
+            .register(
+                Route::new(path!("/reports/report-[year].csv")).get(|_, ctx| {
+                    Box::pin(async move {
+                        Ok(Response::new(full(ctx.param("year").unwrap().to_string())))
+                    })
+                }),
+            )
+            .register(Route::new(path!("/reports/report-[year]-[month].csv")))
+            .register(Route::new(path!("/reports/[name]")));
+
+        let (route, params, _) = router.match_route("/reports/report-2024.csv").unwrap();
+        assert_eq!(route.path, path!("/reports/report-[year].csv"));
+        assert_eq!(params, vec!["2024"]);
+
+        let (route, params, _) = router.match_route("/reports/report-2024-06.csv").unwrap();
+        assert_eq!(route.path, path!("/reports/report-[year]-[month].csv"));
+        assert_eq!(params, vec!["2024", "06"]);
+
+        let (route, params, _) = router.match_route("/reports/summary.csv").unwrap();
+        assert_eq!(route.path, path!("/reports/[name]"));
+        assert_eq!(params, vec!["summary.csv"]);
+
+        let req = Request::get("/reports/report-2024.csv")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.body().as_ref(), b"2024");
+    }
+
+    #[test]
+    fn escaped_bracket_literal() {
+        let router = Router::<()>::default()
+            .register(Route::new(path!(r"/files/\[draft\]")))
+            .register(Route::new(path!("/files/[id]")));
+
+        let (route, params, _) = router.match_route("/files/%5Bdraft%5D").unwrap();
+        assert_eq!(route.path, path!("/files/%5Bdraft%5D"));
+        assert!(params.is_empty());
+
+        let (route, params, _) = router.match_route("/files/draft").unwrap();
+        assert_eq!(route.path, path!("/files/[id]"));
+        assert_eq!(params, vec!["draft"]);
+    }
+
+    #[tokio::test]
+    async fn percent_encoded_literals() {
+        use crate::testing::send;
+
+        let handler = |body: &'static str| {
+            move |_, _| -> std::pin::Pin<Box<crate::ResponseFut>> {
+                Box::pin(async move { Ok(Response::new(full(body))) })
+            }
+        };
+        let router = Arc::new(
+            Router::<()>::default()
+                .register(Route::new(path!("/hello world")).get(handler("space")))
+                .register(Route::new(path!("/café")).get(handler("utf-8")))
+                .register(Route::new(path!("/caf%C3%A9/menu%2Bdrinks")).get(handler("encoded")))
+                .register(Route::new(path!("/[name]")).get(handler("dynamic")))
+                .register(Route::new(path!("/café-[id]")).get(|_, ctx| {
+                    Box::pin(async move { Ok(Response::new(full(ctx.params[0].clone()))) })
+                }))
+                .register(Route::new(path!("/hello world-[id].txt")).get(handler("space pattern")))
+                .register(Route::new(path!("/x/caf%C3%A9-[id]")).get(handler("encoded pattern"))),
+        );
+
+        for (path, body) in [
+            ("/hello%20world", "space"),
+            ("/caf%C3%A9", "utf-8"),
+            ("/caf%c3%a9", "utf-8"),
+            ("/café/menu+drinks", "encoded"),
+            ("/caf%C3%A9/menu%2bdrinks", "encoded"),
+            ("/hello", "dynamic"),
+            ("/caf%C3%A9-7", "7"),
+            ("/caf%c3%a9-%37", "7"),
+            ("/hello%20world-7.txt", "space pattern"),
+            ("/x/caf%C3%A9-7", "encoded pattern"),
+            ("/x/caf%c3%a9-7", "encoded pattern"),
+        ] {
+            let req = Request::get(path).body(Default::default()).unwrap();
+            let resp = send(Arc::clone(&router), req).await;
+            assert_eq!(resp.body(), body, "{path}");
+        }
+    }
+
+    #[tokio::test]
+    async fn strict_query() {
+        use crate::testing::send;
+
+        let router = || {
+            Router::<()>::default().register(
+                Route::new(path!("/"))
+                    .get(|_, _| Box::pin(async { Ok(Response::new(full("OK"))) })),
+            )
+        };
+        let req = || Request::get("/?name=%ff").body(Default::default()).unwrap();
+
+        let resp = send(router(), req()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let mut strict = router();
+        strict
+            .strict_query(true)
+            .error_pages(crate::DefaultErrorPages);
+        let strict = Arc::new(strict);
+        let resp = send(Arc::clone(&strict), req()).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            resp.headers()[header::CONTENT_TYPE],
+            "text/html; charset=utf-8"
+        );
+
+        let valid = Request::get("/?name=caf%C3%A9")
+            .body(Default::default())
+            .unwrap();
+        assert_eq!(send(strict, valid).await.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn strict_params() {
+        use crate::testing::send;
+
+        let mut router =
+            Router::<()>::default().register(Route::new(path!("/files/[name]")).get(|_, ctx| {
+                Box::pin(async move { Ok(Response::new(full(ctx.params[0].clone()))) })
+            }));
+        let req = |path: &str| Request::get(path).body(Default::default()).unwrap();
+
+        let resp = send(router.clone(), req("/files/a%00b")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.body().as_ref(), b"a\0b");
+
+        router.strict_params(true);
+        let router = Arc::new(router);
+        let resp = send(Arc::clone(&router), req("/files/a%00b")).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let resp = send(Arc::clone(&router), req("/files/a%1Bb")).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let resp = send(router, req("/files/caf%C3%A9%20menu")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.body(), "café menu");
+    }
+
+    #[tokio::test]
+    async fn mount() {
+        use crate::testing::send;
+
+        let api =
+            Router::<()>::default().register(Route::new(path!("/users/[id]")).get(|_, ctx| {
+                Box::pin(async move { Ok(Response::new(full(ctx.params.join(" ")))) })
+            }));
+
+        let mut router = Router::<()>::default();
+        router.mount(path!("/api"), api.clone());
+        router.mount(path!("/orgs/[org]"), api);
+        let router = Arc::new(router);
+
+        let (route, params, _) = router.match_route("/api/users/42").unwrap();
+        assert_eq!(route.path, path!("/api/users/[id]"));
+        assert_eq!(params, vec!["42"]);
+
+        let req = Request::get("/orgs/acme/users/42")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.body().as_ref(), b"acme 42");
+    }
+
+    #[tokio::test]
+    async fn merge_at_scopes_handlers() {
+        use crate::testing::send;
+
+        let mut api =
+            Router::<()>::default().register(Route::new(path!("/api/users/[id]")).get(|_, ctx| {
+                Box::pin(async move { Ok(Response::new(full(ctx.params[0].clone()))) })
+            }));
+        api.route_not_found(|_, _| {
+            Box::pin(async {
+                Ok(Response::builder()
+                    .status(StatusCode::NOT_FOUND)
+                    .body(full(r#"{"error":"not found"}"#))
+                    .unwrap())
+            })
+        });
+        api.layer(|req, ctx, next| {
+            Box::pin(async move {
+                let mut resp = next.run(req, ctx).await?;
+                resp.headers_mut()
+                    .insert("x-api", HeaderValue::from_static("v2"));
+                Ok(resp)
+            })
+        });
+
+        let mut router = Router::<()>::default().register(
+            Route::new(path!("/")).get(|_, _| Box::pin(async { Ok(Response::new(full("home"))) })),
+        );
+        router.merge_at(path!("/v2"), api);
+        let router = Arc::new(router);
+
+        let req = Request::get("/v2/api/users/42")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(Arc::clone(&router), req).await;
+        assert_eq!(resp.body().as_ref(), b"42");
+        assert_eq!(resp.headers()["x-api"], "v2");
+
+        let req = Request::get("/v2/api/missing")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(Arc::clone(&router), req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(resp.body().as_ref(), br#"{"error":"not found"}"#);
+
+        let req = Request::get("/missing").body(Default::default()).unwrap();
+        let resp = send(Arc::clone(&router), req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(resp.body().as_ref(), b"Not Found");
+
+        let req = Request::get("/").body(Default::default()).unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.body().as_ref(), b"home");
+        assert!(!resp.headers().contains_key("x-api"));
+    }
+
+    #[tokio::test]
+    async fn closure_handlers_capture_state() {
+        use crate::testing::send;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let hits = Arc::new(AtomicUsize::new(0));
+        let greeting = String::from("hello");
+
+        let counter = Arc::clone(&hits);
+        let mut router =
+            Router::<()>::default().register(Route::new(path!("/")).get(move |_, _| {
+                let count = counter.fetch_add(1, Ordering::SeqCst) + 1;
+                let body = format!("{greeting} #{count}");
+                Box::pin(async move { Ok(Response::new(full(body))) })
+            }));
+        let missing = Arc::clone(&hits);
+        router.route_not_found(move |_, _| {
+            missing.fetch_add(100, Ordering::SeqCst);
+            Box::pin(async {
+                Ok(Response::builder()
+                    .status(StatusCode::NOT_FOUND)
+                    .body(full("gone"))
+                    .unwrap())
+            })
+        });
+        let router = Arc::new(router);
+
+        let req = Request::get("/").body(Default::default()).unwrap();
+        let resp = send(Arc::clone(&router), req).await;
+        assert_eq!(resp.body().as_ref(), b"hello #1");
+
+        let req = Request::get("/nope").body(Default::default()).unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.body().as_ref(), b"gone");
+        assert_eq!(hits.load(Ordering::SeqCst), 101);
+    }
+
+    #[tokio::test]
+    async fn query_in_context() {
+        use crate::testing::send;
+
+        let router = Arc::new(Router::<()>::default().register(Route::new(path!("/")).get(
+            |_, ctx| {
+                let body = format!(
+                    "{:?} {:?} {:?} {:?} {}",
+                    ctx.query("a"),
+                    ctx.query("b"),
+                    ctx.query("flag"),
+                    ctx.query("missing"),
+                    ctx.query.len()
+                );
+                Box::pin(async move { Ok(Response::new(full(body))) })
+            },
+        )));
+
+        let req = Request::get("/?a=1&b=two+words&flag&a=2")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(Arc::clone(&router), req).await;
+        assert_eq!(
+            resp.body().as_ref(),
+            br#"Some("1") Some("two words") Some("") None 4"#
+        );
+
+        let req = Request::get("/").body(Default::default()).unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.body().as_ref(), b"None None None None 0");
+    }
+
+    #[tokio::test]
+    async fn blocking_handler_does_not_block_runtime() {
+        use crate::testing::send;
+        use hyper::Method;
+
+        let router = Arc::new(
+            Router::<()>::default()
+                .register(Route::new(path!("/slow")).blocking(Method::GET, |_, _| {
+                    std::thread::sleep(Duration::from_millis(300));
+                    Ok(Response::new(full("slow")))
+                }))
+                .register(
+                    Route::new(path!("/fast"))
+                        .get(|_, _| Box::pin(async { Ok(Response::new(full("fast"))) })),
+                )
+                .register(Route::new(path!("/panic")).blocking(Method::GET, |_, _| panic!("boom"))),
+        );
+
+        let slow = tokio::spawn(send(
+            Arc::clone(&router),
+            Request::get("/slow").body(Default::default()).unwrap(),
+        ));
+
+        let req = Request::get("/fast").body(Default::default()).unwrap();
+        let fast = tokio::time::timeout(Duration::from_millis(200), send(Arc::clone(&router), req))
+            .await
+            .expect("fast request blocked by blocking handler");
+        assert_eq!(fast.body().as_ref(), b"fast");
+        assert!(!slow.is_finished());
+        assert_eq!(slow.await.unwrap().body().as_ref(), b"slow");
+
+        let req = Request::get("/panic").body(Default::default()).unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[tokio::test]
+    async fn serve_until_shutdown() {
+        use futures::channel::oneshot;
+        use hyper::client::conn::http1 as client;
+        use tokio::net::TcpStream;
+
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let router = Arc::new(Router::<()>::default().register(
+            Route::new(path!("/")).get(|_, _| Box::pin(async { Ok(Response::new(full("OK"))) })),
+        ));
+        let (stop, stopped) = oneshot::channel::<()>();
+        let server = tokio::spawn(router.serve_with_shutdown(addr, stopped.map(drop)));
+
+        // The listener is bound once the server task runs.
+        let stream = loop {
+            match TcpStream::connect(addr).await {
+                Ok(stream) => break stream,
+                Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        };
+        let (mut sender, conn) = client::handshake(TokioIo::new(stream)).await.unwrap();
+        let conn = tokio::spawn(conn);
+
+        let req = Request::get("/").body(Full::<Bytes>::default()).unwrap();
+        let resp = sender.send_request(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body.as_ref(), b"OK");
+
+        stop.send(()).unwrap();
+        server.await.unwrap().unwrap();
+
+        // The idle keep-alive connection is closed, as is the listener.
+        conn.await.unwrap().unwrap();
+        assert!(TcpStream::connect(addr).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn fallback_routers() {
+        use crate::testing::send;
+
+        let handler = |body: &'static str| {
+            move |_, _| -> std::pin::Pin<Box<crate::ResponseFut>> {
+                Box::pin(async move { Ok(Response::new(full(body))) })
             }
-            candidates = new_candidates;
+        };
+        let legacy = Router::<()>::default()
+            .register(Route::new(path!("/users")).get(handler("legacy users")))
+            .register(Route::new(path!("/old/[...path]")).get(handler("legacy")));
+        let mut assets = Router::<()>::default()
+            .register(Route::new(path!("/assets/[...path]")).get(handler("asset")));
+        assets.route_not_found(|_, _| {
+            Box::pin(async {
+                let mut resp = Response::new(full("nothing here"));
+                *resp.status_mut() = StatusCode::NOT_FOUND;
+                Ok(resp)
+            })
+        });
+        let mut router =
+            Router::<()>::default().register(Route::new(path!("/users")).get(handler("users")));
+        router.or_else(legacy).or_else(assets);
+        let router = Arc::new(router);
+
+        for (method, path, status, body) in [
+            (Method::GET, "/users", StatusCode::OK, "users"),
+            (Method::GET, "/old/page", StatusCode::OK, "legacy"),
+            (Method::GET, "/assets/app.js", StatusCode::OK, "asset"),
+            (
+                Method::GET,
+                "/missing",
+                StatusCode::NOT_FOUND,
+                "nothing here",
+            ),
+        ] {
+            let req = Request::builder()
+                .method(method)
+                .uri(path)
+                .body(Default::default())
+                .unwrap();
+            let resp = send(Arc::clone(&router), req).await;
+            assert_eq!(resp.status(), status, "{path}");
+            assert_eq!(resp.body(), body, "{path}");
         }
 
-        if candidates.len() > 1 {
-            eprintln!("Matched multiple routes! {:?}", candidates);
+        // A route without a handler for the method isn't a miss.
+        let req = Request::post("/users").body(Default::default()).unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(resp.headers()[header::ALLOW], "GET");
+
+        // Subtrees claimed with `fallback_under` don't fall through.
+        let legacy = Router::<()>::default()
+            .register(Route::new(path!("/api/old")).get(handler("legacy api")))
+            .register(Route::new(path!("/old")).get(handler("legacy")));
+        let mut router =
+            Router::<()>::default().register(Route::new(path!("/api/users")).get(handler("users")));
+        router
+            .fallback_under(path!("/api"), |_, _| {
+                Box::pin(async {
+                    let mut resp = Response::new(full("no such endpoint"));
+                    *resp.status_mut() = StatusCode::NOT_FOUND;
+                    Ok(resp)
+                })
+            })
+            .or_else(legacy);
+        let router = Arc::new(router);
+
+        let get = |path| Request::get(path).body(Default::default()).unwrap();
+        let resp = send(Arc::clone(&router), get("/api/old")).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(resp.body(), "no such endpoint");
+        let resp = send(router, get("/old")).await;
+        assert_eq!(resp.body(), "legacy");
+    }
+
+    #[tokio::test]
+    async fn request_complete_hook() {
+        use crate::testing::send;
+
+        let mut router = Router::<()>::default()
+            .register(
+                Route::new(path!("/users/[id]"))
+                    .get(|_, _| Box::pin(async { Ok(Response::new(full("user"))) })),
+            )
+            .register(Route::new(path!("/panic")).get(|_, _| panic!("handler panicked")))
+            .register(Route::new(path!("/slow")).get(|_, _| {
+                Box::pin(async {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    Ok(Response::new(full("late")))
+                })
+            }));
+        let metrics = Arc::new(std::sync::Mutex::new(vec![]));
+        router
+            .timeout(Duration::from_millis(50))
+            .on_request_complete({
+                let metrics = Arc::clone(&metrics);
+                move |request: &RequestMetrics<'_>| {
+                    metrics.lock().unwrap().push((
+                        request.method.clone(),
+                        request.route.map(ToString::to_string),
+                        request.status,
+                        request.elapsed,
+                    ));
+                }
+            });
+        let router = Arc::new(router);
+
+        for (method, path) in [
+            (Method::GET, "/users/7"),
+            (Method::GET, "/missing"),
+            (Method::DELETE, "/users/7"),
+            (Method::GET, "/panic"),
+            (Method::GET, "/slow"),
+        ] {
+            let req = Request::builder()
+                .method(method)
+                .uri(path)
+                .body(Default::default())
+                .unwrap();
+            send(Arc::clone(&router), req).await;
         }
 
-        let route = candidates.first()?.route.as_ref()?;
-        let params = route
-            .path
-            .0
+        let metrics = metrics.lock().unwrap();
+        let summary: Vec<_> = metrics
             .iter()
-            .enumerate()
-            .fold(vec![], |mut acc, (idx, seg)| {
-                if let Segment::Dynamic(_) = seg {
-                    acc.push(segments[idx].to_string());
-                }
+            .map(|(method, route, status, _)| (method.clone(), route.as_deref(), *status))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (Method::GET, Some("/users/[id]"), Some(StatusCode::OK)),
+                (Method::GET, None, Some(StatusCode::NOT_FOUND)),
+                (
+                    Method::DELETE,
+                    Some("/users/[id]"),
+                    Some(StatusCode::METHOD_NOT_ALLOWED)
+                ),
+                (
+                    Method::GET,
+                    Some("/panic"),
+                    Some(StatusCode::INTERNAL_SERVER_ERROR)
+                ),
+                (
+                    Method::GET,
+                    Some("/slow"),
+                    Some(StatusCode::SERVICE_UNAVAILABLE)
+                ),
+            ]
+        );
+        assert!(metrics[4].3 >= Duration::from_millis(50));
+    }
 
-                acc
-            });
+    #[tokio::test]
+    async fn connection_error_hook() {
+        use futures::channel::oneshot;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpStream;
+
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let errors = Arc::new(AtomicUsize::new(0));
+        let mut router = Router::<()>::default();
+        router.on_connection_error({
+            let errors = Arc::clone(&errors);
+            move |_| {
+                errors.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+        let (stop, stopped) = oneshot::channel::<()>();
+        let server = tokio::spawn(Arc::new(router).serve_with_shutdown(addr, stopped.map(drop)));
+
+        let mut stream = loop {
+            match TcpStream::connect(addr).await {
+                Ok(stream) => break stream,
+                Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        };
+        stream.write_all(b"NOT HTTP\r\n\r\n").await.unwrap();
 
-        Some((route.clone(), params))
+        // hyper answers with a 400 and closes the connection.
+        let mut resp = Vec::new();
+        stream.read_to_end(&mut resp).await.unwrap();
+        assert!(resp.starts_with(b"HTTP/1.1 400"));
+
+        // The hook runs on the connection's task, after the socket is closed.
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while errors.load(Ordering::SeqCst) == 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("connection error hook not called");
+
+        stop.send(()).unwrap();
+        server.await.unwrap().unwrap();
+        assert_eq!(errors.load(Ordering::SeqCst), 1);
     }
 
-    /// Processes an incoming request and generates a response for hyper.
-    pub async fn route(
-        self: Arc<Self>,
-        req: Request<Incoming>,
-    ) -> Result<Response<BoxBody<Bytes, hyper::Error>>, hyper::Error> {
-        #[cfg(feature = "logging")]
-        let before = Instant::now();
-        #[cfg(feature = "logging")]
-        let method = req.method().clone();
+    #[tokio::test]
+    async fn serve_passes_remote_addr() {
+        use futures::channel::oneshot;
+        use hyper::client::conn::http1;
+        use hyper_util::rt::TokioIo;
+        use tokio::net::TcpStream;
 
-        let path = req.uri().path().to_string();
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let router = Router::<()>::default().register(Route::new(path!("/")).get(|_, ctx| {
+            let body = format!("{:?}", ctx.remote_addr);
+            Box::pin(async move { Ok(Response::new(full(body))) })
+        }));
+        let (stop, stopped) = oneshot::channel::<()>();
+        let server = tokio::spawn(Arc::new(router).serve_with_shutdown(addr, stopped.map(drop)));
 
-        let Some((route, params)) = self.match_route(&path) else {
-            return (self.route_not_found)(req, Arc::clone(&self.ex)).await;
+        let stream = loop {
+            match TcpStream::connect(addr).await {
+                Ok(stream) => break stream,
+                Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
         };
+        let client_addr = stream.local_addr().unwrap();
+        let (mut sender, conn) = http1::handshake(TokioIo::new(stream)).await.unwrap();
+        tokio::spawn(conn);
+        let req = Request::get("/")
+            .header(header::HOST, "localhost")
+            .body(String::new())
+            .unwrap();
+        let resp = sender.send_request(req).await.unwrap();
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, format!("{:?}", Some(client_addr)));
+        drop(sender);
 
-        let ctx = Context {
-            params,
-            ex: Arc::clone(&self.ex),
+        stop.send(()).unwrap();
+        server.await.unwrap().unwrap();
+    }
+
+    #[cfg(feature = "http2")]
+    #[tokio::test]
+    async fn serves_http2() {
+        use futures::channel::oneshot;
+        use hyper::client::conn::{http1, http2};
+        use hyper_util::rt::{TokioExecutor, TokioIo};
+        use tokio::net::TcpStream;
+
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let mut router =
+            Router::<()>::default().register(Route::new(path!("/users/[id]")).get(|req, ctx| {
+                let body = format!("{:?} {}", req.version(), ctx.params[0]);
+                Box::pin(async move { Ok(Response::new(full(body))) })
+            }));
+        router.protocol(Protocol::Auto);
+        let (stop, stopped) = oneshot::channel::<()>();
+        let server = tokio::spawn(Arc::new(router).serve_with_shutdown(addr, stopped.map(drop)));
+
+        let connect = || async {
+            loop {
+                match TcpStream::connect(addr).await {
+                    Ok(stream) => break TokioIo::new(stream),
+                    Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+                }
+            }
         };
 
-        let Some(handler) = route.handler(req.method()) else {
-            return (self.method_not_allowed)(route, req, ctx).await;
+        let (mut sender, conn) = http2::handshake(TokioExecutor::new(), connect().await)
+            .await
+            .unwrap();
+        tokio::spawn(conn);
+        let req = Request::get(format!("http://{}/users/42", addr))
+            .body(String::new())
+            .unwrap();
+        let resp = sender.send_request(req).await.unwrap();
+        assert_eq!(resp.version(), hyper::Version::HTTP_2);
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body.as_ref(), b"HTTP/2.0 42");
+        drop(sender);
+
+        // HTTP/1 is still accepted on the same listener.
+        let (mut sender, conn) = http1::handshake(connect().await).await.unwrap();
+        tokio::spawn(conn);
+        let req = Request::get("/users/7")
+            .header(header::HOST, "localhost")
+            .body(String::new())
+            .unwrap();
+        let resp = sender.send_request(req).await.unwrap();
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body.as_ref(), b"HTTP/1.1 7");
+        drop(sender);
+
+        stop.send(()).unwrap();
+        server.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn auto_head() {
+        use crate::testing::send;
+
+        let get = |_, _| -> std::pin::Pin<Box<crate::ResponseFut>> {
+            Box::pin(async {
+                Ok(Response::builder()
+                    .status(StatusCode::ACCEPTED)
+                    .body(full("hello"))
+                    .unwrap())
+            })
         };
+        let router = Arc::new(
+            Router::<()>::default()
+                .register(Route::new(path!("/auto")).get(get).auto_head())
+                .register(Route::new(path!("/plain")).get(get))
+                .register(
+                    Route::new(path!("/explicit"))
+                        .get(get)
+                        .head(|_, _| Box::pin(async { Ok(Response::new(full(""))) }))
+                        .auto_head(),
+                ),
+        );
 
-        let resp = handler(req, ctx).await;
+        let (route, ..) = router.match_route("/auto").unwrap();
+        assert!(route.allowed_methods().contains(&Method::HEAD));
 
-        #[cfg(feature = "logging")]
-        {
-            use chrono::Utc;
+        let req = Request::head("/auto").body(Default::default()).unwrap();
+        let resp = send(Arc::clone(&router), req).await;
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        assert_eq!(resp.headers()[header::CONTENT_LENGTH], "5");
+        assert!(resp.body().is_empty());
 
-            let elapsed = before.elapsed();
-            match resp {
-                Ok(ref resp) => {
-                    let status_code = resp.status().as_u16();
-                    let status_color = match status_code {
-                        200..=299 => 92, // bright green
-                        300..=399 => 95, // bright magenta
-                        400..=499 => 93, // bright yellow
-                        500..=599 => 91, // bright red
-                        _ => 97,         // white
-                    };
-
-                    println!(
-                        "\x1B[34m[{}] \x1B[{status_color}m{}\x1B[97m {:6} {} \x1B[37m({:?})",
-                        Utc::now().format("%Y-%m-%d %H:%M:%S"),
-                        status_code,
-                        method,
-                        path,
-                        elapsed
-                    );
+        let req = Request::head("/plain").body(Default::default()).unwrap();
+        let resp = send(Arc::clone(&router), req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+        let req = Request::head("/explicit").body(Default::default()).unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn auto_head_content_length() {
+        use crate::testing::send;
+        use http_body_util::StreamBody;
+        use hyper::body::Frame;
+
+        let router = Arc::new(
+            Router::<()>::default()
+                .register(
+                    Route::new(path!("/fixed"))
+                        .get(|_, _| Box::pin(async { Ok(Response::new(full("hello, world"))) }))
+                        .auto_head(),
+                )
+                .register(
+                    Route::new(path!("/stream"))
+                        .get(|_, _| {
+                            Box::pin(async {
+                                let chunks =
+                                    futures::stream::iter(["hello", ", world"].map(|chunk| {
+                                        Ok::<_, crate::BodyError>(Frame::data(Bytes::from_static(
+                                            chunk.as_bytes(),
+                                        )))
+                                    }));
+                                Ok(Response::new(BodyExt::boxed(StreamBody::new(chunks))))
+                            })
+                        })
+                        .auto_head(),
+                ),
+        );
+
+        let get = send(
+            Arc::clone(&router),
+            Request::get("/fixed").body(Default::default()).unwrap(),
+        )
+        .await;
+        let head = send(
+            Arc::clone(&router),
+            Request::head("/fixed").body(Default::default()).unwrap(),
+        )
+        .await;
+        assert_eq!(
+            head.headers()[header::CONTENT_LENGTH],
+            get.body().len().to_string()
+        );
+        assert!(head.body().is_empty());
+
+        let get = send(
+            Arc::clone(&router),
+            Request::get("/stream").body(Default::default()).unwrap(),
+        )
+        .await;
+        assert_eq!(get.body().as_ref(), b"hello, world");
+        let head = send(
+            router,
+            Request::head("/stream").body(Default::default()).unwrap(),
+        )
+        .await;
+        assert_eq!(head.status(), StatusCode::OK);
+        assert!(!head.headers().contains_key(header::CONTENT_LENGTH));
+        assert!(head.body().is_empty());
+    }
+
+    #[tokio::test]
+    async fn shares_mutable_state() {
+        use crate::testing::send;
+        use std::sync::Mutex;
+
+        struct App {
+            counter: Mutex<i32>,
+        }
+
+        let router = Router::with_state(App {
+            counter: Mutex::new(0),
+        })
+        .register(Route::<App>::new(path!("/count")).post(|_, ctx| {
+            let count = {
+                let mut counter = ctx.ex.counter.lock().unwrap();
+                *counter += 1;
+                *counter
+            };
+            Box::pin(async move { Ok(Response::new(full(count.to_string()))) })
+        }));
+        let router = Arc::new(router);
+
+        for expected in ["1", "2"] {
+            let req = Request::post("/count").body(Default::default()).unwrap();
+            let resp = send(Arc::clone(&router), req).await;
+            assert_eq!(resp.body().as_ref(), expected.as_bytes());
+        }
+        assert_eq!(*router.ex.counter.lock().unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn error_formats() {
+        use crate::testing::send;
+
+        for (format, not_found, method_not_allowed, content_type) in [
+            (
+                None,
+                "Not Found",
+                "Method Not Allowed",
+                "text/plain; charset=utf-8",
+            ),
+            (
+                Some(Format::Json),
+                r#"{"error":"Not Found","status":404}"#,
+                r#"{"error":"Method Not Allowed","status":405}"#,
+                "application/json",
+            ),
+        ] {
+            let mut router = Router::<()>::default().register(
+                Route::new(path!("/users"))
+                    .get(|_, _| Box::pin(async { Ok(Response::new(full("users"))) })),
+            );
+            if let Some(format) = format {
+                router.error_format(format);
+            }
+            let router = Arc::new(router);
+
+            let req = Request::get("/missing").body(Default::default()).unwrap();
+            let resp = send(Arc::clone(&router), req).await;
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+            assert_eq!(resp.headers()[header::CONTENT_TYPE], content_type);
+            assert_eq!(resp.body().as_ref(), not_found.as_bytes());
+
+            let req = Request::delete("/users").body(Default::default()).unwrap();
+            let resp = send(router, req).await;
+            assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+            assert_eq!(resp.headers()[header::ALLOW], "GET");
+            assert_eq!(resp.headers()[header::CONTENT_TYPE], content_type);
+            assert_eq!(resp.body().as_ref(), method_not_allowed.as_bytes());
+        }
+    }
+
+    #[tokio::test]
+    async fn custom_method_not_allowed() {
+        use crate::testing::send;
+
+        let mut router = Router::<()>::default().register(
+            Route::new(path!("/items/[id]"))
+                .get(|_, _| unreachable!())
+                .put(|_, _| unreachable!()),
+        );
+        router.method_not_allowed(|route, _, ctx| {
+            let body = format!("{} {:?}", route.path, ctx.params);
+            Box::pin(async move {
+                Ok(Response::builder()
+                    .status(StatusCode::METHOD_NOT_ALLOWED)
+                    .body(full(body))
+                    .unwrap())
+            })
+        });
+
+        let req = Request::delete("/items/7")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(resp.body().as_ref(), br#"/items/[id] ["7"]"#);
+    }
+
+    #[tokio::test]
+    async fn error_handlers_capture_config() {
+        use crate::testing::send;
+
+        let template = String::from("<h1>Acme</h1><p>{message}</p>");
+        let mut router = Router::<()>::default()
+            .register(Route::new(path!("/items/[id]")).get(|_, _| unreachable!()));
+        let page = template.clone();
+        router.method_not_allowed(move |route, req, _| {
+            let message = format!("{} can't be used on {}", req.method(), route.path);
+            let body = page.replace("{message}", &message);
+            Box::pin(async move {
+                Ok(Response::builder()
+                    .status(StatusCode::METHOD_NOT_ALLOWED)
+                    .body(full(body))
+                    .unwrap())
+            })
+        });
+        router.route_not_found(move |req, _| {
+            let body = template.replace("{message}", &format!("{} not found", req.uri().path()));
+            Box::pin(async move {
+                Ok(Response::builder()
+                    .status(StatusCode::NOT_FOUND)
+                    .body(full(body))
+                    .unwrap())
+            })
+        });
+        let router = Arc::new(router);
+
+        let req = Request::post("/items/7").body(Default::default()).unwrap();
+        let resp = send(Arc::clone(&router), req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(
+            resp.body().as_ref(),
+            b"<h1>Acme</h1><p>POST can't be used on /items/[id]</p>"
+        );
+
+        let req = Request::get("/missing").body(Default::default()).unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            resp.body().as_ref(),
+            b"<h1>Acme</h1><p>/missing not found</p>"
+        );
+    }
+
+    #[tokio::test]
+    async fn auto_options() {
+        use crate::testing::send;
+
+        let mut router = Router::<()>::default()
+            .register(
+                Route::new(path!("/items"))
+                    .get(|_, _| unreachable!())
+                    .post(|_, _| unreachable!()),
+            )
+            .register(
+                Route::new(path!("/custom"))
+                    .get(|_, _| unreachable!())
+                    .options(|_, _| Box::pin(async { Ok(Response::new(full("custom"))) })),
+            );
+
+        let req = Request::options("/items").body(Default::default()).unwrap();
+        let resp = send(router.clone(), req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+        router.auto_options(true);
+        let router = Arc::new(router);
+
+        let req = Request::options("/items").body(Default::default()).unwrap();
+        let resp = send(Arc::clone(&router), req).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert_eq!(resp.headers()[header::ALLOW], "GET, POST, OPTIONS");
+        assert!(resp.body().is_empty());
+
+        let req = Request::options("/custom")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.body().as_ref(), b"custom");
+    }
+
+    #[test]
+    fn try_register_conflict() {
+        let router = Router::<()>::default()
+            .try_register(Route::new(path!("/users/[id]")).get(|_, _| unreachable!()))
+            .unwrap()
+            .try_register(Route::new(path!("/users/[id]/posts")))
+            .unwrap();
+
+        let conflict = router
+            .try_register(Route::new(path!("/users/[name]")).post(|_, _| unreachable!()))
+            .unwrap_err();
+        assert_eq!(conflict.existing.path.to_string(), "/users/[id]");
+        assert_eq!(conflict.existing.allowed_methods(), vec![Method::GET]);
+        assert_eq!(conflict.rejected.path.to_string(), "/users/[name]");
+        assert_eq!(
+            conflict.to_string(),
+            "route `/users/[name]` conflicts with registered route `/users/[id]`"
+        );
+    }
+
+    #[test]
+    fn no_conflicts() {
+        Router::<()>::default()
+            .register(Route::new(path!("/users/[id]")).get(|_, _| unreachable!()))
+            .register(Route::new(path!("/users/[id]")).post(|_, _| unreachable!()))
+            .register(Route::new(path!("/users/[id]/posts")))
+            .register(Route::new(path!("/users/me")))
+            .register(Route::new(path!("/files/[...rest]")))
+            .assert_no_conflicts();
+    }
+
+    #[tokio::test]
+    async fn exposes_matched_pattern() {
+        use crate::testing::send;
+
+        let router =
+            Router::<()>::default().register(Route::new(path!("/users/[id]")).get(|_, ctx| {
+                assert_eq!(ctx.matched_pattern, Some(path!("/users/[id]")));
+                Box::pin(async { Ok(Response::new(full("OK"))) })
+            }));
+
+        let req = Request::get("/users/42").body(Default::default()).unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.body().as_ref(), b"OK");
+    }
+
+    #[tokio::test]
+    async fn context_exposes_headers() {
+        use crate::testing::send;
+
+        let mut router =
+            Router::<()>::default().register(Route::new(path!("/me")).get(|_, ctx| {
+                let token = ctx.header(header::AUTHORIZATION).unwrap();
+                let body = format!("{} {}", ctx.method(), token.to_str().unwrap());
+                Box::pin(async move { Ok(Response::new(full(body))) })
+            }));
+        router.layer(|req, ctx, next| {
+            Box::pin(async move {
+                if ctx.header("authorization").is_none() {
+                    return Ok(Response::builder()
+                        .status(StatusCode::UNAUTHORIZED)
+                        .body(full(""))
+                        .unwrap());
                 }
 
-                Err(ref err) => {
-                    println!(
-                        "\x1B[34m[{}]\x1B[91m Error\x1B[97m {:6} {} ({:?}) => {:?}",
-                        Utc::now().format("%Y-%m-%d %H:%M:%S"),
-                        method,
-                        path,
-                        elapsed,
-                        err
-                    );
+                next.run(req, ctx).await
+            })
+        });
+        let router = Arc::new(router);
+
+        let req = Request::get("/me")
+            .header(header::AUTHORIZATION, "Bearer abc")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(Arc::clone(&router), req).await;
+        assert_eq!(resp.body().as_ref(), b"GET Bearer abc");
+
+        let req = Request::get("/me").body(Default::default()).unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn scoped_fallback() {
+        use crate::testing::send;
+
+        let mut router = Router::<()>::default()
+            .register(Route::new(path!("/api/users")).get(|_, _| unreachable!()));
+        router.fallback_under(path!("/api"), |_, _| {
+            Box::pin(async {
+                Ok(Response::builder()
+                    .status(StatusCode::NOT_FOUND)
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(full(r#"{"error":"not found"}"#))
+                    .unwrap())
+            })
+        });
+        router.case_insensitive(true);
+        let router = Arc::new(router);
+
+        // Prefixes match like routes do, encoded and in any case here.
+        for path in [
+            "/api/missing",
+            "/api/users/1/posts",
+            "/%61pi/missing",
+            "/API/missing",
+        ] {
+            let req = Request::get(path).body(Default::default()).unwrap();
+            let resp = send(Arc::clone(&router), req).await;
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+            assert_eq!(resp.headers()[header::CONTENT_TYPE], "application/json");
+            assert_eq!(resp.body().as_ref(), br#"{"error":"not found"}"#);
+        }
+
+        let req = Request::get("/missing").body(Default::default()).unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_ne!(resp.body().as_ref(), br#"{"error":"not found"}"#);
+    }
+
+    #[tokio::test]
+    async fn merge_routers() {
+        use crate::testing::send;
+
+        let text = |path: Path, body: &'static str| {
+            Route::new(path).get(move |_, _| Box::pin(async move { Ok(Response::new(full(body))) }))
+        };
+        let mut router = Router::<()>::default().register(text(path!("/users"), "users"));
+        router.route_not_found(|_, _| Box::pin(async { Ok(Response::new(full("own 404"))) }));
+
+        let mut posts = Router::<()>::default().register(text(path!("/posts"), "posts"));
+        posts.route_not_found(|_, _| unreachable!());
+        router.merge(posts);
+        router.assert_no_conflicts();
+
+        router.merge(Router::default().register(text(path!("/users"), "replaced")));
+        let router = Arc::new(router);
+
+        for (path, expected) in [
+            ("/users", "replaced"),
+            ("/posts", "posts"),
+            ("/missing", "own 404"),
+        ] {
+            let req = Request::get(path).body(Default::default()).unwrap();
+            let resp = send(Arc::clone(&router), req).await;
+            assert_eq!(resp.body().as_ref(), expected.as_bytes(), "{path}");
+        }
+
+        let panic = std::panic::catch_unwind(AssertUnwindSafe(|| router.assert_no_conflicts()))
+            .unwrap_err();
+        assert_eq!(
+            panic.downcast_ref::<String>().unwrap(),
+            "conflicting routes:\n`GET /users` is registered more than once"
+        );
+    }
+
+    #[test]
+    fn conflicts() {
+        let router = Router::<()>::default()
+            .register(Route::new(path!("/users/[id]")).get(|_, _| unreachable!()))
+            .register(Route::new(path!("/users/[id]")).get(|_, _| unreachable!()))
+            .register(Route::new(path!("/users/[name]/posts")))
+            .register(Route::new(Path(vec![
+                Segment::catch_all("rest"),
+                Segment::literal("edit"),
+            ])));
+
+        let panic = std::panic::catch_unwind(AssertUnwindSafe(|| router.assert_no_conflicts()))
+            .unwrap_err();
+        let message = panic.downcast_ref::<String>().unwrap();
+        assert_eq!(
+            message,
+            "conflicting routes:\n\
+             `/users/[name]/posts` names parameter `id` `name` instead\n\
+             `GET /users/[id]` is registered more than once\n\
+             catch-all `/[...rest]` is followed by more segments"
+        );
+    }
+
+    #[tokio::test]
+    async fn index_routes() {
+        use crate::testing::send;
+
+        let handler = |body: &'static str| {
+            move |_, _| -> std::pin::Pin<Box<crate::ResponseFut>> {
+                Box::pin(async move { Ok(Response::new(full(body))) })
+            }
+        };
+        let router = Router::<()>::default()
+            .register(Route::new(path!("/docs/index")).get(handler("docs index")))
+            .register(Route::new(path!("/blog")).get(handler("blog")))
+            .register(Route::new(path!("/blog/index")).get(handler("blog index")));
+        let get = |path: &str| Request::get(path).body(Default::default()).unwrap();
+
+        let resp = send(router.clone(), get("/docs/")).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        for policy in [
+            TrailingSlash::Lenient,
+            TrailingSlash::Strict,
+            TrailingSlash::RedirectToNoSlash,
+        ] {
+            let mut router = router.clone();
+            router.index_route("index").trailing_slash(policy);
+            let router = Arc::new(router);
+
+            let resp = send(Arc::clone(&router), get("/docs/")).await;
+            assert_eq!(resp.body(), "docs index", "{policy:?}");
+            let resp = send(Arc::clone(&router), get("/docs")).await;
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND, "{policy:?}");
+
+            let resp = send(router, get("/blog/")).await;
+            match policy {
+                TrailingSlash::Lenient => assert_eq!(resp.body(), "blog"),
+                TrailingSlash::Strict => assert_eq!(resp.body(), "blog index"),
+                TrailingSlash::RedirectToNoSlash => {
+                    assert_eq!(resp.status(), StatusCode::PERMANENT_REDIRECT)
                 }
             }
         }
+    }
 
-        resp
+    #[tokio::test]
+    async fn trailing_slash() {
+        use crate::testing::send;
+
+        let router = |policy| {
+            let mut router = Router::<()>::default().register(
+                Route::new(path!("/foo"))
+                    .get(|_, _| Box::pin(async { Ok(Response::new(full("foo"))) })),
+            );
+            router.trailing_slash(policy);
+            Arc::new(router)
+        };
+        let get = |path| Request::get(path).body(Default::default()).unwrap();
+
+        for policy in [
+            TrailingSlash::Strict,
+            TrailingSlash::Lenient,
+            TrailingSlash::RedirectToNoSlash,
+        ] {
+            let resp = send(router(policy), get("/foo")).await;
+            assert_eq!(resp.body().as_ref(), b"foo", "{:?}", policy);
+        }
+
+        let resp = send(router(TrailingSlash::Strict), get("/foo/")).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let resp = send(router(TrailingSlash::Lenient), get("/foo/")).await;
+        assert_eq!(resp.body().as_ref(), b"foo");
+
+        let router = router(TrailingSlash::RedirectToNoSlash);
+        let resp = send(Arc::clone(&router), get("/foo/?page=2")).await;
+        assert_eq!(resp.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(resp.headers()[header::LOCATION], "/foo?page=2");
+
+        let resp = send(router, get("/bar/")).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let mut router = Router::<()>::default().register(
+            Route::new(path!("/[site]"))
+                .get(|_, _| Box::pin(async { Ok(Response::new(full("site"))) })),
+        );
+        router.trailing_slash(TrailingSlash::RedirectToNoSlash);
+        let resp = send(router, get("//evil.com/")).await;
+        assert_eq!(resp.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(resp.headers()[header::LOCATION], "/evil.com");
     }
-}
 
-fn full<T>(chunk: T) -> BoxBody<Bytes, hyper::Error>
-where
-    T: Into<Bytes>,
-{
-    Full::new(chunk.into())
-        .map_err(|never| match never {})
-        .boxed()
+    #[test]
+    fn lists_routes() {
+        let handler = |_, _| -> std::pin::Pin<Box<crate::ResponseFut>> {
+            Box::pin(async { Ok(Response::new(full(""))) })
+        };
+        let router = Router::<()>::default()
+            .register(Route::new(path!("/")).get(handler))
+            .register(
+                Route::new(path!("/users/[id]"))
+                    .get(handler)
+                    .delete(handler),
+            )
+            .register(Route::new(path!("/users/[user_id]/posts/[...rest]")).post(handler));
+
+        let mut routes = router.routes();
+        routes.sort_by_key(|(path, _)| path.to_string());
+        let routes: Vec<_> = routes
+            .into_iter()
+            .map(|(path, methods)| (path.to_string(), methods))
+            .collect();
+        assert_eq!(
+            routes,
+            vec![
+                (String::from("/"), vec![Method::GET]),
+                (
+                    String::from("/users/[id]"),
+                    vec![Method::GET, Method::DELETE]
+                ),
+                (
+                    String::from("/users/[user_id]/posts/[...rest]"),
+                    vec![Method::POST]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn normalize_slashes() {
+        let mut router = Router::<()>::default()
+            .register(Route::new(path!("/")))
+            .register(Route::new(path!("/a/b")));
+
+        for path in ["/a//b", "//a/b", "/a/b//", "/a/b/"] {
+            let (route, _, _) = router.match_route(path).unwrap();
+            assert_eq!(route.path, path!("/a/b"), "{}", path);
+        }
+
+        router.normalize_slashes(false);
+        for path in ["/a//b", "//a/b", "/a/b//", "//"] {
+            assert_eq!(
+                router.match_route(path).unwrap_err(),
+                MatchError::NotFound,
+                "{}",
+                path
+            );
+        }
+        for path in ["/a/b", "/a/b/"] {
+            assert_eq!(
+                router.match_route(path).unwrap().0.path,
+                path!("/a/b"),
+                "{}",
+                path
+            );
+        }
+        assert_eq!(router.match_route("/").unwrap().0.path, path!("/"));
+    }
+
+    #[test]
+    fn match_on_raw_and_decoded() {
+        let mut router = Router::<()>::default()
+            .register(Route::new(path!("/a/b")))
+            .register(Route::new(path!("/[x]")));
+
+        let (route, params, _) = router.match_route("/a%2Fb").unwrap();
+        assert_eq!(route.path, path!("/[x]"));
+        assert_eq!(params, vec!["a/b".to_string()]);
+
+        router.match_on(MatchOn::Decoded);
+        let (route, params, _) = router.match_route("/a%2Fb").unwrap();
+        assert_eq!(route.path, path!("/a/b"));
+        assert!(params.is_empty());
+    }
+
+    #[test]
+    fn match_on_decoded_decodes_once() {
+        let mut router = Router::<()>::default()
+            .register(Route::new(path!("/a%25b")))
+            .register(Route::new(path!("/c/d%25e/f")));
+        // Compacting leaves `d%e` and `f` as a literal chain.
+        router.root.compact();
+
+        assert!(router.match_route("/a%25b").is_ok());
+        assert!(router.match_route("/a%2525b").is_err());
+        assert!(router.match_route("/c/d%25e/f").is_ok());
+        assert!(router.match_route("/c/d%2525e/f").is_err());
+
+        router.match_on(MatchOn::Decoded);
+        let (route, ..) = router.match_route("/a%25b").unwrap();
+        assert_eq!(route.path, path!("/a%25b"));
+        assert!(router.match_route("/c/d%25e/f").is_ok());
+        assert!(router.match_route("/a%2525b").is_err());
+        assert!(router.match_route("/c/d%2525e/f").is_err());
+    }
+
+    #[test]
+    fn match_on_decoded_decodes_patterns_once() {
+        let mut router = Router::<()>::default().register(Route::new(path!("/files/[name].txt")));
+
+        let (_, params, _) = router.match_route("/files/x%2etxt").unwrap();
+        assert_eq!(params, vec!["x".to_string()]);
+        assert!(router.match_route("/files/x%252etxt").is_err());
+
+        router.match_on(MatchOn::Decoded);
+        let (_, params, _) = router.match_route("/files/x%2etxt").unwrap();
+        assert_eq!(params, vec!["x".to_string()]);
+        assert!(router.match_route("/files/x%252etxt").is_err());
+    }
 }
@@ -1,3 +1,5 @@
+use crate::encoding::{find_decoded, strip_decoded_prefix};
+use macros::CaseIterable;
 use std::fmt::{Debug, Formatter};
 use std::hash::{Hash, Hasher};
 
@@ -14,6 +16,45 @@ pub enum Segment {
     /// - `/some/cool/segment` and
     /// - `/some/other/segment`
     Dynamic(String),
+    /// A dynamic path segment that only matches values satisfying its
+    /// constraint, written `[name:constraint]`. Constrained segments are equal
+    /// if their constraints are, regardless of their name.
+    ///
+    /// `/users/[id:int]` matches `/users/42`, but not `/users/me`.
+    Constrained(String, Constraint),
+    /// A segment mixing literal text and placeholders, such as
+    /// `report-[year].csv`. Each placeholder matches one or more characters.
+    /// Patterns are equal if their literal parts are and their placeholders
+    /// are in the same places, regardless of their names.
+    ///
+    /// `/reports/report-[year].csv` matches `/reports/report-2024.csv`.
+    Pattern(Vec<PatternPart>),
+    /// A dynamic segment that may be left out, written `[name?]`. It can only
+    /// be followed by other optional segments. All optional segments are
+    /// considered equal regardless of their name.
+    ///
+    /// `/posts/[page?]` matches
+    /// - `/posts/3` and
+    /// - `/posts`, without a `page` parameter.
+    Optional(String),
+    /// A catch-all segment that matches zero or more remaining segments.
+    /// Must be the final segment of a path.
+    ///
+    /// `/assets/[...path]` matches
+    /// - `/assets/style.css`,
+    /// - `/assets/img/logo.png` and
+    /// - `/assets`, capturing an empty `path`.
+    CatchAll(String),
+}
+
+/// A part of a [`Segment::Pattern`].
+#[derive(Clone, Debug)]
+pub enum PatternPart {
+    /// Literal text, stored percent-decoded, that matches itself as is or
+    /// percent-encoded.
+    Literal(String),
+    /// A named placeholder, written `[name]`.
+    Placeholder(String),
 }
 
 impl Segment {
@@ -26,21 +67,184 @@ impl Segment {
     pub fn dynamic(dynamic: impl Into<String>) -> Self {
         Self::Dynamic(dynamic.into())
     }
+
+    /// Constructs a constrained dynamic segment from any `Into<String>`.
+    pub fn constrained(name: impl Into<String>, constraint: Constraint) -> Self {
+        Self::Constrained(name.into(), constraint)
+    }
+
+    /// Constructs an optional dynamic segment from any `Into<String>`.
+    pub fn optional(optional: impl Into<String>) -> Self {
+        Self::Optional(optional.into())
+    }
+
+    /// Constructs a catch-all segment from any `Into<String>`.
+    pub fn catch_all(catch_all: impl Into<String>) -> Self {
+        Self::CatchAll(catch_all.into())
+    }
+
+    /// Returns the values captured by the placeholders of a pattern segment
+    /// in `value`, or `None` if it doesn't match or this isn't a pattern.
+    ///
+    /// A placeholder followed by literal text captures up to the first
+    /// occurrence of that text.
+    pub fn captures<'a>(&self, value: &'a str) -> Option<Vec<&'a str>> {
+        let mut captures = vec![];
+        self.capture_each(value, true, |capture| captures.push(capture))?;
+        Some(captures)
+    }
+
+    /// Matches a single segment of a request path, `input`, returning the
+    /// parameters it captures as pairs of names and values, or `None` if it
+    /// doesn't match.
+    ///
+    /// Literals match `input` exactly and capture nothing. Other segments
+    /// capture `input` under their name, and patterns capture a value per
+    /// placeholder. Only the first of the segments a catch-all consumes is
+    /// matched here. Empty input only matches an empty literal or a
+    /// catch-all, which may capture nothing.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use radmin_router::{Constraint, Segment};
+    ///
+    /// assert_eq!(Segment::literal("users").matches("users"), Some(vec![]));
+    /// assert_eq!(
+    ///     Segment::constrained("id", Constraint::Int).matches("42"),
+    ///     Some(vec![("id", "42")])
+    /// );
+    /// assert_eq!(Segment::constrained("id", Constraint::Int).matches("me"), None);
+    /// ```
+    pub fn matches<'s, 'a>(&'s self, input: &'a str) -> Option<Vec<(&'s str, &'a str)>> {
+        self.matches_with(input, true)
+    }
+
+    /// Like [`matches`](Self::matches), but the literal text of patterns only
+    /// matches percent-encoded with `decode`. Paths matched per
+    /// [`MatchOn::Decoded`](crate::MatchOn::Decoded) are already decoded.
+    pub(crate) fn matches_with<'s, 'a>(
+        &'s self,
+        input: &'a str,
+        decode: bool,
+    ) -> Option<Vec<(&'s str, &'a str)>> {
+        match self {
+            Self::Literal(literal) => (literal == input).then(Vec::new),
+            Self::Pattern(parts) => {
+                let names = parts.iter().filter_map(|part| match part {
+                    PatternPart::Placeholder(name) => Some(name.as_str()),
+                    PatternPart::Literal(_) => None,
+                });
+                let mut captures = vec![];
+                self.capture_each(input, decode, |capture| captures.push(capture))?;
+                Some(names.zip(captures).collect())
+            }
+            Self::Dynamic(name)
+            | Self::Constrained(name, _)
+            | Self::Optional(name)
+            | Self::CatchAll(name) => self.is_match(input).then(|| vec![(name.as_str(), input)]),
+        }
+    }
+
+    /// Returns whether `input` matches this segment, like
+    /// [`matches`](Self::matches) without collecting the captures.
+    pub fn is_match(&self, input: &str) -> bool {
+        self.is_match_with(input, true)
+    }
+
+    /// Like [`is_match`](Self::is_match), decoding the literal text of
+    /// patterns like [`matches_with`](Self::matches_with).
+    pub(crate) fn is_match_with(&self, input: &str, decode: bool) -> bool {
+        match self {
+            Self::Literal(literal) => literal == input,
+            Self::Constrained(_, constraint) => constraint.matches(input),
+            Self::Pattern(_) => self.capture_each(input, decode, |_| {}).is_some(),
+            Self::Dynamic(_) | Self::Optional(_) => !input.is_empty(),
+            Self::CatchAll(_) => true,
+        }
+    }
+
+    /// Passes each value captured by the placeholders of a pattern segment in
+    /// `value` to `capture`, returning `None` if it doesn't match.
+    fn capture_each<'a>(
+        &self,
+        value: &'a str,
+        decode: bool,
+        mut capture: impl FnMut(&'a str),
+    ) -> Option<()> {
+        let Self::Pattern(parts) = self else {
+            return None;
+        };
+
+        let mut rest = value;
+
+        // Literal parts are stored decoded, and match their text either as is
+        // or, with `decode`, percent-encoded.
+        for (idx, part) in parts.iter().enumerate() {
+            match part {
+                PatternPart::Literal(literal) if decode => {
+                    rest = strip_decoded_prefix(rest, literal)?
+                }
+                PatternPart::Literal(literal) => rest = rest.strip_prefix(literal.as_str())?,
+                PatternPart::Placeholder(_) => {
+                    let end = match parts.get(idx + 1) {
+                        Some(PatternPart::Literal(next)) => {
+                            let first = rest.chars().next()?.len_utf8();
+                            let found = if decode {
+                                find_decoded(&rest[first..], next)
+                            } else {
+                                rest[first..].find(next.as_str())
+                            };
+                            found? + first
+                        }
+                        _ => rest.len(),
+                    };
+
+                    if end == 0 {
+                        return None;
+                    }
+                    capture(&rest[..end]);
+                    rest = &rest[end..];
+                }
+            }
+        }
+
+        rest.is_empty().then_some(())
+    }
 }
 
 impl Debug for Segment {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Dynamic(name) => write!(f, "[{}]", name),
+            Self::Constrained(name, constraint) => write!(f, "[{}:{}]", name, constraint.name()),
+            Self::Optional(name) => write!(f, "[{}?]", name),
+            Self::CatchAll(name) => write!(f, "[...{}]", name),
             Self::Literal(segment) => write!(f, "{}", segment),
+            Self::Pattern(parts) => parts.iter().try_for_each(|part| match part {
+                PatternPart::Literal(literal) => write!(f, "{}", literal),
+                PatternPart::Placeholder(name) => write!(f, "[{}]", name),
+            }),
         }
     }
 }
 
 impl Hash for Segment {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        if let Self::Literal(literal) = self {
-            literal.hash(state);
+        std::mem::discriminant(self).hash(state);
+
+        match self {
+            Self::Literal(literal) => literal.hash(state),
+            Self::Constrained(_, constraint) => constraint.hash(state),
+            Self::Pattern(parts) => {
+                for part in parts {
+                    match part {
+                        PatternPart::Literal(literal) => Some(literal).hash(state),
+                        PatternPart::Placeholder(_) => None::<&String>.hash(state),
+                    }
+                }
+            }
+            Self::Dynamic(_) | Self::Optional(_) | Self::CatchAll(_) => {}
         }
     }
 }
@@ -50,6 +254,17 @@ impl PartialEq for Segment {
         match (self, other) {
             (Segment::Literal(lhs), Segment::Literal(rhs)) => lhs == rhs,
             (Segment::Dynamic(_), Segment::Dynamic(_)) => true,
+            (Segment::Constrained(_, lhs), Segment::Constrained(_, rhs)) => lhs == rhs,
+            (Segment::Pattern(lhs), Segment::Pattern(rhs)) => {
+                lhs.len() == rhs.len()
+                    && lhs.iter().zip(rhs).all(|parts| match parts {
+                        (PatternPart::Literal(lhs), PatternPart::Literal(rhs)) => lhs == rhs,
+                        (PatternPart::Placeholder(_), PatternPart::Placeholder(_)) => true,
+                        _ => false,
+                    })
+            }
+            (Segment::Optional(_), Segment::Optional(_)) => true,
+            (Segment::CatchAll(_), Segment::CatchAll(_)) => true,
             _ => false,
         }
     }
@@ -57,6 +272,51 @@ impl PartialEq for Segment {
 
 impl Eq for Segment {}
 
+/// The values a [`Segment::Constrained`] matches.
+#[derive(CaseIterable, Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum Constraint {
+    /// One or more ASCII digits, written `int`.
+    Int,
+    /// A hyphenated UUID such as `67e55044-10b1-426f-9247-bb680e5fe0c8`,
+    /// written `uuid`.
+    Uuid,
+    /// One or more ASCII letters, written `alpha`.
+    Alpha,
+}
+
+impl Constraint {
+    /// Returns the constraint written as `name`, if any.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::all_cases()
+            .into_iter()
+            .find(|constraint| constraint.name() == name)
+    }
+
+    /// Returns the name the constraint is written as in paths.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Int => "int",
+            Self::Uuid => "uuid",
+            Self::Alpha => "alpha",
+        }
+    }
+
+    /// Returns whether `value` satisfies the constraint.
+    pub fn matches(self, value: &str) -> bool {
+        match self {
+            Self::Int => !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()),
+            Self::Uuid => {
+                value.len() == 36
+                    && value.bytes().enumerate().all(|(idx, b)| match idx {
+                        8 | 13 | 18 | 23 => b == b'-',
+                        _ => b.is_ascii_hexdigit(),
+                    })
+            }
+            Self::Alpha => !value.is_empty() && value.bytes().all(|b| b.is_ascii_alphabetic()),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -87,4 +347,128 @@ mod tests {
         let segment2 = Segment::dynamic("2");
         assert_eq!(hash(&segment1a), hash(&segment2));
     }
+
+    #[test]
+    fn constrained_eq() {
+        let int = Segment::constrained("id", Constraint::Int);
+        assert_eq!(int, Segment::constrained("other", Constraint::Int));
+        assert_eq!(
+            hash(&int),
+            hash(&Segment::constrained("other", Constraint::Int))
+        );
+        assert_ne!(int, Segment::constrained("id", Constraint::Alpha));
+        assert_ne!(int, Segment::dynamic("id"));
+    }
+
+    #[test]
+    fn constraints() {
+        assert!(Constraint::Int.matches("0042"));
+        assert!(!Constraint::Int.matches("42a"));
+        assert!(!Constraint::Int.matches(""));
+        assert!(Constraint::Alpha.matches("abcXYZ"));
+        assert!(!Constraint::Alpha.matches("abc1"));
+        assert!(Constraint::Uuid.matches("67e55044-10b1-426f-9247-bb680e5fe0c8"));
+        assert!(!Constraint::Uuid.matches("67e55044-10b1-426f-9247-bb680e5fe0c"));
+        assert!(!Constraint::Uuid.matches("67e55044x10b1-426f-9247-bb680e5fe0c8"));
+    }
+
+    #[test]
+    fn pattern_eq() {
+        let pattern = |name: &str, suffix: &str| {
+            Segment::Pattern(vec![
+                PatternPart::Literal("report-".into()),
+                PatternPart::Placeholder(name.into()),
+                PatternPart::Literal(suffix.into()),
+            ])
+        };
+
+        assert_eq!(pattern("year", ".csv"), pattern("y", ".csv"));
+        assert_eq!(hash(&pattern("year", ".csv")), hash(&pattern("y", ".csv")));
+        assert_ne!(pattern("year", ".csv"), pattern("year", ".json"));
+        assert_ne!(
+            pattern("year", ".csv"),
+            Segment::Pattern(vec![PatternPart::Literal("report-.csv".into())])
+        );
+    }
+
+    #[test]
+    fn pattern_captures() {
+        let pattern = Segment::Pattern(vec![
+            PatternPart::Literal("report-".into()),
+            PatternPart::Placeholder("year".into()),
+            PatternPart::Literal("-".into()),
+            PatternPart::Placeholder("month".into()),
+            PatternPart::Literal(".csv".into()),
+        ]);
+
+        assert_eq!(
+            pattern.captures("report-2024-06.csv"),
+            Some(vec!["2024", "06"])
+        );
+        assert_eq!(
+            pattern.captures("report-2024-06-b.csv"),
+            Some(vec!["2024", "06-b"])
+        );
+        assert_eq!(pattern.captures("report--06.csv"), None);
+        assert_eq!(pattern.captures("report-2024-06.json"), None);
+        // Literal text also matches percent-encoded, keeping captures raw.
+        assert_eq!(
+            pattern.captures("report%2D2024%2d%30%36.cs%76"),
+            Some(vec!["2024", "%30%36"])
+        );
+        assert_eq!(Segment::dynamic("x").captures("x"), None);
+    }
+
+    #[test]
+    fn catch_all_eq() {
+        assert_eq!(Segment::catch_all("a"), Segment::catch_all("b"));
+        assert_ne!(Segment::catch_all("a"), Segment::dynamic("a"));
+    }
+
+    #[test]
+    fn matches_each_variant() {
+        assert_eq!(Segment::literal("users").matches("users"), Some(vec![]));
+        assert_eq!(Segment::literal("users").matches("Users"), None);
+
+        assert_eq!(
+            Segment::dynamic("id").matches("42"),
+            Some(vec![("id", "42")])
+        );
+        assert_eq!(Segment::dynamic("id").matches(""), None);
+
+        let int = Segment::constrained("id", Constraint::Int);
+        assert_eq!(int.matches("42"), Some(vec![("id", "42")]));
+        assert_eq!(int.matches("4a"), None);
+        let uuid = Segment::constrained("id", Constraint::Uuid);
+        assert!(uuid.is_match("67e55044-10b1-426f-9247-bb680e5fe0c8"));
+        assert!(!uuid.is_match("67e55044"));
+        let alpha = Segment::constrained("name", Constraint::Alpha);
+        assert_eq!(alpha.matches("bob"), Some(vec![("name", "bob")]));
+        assert_eq!(alpha.matches("bob1"), None);
+
+        let pattern = Segment::Pattern(vec![
+            PatternPart::Placeholder("name".into()),
+            PatternPart::Literal(".".into()),
+            PatternPart::Placeholder("ext".into()),
+        ]);
+        assert_eq!(
+            pattern.matches("logo.png"),
+            Some(vec![("name", "logo"), ("ext", "png")])
+        );
+        assert_eq!(pattern.matches("logo"), None);
+        assert!(!pattern.is_match(".png"));
+
+        assert_eq!(
+            Segment::optional("page").matches("3"),
+            Some(vec![("page", "3")])
+        );
+        assert_eq!(
+            Segment::catch_all("path").matches("img"),
+            Some(vec![("path", "img")])
+        );
+        assert_eq!(
+            Segment::catch_all("path").matches(""),
+            Some(vec![("path", "")])
+        );
+    }
 }
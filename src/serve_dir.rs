@@ -0,0 +1,630 @@
+use crate::body::{empty, full};
+use crate::context::Context;
+use crate::encoding::percent_decode_utf8;
+use crate::hash::Fnv1a;
+use crate::middleware::if_none_match;
+use crate::route::{HandlerFn, ResponseFut};
+use bytes::Bytes;
+use futures::stream;
+use http_body_util::combinators::BoxBody;
+use http_body_util::{BodyExt, StreamBody};
+use hyper::body::{Frame, Incoming};
+use hyper::header::{self, HeaderValue};
+use hyper::{Method, Request, Response, StatusCode};
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::io::{self, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::fs::File;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, Take};
+
+/// The size of the chunks file bodies are streamed in.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// A strong `ETag`, with the size and modification time of the file it was
+/// computed for.
+type CachedHash = (u64, SystemTime, HeaderValue);
+
+/// How [`ServeDir`] computes `ETag`s.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ETag {
+    /// A weak tag derived from the file's size and modification time, which
+    /// needs no reads.
+    #[default]
+    Weak,
+    /// A strong tag hashing the file's contents. The hash is cached until the
+    /// file's size or modification time changes, and HEAD requests only use
+    /// a cached hash, leaving the tag out rather than reading the file.
+    Strong,
+    /// No `ETag`.
+    Disabled,
+}
+
+/// A handler serving files from a directory, for a route ending in a
+/// catch-all segment that captures the file's path relative to the root.
+///
+/// Responds with the file streamed in chunks, a `Content-Type` guessed from
+/// its extension and an [`ETag`]. Requests carrying a matching
+/// `If-None-Match` get `304 Not Modified`. A single-range `Range` header is
+/// answered with `206 Partial Content`, reading from the requested offset
+/// onwards, or `416 Range Not Satisfiable`. HEAD requests, whether routed
+/// directly or through [`Route::auto_head`](crate::Route::auto_head), only
+/// look up the file's metadata, without reading it.
+///
+/// Paths containing `..` are rejected with `403 Forbidden`, so requests
+/// can't escape the root. Missing files and directories respond with
+/// `404 Not Found`.
+///
+/// # Example
+///
+/// ```
+/// use radmin_router::{Route, Router, ServeDir, path};
+///
+/// Router::<()>::default().register(
+///     Route::new(path!("/assets/[...path]"))
+///         .auto_head()
+///         .get(ServeDir::new("public").handler()),
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct ServeDir {
+    root: PathBuf,
+    etag: ETag,
+    /// Strong `ETag`s by file.
+    hashes: Arc<Mutex<HashMap<PathBuf, CachedHash>>>,
+}
+
+impl ServeDir {
+    /// Creates a handler serving files under `root`.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            etag: ETag::default(),
+            hashes: Arc::default(),
+        }
+    }
+
+    /// Sets how `ETag`s are computed. Defaults to [`ETag::Weak`].
+    pub fn etag(mut self, strategy: ETag) -> Self {
+        self.etag = strategy;
+        self
+    }
+
+    /// Builds the handler.
+    pub fn handler<Extra: Send + Sync + 'static>(self) -> impl HandlerFn<Extra> {
+        let dir = Arc::new(self);
+
+        move |req: Request<Incoming>, ctx: Context<Extra>| -> Pin<Box<ResponseFut>> {
+            let dir = Arc::clone(&dir);
+            let path = ctx.params.last().cloned().unwrap_or_default();
+
+            Box::pin(async move { Ok(dir.serve(&req, &path).await) })
+        }
+    }
+
+    async fn serve(&self, req: &Request<Incoming>, path: &str) -> crate::Response {
+        let path = match self.resolve(path) {
+            Ok(path) => path,
+            Err(status) => return status_response(status),
+        };
+
+        let metadata = match tokio::fs::metadata(&path).await {
+            Ok(metadata) if metadata.is_file() => metadata,
+            _ => return status_response(StatusCode::NOT_FOUND),
+        };
+
+        let head = req.method() == Method::HEAD;
+        let etag = match self.compute_etag(&path, &metadata, head).await {
+            Ok(etag) => etag,
+            Err(_) => return status_response(StatusCode::NOT_FOUND),
+        };
+
+        let mut resp = Response::new(empty());
+        if let Some(etag) = etag {
+            if if_none_match(req.headers(), &etag) {
+                *resp.status_mut() = StatusCode::NOT_MODIFIED;
+            }
+            resp.headers_mut().insert(header::ETAG, etag);
+        }
+        if resp.status() == StatusCode::NOT_MODIFIED {
+            return resp;
+        }
+
+        let len = metadata.len();
+        let range = match req
+            .headers()
+            .get(header::RANGE)
+            .map(|range| parse_range(range, len))
+        {
+            Some(Some(Ok(range))) => Some(range),
+            Some(Some(Err(()))) => {
+                let mut resp = status_response(StatusCode::RANGE_NOT_SATISFIABLE);
+                resp.headers_mut().insert(
+                    header::CONTENT_RANGE,
+                    HeaderValue::from_str(&format!("bytes */{}", len)).unwrap(),
+                );
+                return resp;
+            }
+            // Malformed and multi-range requests get the whole file.
+            Some(None) | None => None,
+        };
+
+        let headers = resp.headers_mut();
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static(content_type(&path)),
+        );
+        headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+
+        let (start, end) = range.unwrap_or((0, len.saturating_sub(1)));
+        let body_len = if len == 0 { 0 } else { end - start + 1 };
+        if range.is_some() {
+            *resp.status_mut() = StatusCode::PARTIAL_CONTENT;
+            resp.headers_mut().insert(
+                header::CONTENT_RANGE,
+                HeaderValue::from_str(&format!("bytes {}-{}/{}", start, end, len)).unwrap(),
+            );
+        }
+        resp.headers_mut()
+            .insert(header::CONTENT_LENGTH, body_len.into());
+
+        if head {
+            return resp;
+        }
+
+        let file = async { read_range(File::open(&path).await?, start, body_len).await };
+        match file.await {
+            Ok(file) => {
+                *resp.body_mut() = file_body(file);
+                resp
+            }
+            Err(_) => status_response(StatusCode::NOT_FOUND),
+        }
+    }
+
+    /// Resolves a request path, as captured by the route's catch-all, against
+    /// the root.
+    fn resolve(&self, path: &str) -> Result<PathBuf, StatusCode> {
+        let path = percent_decode_utf8(path).ok_or(StatusCode::NOT_FOUND)?;
+        let mut resolved = self.root.clone();
+
+        for component in path.split('/') {
+            match component {
+                "" | "." => {}
+                ".." => return Err(StatusCode::FORBIDDEN),
+                // Separators and drive prefixes on other platforms, or a NUL
+                // truncating the path.
+                component if component.contains(['\\', ':', '\0']) => {
+                    return Err(StatusCode::FORBIDDEN);
+                }
+                component => resolved.push(component),
+            }
+        }
+
+        Ok(resolved)
+    }
+
+    /// Computes the `ETag` of the file at `path`. With `head`, a strong tag is
+    /// only returned if it's cached.
+    async fn compute_etag(
+        &self,
+        path: &Path,
+        metadata: &Metadata,
+        head: bool,
+    ) -> io::Result<Option<HeaderValue>> {
+        let tag = match self.etag {
+            ETag::Weak => {
+                let modified = metadata
+                    .modified()?
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default();
+                format!("W/\"{:x}-{:x}\"", metadata.len(), modified.as_nanos())
+            }
+            ETag::Strong => {
+                let modified = metadata.modified()?;
+                let cached = self.hashes.lock().unwrap().get(path).cloned();
+                match cached {
+                    Some((len, at, tag)) if len == metadata.len() && at == modified => {
+                        return Ok(Some(tag));
+                    }
+                    _ if head => return Ok(None),
+                    _ => {}
+                }
+
+                let mut file = File::open(path).await?;
+                let mut buf = vec![0; CHUNK_SIZE];
+                let mut hash = Fnv1a::default();
+
+                loop {
+                    let read = file.read(&mut buf).await?;
+                    if read == 0 {
+                        break;
+                    }
+                    hash.write(&buf[..read]);
+                }
+
+                let tag = HeaderValue::from_str(&format!("\"{:016x}\"", hash.0)).unwrap();
+                self.hashes
+                    .lock()
+                    .unwrap()
+                    .insert(path.to_path_buf(), (metadata.len(), modified, tag.clone()));
+                return Ok(Some(tag));
+            }
+            ETag::Disabled => return Ok(None),
+        };
+
+        Ok(Some(HeaderValue::from_str(&tag).unwrap()))
+    }
+}
+
+/// Parses a single-range `Range` header into the inclusive byte range it
+/// selects from a file of `len` bytes. Returns `None` if the header is
+/// malformed or requests several ranges, which are served as the full file,
+/// and `Some(Err(()))` if the range is unsatisfiable.
+fn parse_range(range: &HeaderValue, len: u64) -> Option<Result<(u64, u64), ()>> {
+    let spec = range.to_str().ok()?.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') {
+        return None;
+    }
+
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = (start.trim(), end.trim());
+    let range = match (start.is_empty(), end.is_empty()) {
+        // `-n`: the last `n` bytes.
+        (true, false) => {
+            let suffix: u64 = end.parse().ok()?;
+            (suffix > 0 && len > 0).then(|| (len.saturating_sub(suffix), len - 1))
+        }
+        (false, _) => {
+            let start: u64 = start.parse().ok()?;
+            let end = match end {
+                "" => None,
+                end => Some(end.parse::<u64>().ok()?),
+            };
+            if end.is_some_and(|end| end < start) {
+                return None;
+            }
+
+            let end = end.unwrap_or(u64::MAX).min(len.saturating_sub(1));
+            (start < len).then_some((start, end))
+        }
+        (true, true) => return None,
+    };
+
+    Some(range.ok_or(()))
+}
+
+/// Positions `file` at `start`, seeking past the preceding data rather than
+/// reading it, and limits it to the `len` bytes of the range.
+async fn read_range<F>(mut file: F, start: u64, len: u64) -> io::Result<Take<F>>
+where
+    F: AsyncRead + AsyncSeek + Unpin,
+{
+    if start > 0 {
+        file.seek(SeekFrom::Start(start)).await?;
+    }
+    Ok(file.take(len))
+}
+
+/// Streams `reader` in chunks. A read error ends the body early, which aborts
+/// the response since it falls short of its `Content-Length`.
+fn file_body<R>(reader: R) -> BoxBody<Bytes, crate::BodyError>
+where
+    R: AsyncRead + Send + Sync + Unpin + 'static,
+{
+    let chunks = stream::unfold(reader, |mut reader| async move {
+        let mut buf = vec![0; CHUNK_SIZE];
+        match reader.read(&mut buf).await {
+            Ok(0) | Err(_) => None,
+            Ok(read) => {
+                buf.truncate(read);
+                Some((Ok(Frame::data(Bytes::from(buf))), reader))
+            }
+        }
+    });
+
+    StreamBody::new(chunks).boxed()
+}
+
+/// Guesses a file's `Content-Type` from its extension.
+fn content_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+
+    match extension.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" | "map" => "application/json",
+        "txt" => "text/plain; charset=utf-8",
+        "csv" => "text/csv; charset=utf-8",
+        "xml" => "application/xml",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "wasm" => "application/wasm",
+        "pdf" => "application/pdf",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "mp3" => "audio/mpeg",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        _ => "application/octet-stream",
+    }
+}
+
+fn status_response(status: StatusCode) -> crate::Response {
+    let mut resp = Response::new(full(status.canonical_reason().unwrap_or_default()));
+    *resp.status_mut() = status;
+    resp
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::send;
+    use crate::{Route, Router, path};
+    use std::task::{self, Poll};
+    use tokio::io::ReadBuf;
+    use http_body_util::Full;
+
+    /// Creates a fresh directory of assets for a test.
+    fn assets(test: &str) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("radmin-router-{}-{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("css")).unwrap();
+        std::fs::write(root.join("index.html"), "<h1>hello</h1>").unwrap();
+        std::fs::write(root.join("css/site.css"), "body { margin: 0 }").unwrap();
+        root
+    }
+
+    fn router(dir: ServeDir) -> Arc<Router<()>> {
+        Arc::new(
+            Router::<()>::default().register(
+                Route::new(path!("/assets/[...path]"))
+                    .auto_head()
+                    .get(dir.handler()),
+            ),
+        )
+    }
+
+    fn get(path: &str) -> Request<Full<Bytes>> {
+        Request::get(path).body(Default::default()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn serves_files() {
+        let router = router(ServeDir::new(assets("serves_files")));
+
+        let resp = send(Arc::clone(&router), get("/assets/css/site.css")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers()[header::CONTENT_TYPE],
+            "text/css; charset=utf-8"
+        );
+        assert_eq!(resp.headers()[header::CONTENT_LENGTH], "18");
+        assert_eq!(resp.body().as_ref(), b"body { margin: 0 }");
+
+        let resp = send(router, get("/assets/index%2Ehtml")).await;
+        assert_eq!(resp.body().as_ref(), b"<h1>hello</h1>");
+    }
+
+    #[tokio::test]
+    async fn missing_files() {
+        let router = router(ServeDir::new(assets("missing_files")));
+
+        let resp = send(Arc::clone(&router), get("/assets/missing.css")).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let resp = send(router, get("/assets/css")).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn rejects_traversal() {
+        let root = assets("rejects_traversal");
+        std::fs::write(root.with_extension("secret"), "secret").unwrap();
+        let router = router(ServeDir::new(root.join("css")));
+
+        for path in [
+            "/assets/../index.html",
+            "/assets/%2E%2E/index.html",
+            "/assets/..%2F..%2Frejects_traversal.secret",
+            "/assets/..%5Cindex.html",
+        ] {
+            let resp = send(Arc::clone(&router), get(path)).await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN, "{}", path);
+        }
+    }
+
+    #[tokio::test]
+    async fn head_skips_body() {
+        let root = assets("head_skips_body");
+        let head = || {
+            Request::head("/assets/index.html")
+                .body(Default::default())
+                .unwrap()
+        };
+
+        let resp = send(router(ServeDir::new(&root)), head()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()[header::CONTENT_LENGTH], "14");
+        assert!(resp.body().is_empty());
+
+        // Strong tags aren't computed for HEAD, only reused once a GET has.
+        let router = router(ServeDir::new(&root).etag(ETag::Strong));
+        let resp = send(Arc::clone(&router), head()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()[header::CONTENT_LENGTH], "14");
+        assert!(!resp.headers().contains_key(header::ETAG));
+
+        let etag = send(Arc::clone(&router), get("/assets/index.html"))
+            .await
+            .headers()[header::ETAG]
+            .clone();
+        let resp = send(Arc::clone(&router), head()).await;
+        assert_eq!(resp.headers()[header::ETAG], etag);
+
+        // A changed file is hashed again.
+        std::fs::write(root.join("index.html"), "<h1>changed</h1>").unwrap();
+        let resp = send(Arc::clone(&router), head()).await;
+        assert!(!resp.headers().contains_key(header::ETAG));
+        let resp = send(router, get("/assets/index.html")).await;
+        assert_ne!(resp.headers()[header::ETAG], etag);
+    }
+
+    #[tokio::test]
+    async fn byte_ranges() {
+        let root = assets("byte_ranges");
+        let contents: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(root.join("video.mp4"), &contents).unwrap();
+        let router = router(ServeDir::new(&root));
+        let range = |range: &str| {
+            Request::get("/assets/video.mp4")
+                .header(header::RANGE, range)
+                .body(Default::default())
+                .unwrap()
+        };
+
+        let resp = send(Arc::clone(&router), get("/assets/video.mp4")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()[header::ACCEPT_RANGES], "bytes");
+
+        let resp = send(Arc::clone(&router), range("bytes=0-4")).await;
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(resp.headers()[header::CONTENT_RANGE], "bytes 0-4/200000");
+        assert_eq!(resp.headers()[header::CONTENT_LENGTH], "5");
+        assert_eq!(resp.body().as_ref(), &contents[..5]);
+
+        let resp = send(Arc::clone(&router), range("bytes=100-")).await;
+        assert_eq!(
+            resp.headers()[header::CONTENT_RANGE],
+            "bytes 100-199999/200000"
+        );
+        assert_eq!(resp.body().as_ref(), &contents[100..]);
+
+        let resp = send(Arc::clone(&router), range("bytes=-10")).await;
+        assert_eq!(resp.body().as_ref(), &contents[199_990..]);
+
+        let resp = send(Arc::clone(&router), range("bytes=200000-")).await;
+        assert_eq!(resp.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(resp.headers()[header::CONTENT_RANGE], "bytes */200000");
+
+        for ignored in ["bytes=5-1", "bytes=0-1, 4-5", "items=0-1"] {
+            let resp = send(Arc::clone(&router), range(ignored)).await;
+            assert_eq!(resp.status(), StatusCode::OK, "{}", ignored);
+            assert_eq!(resp.body().len(), contents.len());
+        }
+    }
+
+    /// A file source counting the bytes read from it.
+    struct CountingReader<R> {
+        inner: R,
+        read: u64,
+    }
+
+    impl<R: AsyncRead + Unpin> AsyncRead for CountingReader<R> {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut task::Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            let before = buf.filled().len();
+            let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+            self.read += (buf.filled().len() - before) as u64;
+            poll
+        }
+    }
+
+    impl<R: AsyncSeek + Unpin> AsyncSeek for CountingReader<R> {
+        fn start_seek(mut self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+            Pin::new(&mut self.inner).start_seek(position)
+        }
+
+        fn poll_complete(
+            mut self: Pin<&mut Self>,
+            cx: &mut task::Context<'_>,
+        ) -> Poll<io::Result<u64>> {
+            Pin::new(&mut self.inner).poll_complete(cx)
+        }
+    }
+
+    #[tokio::test]
+    async fn seeks_to_range() {
+        let root = assets("seeks_to_range");
+        let contents: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(root.join("video.mp4"), &contents).unwrap();
+
+        // Past the first chunk, so reading starts at a seeked offset.
+        let req = Request::get("/assets/video.mp4")
+            .header(header::RANGE, "bytes=150000-150099")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(router(ServeDir::new(&root)), req).await;
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            resp.headers()[header::CONTENT_RANGE],
+            "bytes 150000-150099/200000"
+        );
+        assert_eq!(resp.body().as_ref(), &contents[150_000..150_100]);
+
+        // Only the range itself is read from the file.
+        let file = CountingReader {
+            inner: io::Cursor::new(contents.clone()),
+            read: 0,
+        };
+        let mut range = read_range(file, 150_000, 100).await.unwrap();
+        let mut read = Vec::new();
+        range.read_to_end(&mut read).await.unwrap();
+        assert_eq!(read, &contents[150_000..150_100]);
+        assert_eq!(range.into_inner().read, 100);
+    }
+
+    #[tokio::test]
+    async fn etags() {
+        let root = assets("etags");
+
+        for strategy in [ETag::Weak, ETag::Strong] {
+            let router = router(ServeDir::new(&root).etag(strategy));
+
+            let first = send(Arc::clone(&router), get("/assets/index.html")).await;
+            let second = send(Arc::clone(&router), get("/assets/index.html")).await;
+            let etag = first.headers()[header::ETAG].clone();
+            assert_eq!(etag, second.headers()[header::ETAG]);
+            assert_eq!(
+                etag.to_str().unwrap().starts_with("W/"),
+                strategy == ETag::Weak
+            );
+
+            let req = Request::get("/assets/index.html")
+                .header(header::IF_NONE_MATCH, etag.clone())
+                .body(Default::default())
+                .unwrap();
+            let resp = send(Arc::clone(&router), req).await;
+            assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+            assert_eq!(resp.headers()[header::ETAG], etag);
+            assert!(resp.body().is_empty());
+
+            let req = Request::get("/assets/index.html")
+                .header(header::IF_NONE_MATCH, "\"other\"")
+                .body(Default::default())
+                .unwrap();
+            let resp = send(router, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+        }
+
+        let router = router(ServeDir::new(&root).etag(ETag::Disabled));
+        let resp = send(router, get("/assets/index.html")).await;
+        assert!(!resp.headers().contains_key(header::ETAG));
+    }
+}
@@ -0,0 +1,190 @@
+use crate::response::IntoResponse;
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use http_body_util::{BodyExt, StreamBody};
+use hyper::body::Frame;
+use hyper::header::{self, HeaderValue};
+use std::fmt::Write;
+use std::time::Duration;
+
+/// A Server-Sent Events message, serialized as `event:`, `data:`, `id:` and
+/// `retry:` fields followed by a blank line.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Event {
+    event: Option<String>,
+    data: Option<String>,
+    id: Option<String>,
+    retry: Option<Duration>,
+}
+
+impl Event {
+    /// Sets the event type, dispatched to `addEventListener` listeners of
+    /// that type instead of `onmessage`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `event` contains a newline.
+    pub fn event(mut self, event: impl Into<String>) -> Self {
+        self.event = Some(single_line("event", event.into()));
+        self
+    }
+
+    /// Sets the data. Multi-line data is sent as one `data:` field per line,
+    /// splitting on `\r\n`, `\r` and `\n` like clients do.
+    pub fn data(mut self, data: impl Into<String>) -> Self {
+        self.data = Some(data.into());
+        self
+    }
+
+    /// Sets the event ID, sent back by reconnecting clients in
+    /// `Last-Event-ID`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` contains a newline.
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(single_line("id", id.into()));
+        self
+    }
+
+    /// Sets how long clients wait before reconnecting after the connection
+    /// drops.
+    pub fn retry(mut self, retry: Duration) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// Serializes the event into the wire format.
+    fn encode(&self) -> String {
+        let mut encoded = String::new();
+
+        if let Some(event) = &self.event {
+            writeln!(encoded, "event: {}", event).unwrap();
+        }
+
+        if let Some(data) = &self.data {
+            for line in data.split("\r\n").flat_map(|line| line.split(['\r', '\n'])) {
+                writeln!(encoded, "data: {}", line).unwrap();
+            }
+        }
+
+        if let Some(id) = &self.id {
+            writeln!(encoded, "id: {}", id).unwrap();
+        }
+
+        if let Some(retry) = self.retry {
+            writeln!(encoded, "retry: {}", retry.as_millis()).unwrap();
+        }
+
+        encoded.push('\n');
+        encoded
+    }
+}
+
+fn single_line(field: &str, value: String) -> String {
+    assert!(
+        !value.contains(['\n', '\r']),
+        "SSE `{}` field contains a newline",
+        field
+    );
+    value
+}
+
+/// A Server-Sent Events response (`text/event-stream`), sending each
+/// [`Event`] from a stream as it's produced.
+///
+/// # Example
+///
+/// ```
+/// use radmin_router::{Event, IntoResponse, Route, Sse, path};
+///
+/// Route::<()>::new(path!("/events")).get(|_, _| {
+///     let events = futures::stream::iter(vec![
+///         Event::default().event("greeting").data("hello"),
+///         Event::default().data("line one\nline two").id("2"),
+///     ]);
+///
+///     Box::pin(async move { Ok(Sse::new(events).into_response()) })
+/// });
+/// ```
+pub struct Sse<S> {
+    events: S,
+}
+
+impl<S> Sse<S>
+where
+    S: Stream<Item = Event> + Send + Sync + 'static,
+{
+    /// Creates a response sending `events`, ending when the stream does.
+    pub fn new(events: S) -> Self {
+        Self { events }
+    }
+}
+
+impl<S> IntoResponse for Sse<S>
+where
+    S: Stream<Item = Event> + Send + Sync + 'static,
+{
+    fn into_response(self) -> crate::Response {
+        let frames = self
+            .events
+            .map(|event| Ok::<_, crate::BodyError>(Frame::data(Bytes::from(event.encode()))));
+
+        let mut resp = hyper::Response::new(BodyExt::boxed(StreamBody::new(frames)));
+        let headers = resp.headers_mut();
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("text/event-stream"),
+        );
+        headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+        resp
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::send;
+    use crate::{Route, Router, path};
+
+    #[tokio::test]
+    async fn streams_events() {
+        let router = Router::<()>::default().register(Route::new(path!("/events")).get(|_, _| {
+            let events = futures::stream::iter(vec![
+                Event::default().event("update").data("first").id("1"),
+                Event::default()
+                    .data("two\nlines")
+                    .retry(Duration::from_secs(3)),
+            ]);
+
+            Box::pin(async move { Ok(Sse::new(events).into_response()) })
+        }));
+
+        let req = hyper::Request::get("/events")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(router, req).await;
+
+        assert_eq!(resp.headers()[header::CONTENT_TYPE], "text/event-stream");
+        assert_eq!(resp.headers()[header::CACHE_CONTROL], "no-cache");
+        assert_eq!(
+            std::str::from_utf8(resp.body()).unwrap(),
+            "event: update\ndata: first\nid: 1\n\ndata: two\ndata: lines\nretry: 3000\n\n"
+        );
+    }
+
+    #[test]
+    fn splits_data_on_every_line_ending() {
+        let event = Event::default().data("x\rid: evil\r\ny\nz");
+        assert_eq!(
+            event.encode(),
+            "data: x\ndata: id: evil\ndata: y\ndata: z\n\n"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "newline")]
+    fn rejects_multiline_id() {
+        let _ = Event::default().id("1\n2");
+    }
+}
@@ -0,0 +1,56 @@
+use crate::router::Router;
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use hyper::body::Incoming;
+use hyper::client::conn::http1 as client;
+use hyper::server::conn::http1 as server;
+use hyper::service::service_fn;
+use hyper::{Request, Response};
+use hyper_util::rt::TokioIo;
+use std::sync::Arc;
+use tokio::io::DuplexStream;
+
+/// Routes `req` through `router` over an in-memory connection and collects the response body.
+pub(crate) async fn send<Extra: Send + Sync + 'static>(
+    router: impl Into<Arc<Router<Extra>>>,
+    req: Request<Full<Bytes>>,
+) -> Response<Bytes> {
+    let router = router.into();
+    send_with(move |req| Router::route(Arc::clone(&router), req), req).await
+}
+
+/// Serves a single in-memory connection with `service`, returning the client end.
+pub(crate) fn serve<S, F>(service: S) -> DuplexStream
+where
+    S: Fn(Request<Incoming>) -> F + Send + 'static,
+    F: Future<Output = crate::Result> + Send + 'static,
+{
+    let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+
+    tokio::spawn(async move {
+        let svc = service_fn(service);
+        let _ = server::Builder::new()
+            .serve_connection(TokioIo::new(server_io), svc)
+            .with_upgrades()
+            .await;
+    });
+
+    client_io
+}
+
+/// Serves `req` with `service` over an in-memory connection and collects the response body.
+pub(crate) async fn send_with<S, F>(service: S, req: Request<Full<Bytes>>) -> Response<Bytes>
+where
+    S: Fn(Request<Incoming>) -> F + Send + 'static,
+    F: Future<Output = crate::Result> + Send + 'static,
+{
+    let client_io = serve(service);
+    let (mut sender, conn) = client::handshake(TokioIo::new(client_io)).await.unwrap();
+    tokio::spawn(conn);
+
+    let resp = sender.send_request(req).await.unwrap();
+    let (parts, body) = resp.into_parts();
+    let body = body.collect().await.unwrap().to_bytes();
+
+    Response::from_parts(parts, body)
+}
@@ -0,0 +1,127 @@
+use rustls::ServerConfig;
+use rustls::crypto::CryptoProvider;
+use rustls_pki_types::pem::PemObject;
+use rustls_pki_types::{CertificateDer, PrivateKeyDer};
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+pub use tokio_rustls::rustls;
+
+/// Loads a certificate chain and its private key from PEM files into a
+/// configuration for [`Router::serve_tls`](crate::Router::serve_tls).
+///
+/// `cert` holds the server's certificate followed by any intermediates.
+/// `key` holds a PKCS #8, PKCS #1 or SEC1 private key. Client certificates
+/// aren't requested.
+///
+/// Uses the process-wide default `CryptoProvider` if one is installed, and
+/// `ring` otherwise.
+pub fn load_tls_config(cert: impl AsRef<Path>, key: impl AsRef<Path>) -> io::Result<ServerConfig> {
+    let cert = cert.as_ref();
+    let chain = CertificateDer::pem_file_iter(cert)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(invalid_data)?;
+    if chain.is_empty() {
+        return Err(invalid_data(format!(
+            "no certificates in {}",
+            cert.display()
+        )));
+    }
+
+    let key = PrivateKeyDer::from_pem_file(key).map_err(invalid_data)?;
+
+    let provider = CryptoProvider::get_default()
+        .cloned()
+        .unwrap_or_else(|| Arc::new(rustls::crypto::ring::default_provider()));
+    ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(invalid_data)?
+        .with_no_client_auth()
+        .with_single_cert(chain, key)
+        .map_err(invalid_data)
+}
+
+fn invalid_data(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Route, Router, full, path};
+    use futures::FutureExt;
+    use futures::channel::oneshot;
+    use http_body_util::BodyExt;
+    use hyper::client::conn::http1;
+    use hyper_util::rt::TokioIo;
+    use rustls::pki_types::ServerName;
+    use rustls::{ClientConfig, RootCertStore};
+    use std::time::Duration;
+    use tokio::net::TcpStream;
+    use tokio_rustls::TlsConnector;
+
+    const CERT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/tls/cert.pem");
+    const KEY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/tls/key.pem");
+
+    #[tokio::test]
+    async fn serves_https() {
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let router =
+            Router::<()>::default().register(Route::new(path!("/users/[id]")).get(|_, ctx| {
+                let body = format!("{} {}", ctx.is_secure(), ctx.params[0]);
+                Box::pin(async move { Ok(hyper::Response::new(full(body))) })
+            }));
+        let config = load_tls_config(CERT, KEY).unwrap();
+        let (stop, stopped) = oneshot::channel::<()>();
+        let server =
+            tokio::spawn(Arc::new(router).serve_tls_with_shutdown(addr, config, stopped.map(drop)));
+
+        let mut roots = RootCertStore::empty();
+        for cert in CertificateDer::pem_file_iter(CERT).unwrap() {
+            roots.add(cert.unwrap()).unwrap();
+        }
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let mut client = ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        client.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+        let stream = loop {
+            match TcpStream::connect(addr).await {
+                Ok(stream) => break stream,
+                Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        };
+        let stream = TlsConnector::from(Arc::new(client))
+            .connect(ServerName::try_from("localhost").unwrap(), stream)
+            .await
+            .unwrap();
+        assert_eq!(stream.get_ref().1.alpn_protocol(), Some(&b"http/1.1"[..]));
+
+        let (mut sender, conn) = http1::handshake(TokioIo::new(stream)).await.unwrap();
+        tokio::spawn(conn);
+        let req = hyper::Request::get("/users/42")
+            .header(hyper::header::HOST, "localhost")
+            .body(String::new())
+            .unwrap();
+        let resp = sender.send_request(req).await.unwrap();
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body.as_ref(), b"true 42");
+        drop(sender);
+
+        stop.send(()).unwrap();
+        server.await.unwrap().unwrap();
+    }
+
+    #[test]
+    fn rejects_missing_certificates() {
+        let err = load_tls_config(KEY, KEY).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
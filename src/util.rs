@@ -1,6 +1,13 @@
+use crate::hash::Fnv1a;
 use bytes::Bytes;
+use futures::{Stream, TryStreamExt};
 use http_body_util::combinators::BoxBody;
-use http_body_util::{BodyExt, Empty, Full};
+use http_body_util::{BodyExt, StreamBody};
+use hyper::StatusCode;
+use hyper::body::Frame;
+use hyper::header::{self, HeaderValue};
+use std::fmt::{self, Display, Formatter};
+use std::time::Duration;
 
 /// Creates a `BoxBody` containing nothing.
 /// 
@@ -14,10 +21,8 @@ use http_body_util::{BodyExt, Empty, Full};
 ///     .status(204)
 ///     .body(empty())
 ///     .unwrap();
-pub fn empty() -> BoxBody<Bytes, hyper::Error> {
-    Empty::new()
-        .map_err(|e| match e {})
-        .boxed()
+pub fn empty() -> BoxBody<Bytes, crate::BodyError> {
+    crate::body::empty()
 }
 
 /// Creates a `BoxBody` from a `Bytes`-convertible body.
@@ -32,8 +37,530 @@ pub fn empty() -> BoxBody<Bytes, hyper::Error> {
 ///     .status(200)
 ///     .body(full("OK"))
 ///     .unwrap();
-pub fn full<I: Into<Bytes>>(body: I) -> BoxBody<Bytes, hyper::Error> {
-    Full::new(body.into())
-        .map_err(|e| match e {})
-        .boxed()
-}
\ No newline at end of file
+pub fn full<I: Into<Bytes>>(body: I) -> BoxBody<Bytes, crate::BodyError> {
+    crate::body::full(body)
+}
+
+/// Creates a `BoxBody` streaming the chunks produced by `stream`, sent with
+/// chunked transfer encoding as they become available. Errors such as
+/// `io::Error`s convert into the stream's [`BodyError`](crate::BodyError)
+/// with `Into`.
+/// 
+/// # Example
+/// 
+/// ```
+/// use bytes::Bytes;
+/// use hyper::Response;
+/// use radmin_router::stream;
+///
+/// let rows = ["id,name\n", "1,a\n", "2,b\n"].map(|row| Ok(Bytes::from(row)));
+///
+/// Response::builder()
+///     .header("Content-Type", "text/csv")
+///     .body(stream(futures::stream::iter(rows)))
+///     .unwrap();
+/// ```
+pub fn stream<S>(stream: S) -> BoxBody<Bytes, crate::BodyError>
+where
+    S: Stream<Item = Result<Bytes, crate::BodyError>> + Send + Sync + 'static,
+{
+    StreamBody::new(stream.map_ok(Frame::data)).boxed()
+}
+
+/// Creates a `302 Found` response redirecting to `location`. Clients may
+/// change the method of the redirected request to GET.
+///
+/// # Panics
+///
+/// Panics if `location` is empty or isn't a valid header value. The same
+/// goes for the other redirect helpers.
+///
+/// # Example
+///
+/// ```
+/// use radmin_router::{Route, path, redirect};
+///
+/// Route::<()>::new(path!("/old")).get(|_, _| {
+///     Box::pin(async { Ok(redirect("/new")) })
+/// });
+/// ```
+pub fn redirect(location: &str) -> crate::Response {
+    redirect_with(StatusCode::FOUND, location)
+}
+
+/// Creates a `301 Moved Permanently` response redirecting to `location`.
+pub fn redirect_permanent(location: &str) -> crate::Response {
+    redirect_with(StatusCode::MOVED_PERMANENTLY, location)
+}
+
+/// Creates a `303 See Other` response redirecting to `location`, fetched
+/// with GET, e.g. after handling a form submission.
+pub fn redirect_see_other(location: &str) -> crate::Response {
+    redirect_with(StatusCode::SEE_OTHER, location)
+}
+
+/// Creates a `307 Temporary Redirect` response redirecting to `location`,
+/// repeating the request with the same method and body.
+pub fn redirect_temporary(location: &str) -> crate::Response {
+    redirect_with(StatusCode::TEMPORARY_REDIRECT, location)
+}
+
+/// Creates a `308 Permanent Redirect` response redirecting to `location`,
+/// repeating the request with the same method and body.
+pub fn redirect_permanent_preserving(location: &str) -> crate::Response {
+    redirect_with(StatusCode::PERMANENT_REDIRECT, location)
+}
+
+fn redirect_with(status: StatusCode, location: &str) -> crate::Response {
+    assert!(!location.is_empty(), "redirect location is empty");
+    let location = HeaderValue::from_str(location).expect("invalid redirect location");
+
+    hyper::Response::builder()
+        .status(status)
+        .header(header::LOCATION, location)
+        .body(empty())
+        .unwrap()
+}
+
+/// Sets the `ETag` of `resp` to `etag`, quoting it unless it's already a
+/// quoted or weak (`W/"..."`) tag.
+///
+/// Pair with the [`conditional_get`](crate::conditional_get) middleware to
+/// answer requests for unchanged resources with `304 Not Modified`.
+///
+/// # Panics
+///
+/// Panics if `etag` contains characters not allowed in a header value.
+///
+/// # Example
+///
+/// ```
+/// use radmin_router::{full, with_etag};
+///
+/// let resp = with_etag(hyper::Response::new(full("hello")), "v1");
+/// assert_eq!(resp.headers()["ETag"], "\"v1\"");
+/// ```
+pub fn with_etag(mut resp: crate::Response, etag: &str) -> crate::Response {
+    let quoted = etag.strip_prefix("W/").unwrap_or(etag);
+    let etag = if quoted.len() >= 2 && quoted.starts_with('"') && quoted.ends_with('"') {
+        HeaderValue::from_str(etag)
+    } else {
+        HeaderValue::from_str(&format!("\"{}\"", etag))
+    };
+
+    resp.headers_mut()
+        .insert(header::ETAG, etag.expect("invalid ETag"));
+    resp
+}
+
+/// Creates a `200 OK` response serving an embedded asset, e.g. a favicon
+/// included with `include_bytes!`, with its `Content-Type`,
+/// `Content-Length` and a strong `ETag` hashed from `bytes`.
+///
+/// [`Route::serve_static`](crate::Route::serve_static) registers such a
+/// response, hashing `bytes` once and answering matching `If-None-Match`
+/// requests with `304 Not Modified`.
+///
+/// # Panics
+///
+/// Panics if `content_type` isn't a valid header value.
+///
+/// # Example
+///
+/// ```
+/// use radmin_router::static_file;
+///
+/// let resp = static_file(b"<!doctype html>", "text/html; charset=utf-8");
+/// assert_eq!(resp.headers()["Content-Length"], "15");
+/// ```
+pub fn static_file(bytes: &'static [u8], content_type: &str) -> crate::Response {
+    let content_type = HeaderValue::from_str(content_type).expect("invalid Content-Type");
+    static_response(bytes, content_type, static_etag(bytes))
+}
+
+/// Returns the strong `ETag` of an embedded asset.
+pub(crate) fn static_etag(bytes: &[u8]) -> HeaderValue {
+    let mut hash = Fnv1a::default();
+    hash.write(bytes);
+    HeaderValue::from_str(&format!("\"{:016x}\"", hash.0)).unwrap()
+}
+
+/// Builds the response of [`static_file`] from its precomputed headers.
+pub(crate) fn static_response(
+    bytes: &'static [u8],
+    content_type: HeaderValue,
+    etag: HeaderValue,
+) -> crate::Response {
+    hyper::Response::builder()
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::CONTENT_LENGTH, bytes.len())
+        .header(header::ETAG, etag)
+        .body(full(Bytes::from_static(bytes)))
+        .unwrap()
+}
+
+/// Starts a `Set-Cookie` header value setting the cookie `name` to `value`.
+///
+/// # Panics
+///
+/// Panics if `name` isn't a token, or if `value` contains whitespace, double
+/// quotes, commas, semicolons, backslashes or control characters. Encode
+/// such values first, e.g. with percent-encoding.
+///
+/// # Example
+///
+/// ```
+/// use hyper::Response;
+/// use hyper::header::SET_COOKIE;
+/// use radmin_router::{SameSite, empty, set_cookie};
+/// use std::time::Duration;
+///
+/// let cookie = set_cookie("session", "8f4e2a")
+///     .http_only()
+///     .secure()
+///     .same_site(SameSite::Lax)
+///     .max_age(Duration::from_secs(3600))
+///     .path("/");
+///
+/// Response::builder()
+///     .header(SET_COOKIE, cookie)
+///     .body(empty())
+///     .unwrap();
+/// ```
+pub fn set_cookie(name: impl Into<String>, value: impl Into<String>) -> SetCookie {
+    let name = name.into();
+    let value = value.into();
+    assert!(
+        !name.is_empty() && name.bytes().all(is_token_byte),
+        "invalid cookie name `{}`",
+        name
+    );
+    assert!(
+        value.bytes().all(is_cookie_octet),
+        "invalid cookie value `{}`",
+        value
+    );
+
+    SetCookie {
+        name,
+        value,
+        http_only: false,
+        secure: false,
+        same_site: None,
+        max_age: None,
+        path: None,
+    }
+}
+
+/// A `Set-Cookie` header value, started with [`set_cookie`]. Converts into a
+/// `HeaderValue`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SetCookie {
+    name: String,
+    value: String,
+    http_only: bool,
+    secure: bool,
+    same_site: Option<SameSite>,
+    max_age: Option<Duration>,
+    path: Option<String>,
+}
+
+impl SetCookie {
+    /// Hides the cookie from scripts.
+    pub fn http_only(mut self) -> Self {
+        self.http_only = true;
+        self
+    }
+
+    /// Only sends the cookie over HTTPS.
+    pub fn secure(mut self) -> Self {
+        self.secure = true;
+        self
+    }
+
+    /// Sets whether the cookie is sent with cross-site requests.
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = Some(same_site);
+        self
+    }
+
+    /// Sets how long until the cookie expires. Zero deletes it.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Only sends the cookie with requests to `path` and below.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `path` contains a semicolon or control characters.
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        let path = path.into();
+        assert!(
+            path.bytes().all(|b| b != b';' && !b.is_ascii_control()),
+            "invalid cookie path `{}`",
+            path
+        );
+        self.path = Some(path);
+        self
+    }
+}
+
+impl Display for SetCookie {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.name, self.value)?;
+
+        if let Some(max_age) = self.max_age {
+            write!(f, "; Max-Age={}", max_age.as_secs())?;
+        }
+
+        if let Some(path) = &self.path {
+            write!(f, "; Path={}", path)?;
+        }
+
+        if let Some(same_site) = self.same_site {
+            write!(f, "; SameSite={}", same_site)?;
+        }
+
+        if self.secure {
+            f.write_str("; Secure")?;
+        }
+
+        if self.http_only {
+            f.write_str("; HttpOnly")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl From<SetCookie> for HeaderValue {
+    fn from(cookie: SetCookie) -> Self {
+        // Names, values and paths are checked to be visible ASCII.
+        HeaderValue::from_str(&cookie.to_string()).unwrap()
+    }
+}
+
+/// When a cookie is sent with cross-site requests, set with
+/// [`SetCookie::same_site`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SameSite {
+    /// Only with same-site requests.
+    Strict,
+    /// Also with top-level navigations from other sites.
+    Lax,
+    /// With all requests. Browsers require the cookie to be `Secure`.
+    None,
+}
+
+impl Display for SameSite {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Strict => "Strict",
+            Self::Lax => "Lax",
+            Self::None => "None",
+        })
+    }
+}
+
+fn is_token_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+}
+
+fn is_cookie_octet(b: u8) -> bool {
+    b.is_ascii_graphic() && !matches!(b, b'"' | b',' | b';' | b'\\')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn collects_streamed_chunks() {
+        let chunks = ["one ", "two ", "three"].map(|chunk| Ok(Bytes::from(chunk)));
+        let body = stream(futures::stream::iter(chunks))
+            .collect()
+            .await
+            .unwrap()
+            .to_bytes();
+
+        assert_eq!(body.as_ref(), b"one two three");
+    }
+
+    #[tokio::test]
+    async fn streams_bodies_failing_with_io_errors() {
+        use crate::testing::serve;
+        use crate::{Route, Router, path};
+        use futures::StreamExt;
+        use hyper::Request;
+        use hyper_util::rt::TokioIo;
+        use std::io;
+
+        let router = Arc::new(
+            Router::<()>::default().register(Route::new(path!("/file")).get(|_, _| {
+                let chunks = futures::stream::iter([Ok(Bytes::from_static(b"first chunk"))]).chain(
+                    futures::stream::once(async {
+                        tokio::time::sleep(Duration::from_millis(10)).await;
+                        Err(io::Error::other("disk failure").into())
+                    }),
+                );
+                Box::pin(async move { Ok(hyper::Response::new(stream(chunks))) })
+            })),
+        );
+        let io = serve(move |req| Router::route(Arc::clone(&router), req));
+        let (mut sender, conn) = hyper::client::conn::http1::handshake(TokioIo::new(io))
+            .await
+            .unwrap();
+        tokio::spawn(conn);
+
+        let req = Request::get("/file").body(String::new()).unwrap();
+        let resp = sender.send_request(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // The server aborts the response once the body fails.
+        let mut body = resp.into_body();
+        let frame = body.frame().await.unwrap().unwrap();
+        assert_eq!(frame.into_data().unwrap().as_ref(), b"first chunk");
+        assert!(body.collect().await.is_err());
+
+        let chunks = futures::stream::iter([Err(io::Error::other("disk failure").into())]);
+        let err = stream(chunks).collect().await.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<io::Error>().unwrap().kind(),
+            io::ErrorKind::Other
+        );
+    }
+
+    #[tokio::test]
+    async fn serves_static_files() {
+        use crate::testing::send;
+        use crate::{Route, Router, path};
+        use hyper::Request;
+
+        const INDEX: &[u8] = b"<!doctype html><title>App</title>";
+
+        let resp = static_file(INDEX, "text/html");
+        let etag = resp.headers()[header::ETAG].clone();
+        assert_eq!(
+            static_file(INDEX, "text/html").headers()[header::ETAG],
+            etag
+        );
+        assert_ne!(
+            static_file(b"other", "text/html").headers()[header::ETAG],
+            etag
+        );
+
+        let router = Arc::new(
+            Router::<()>::default()
+                .register(Route::new(path!("/")).serve_static(INDEX, "text/html; charset=utf-8")),
+        );
+        let req = Request::get("/").body(Default::default()).unwrap();
+        let resp = send(Arc::clone(&router), req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.body().as_ref(), INDEX);
+        assert_eq!(
+            resp.headers()[header::CONTENT_TYPE],
+            "text/html; charset=utf-8"
+        );
+        assert_eq!(
+            resp.headers()[header::CONTENT_LENGTH],
+            INDEX.len().to_string()
+        );
+        assert_eq!(resp.headers()[header::ETAG], etag);
+
+        let req = Request::get("/")
+            .header(header::IF_NONE_MATCH, etag.clone())
+            .body(Default::default())
+            .unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+        assert!(resp.body().is_empty());
+    }
+
+    #[tokio::test]
+    async fn set_cookie_round_trip() {
+        use crate::testing::send;
+        use crate::{Route, Router, path};
+        use hyper::header::{COOKIE, SET_COOKIE};
+
+        let cookie = set_cookie("session", "8f4e2a")
+            .http_only()
+            .secure()
+            .same_site(SameSite::Strict)
+            .max_age(Duration::from_secs(3600))
+            .path("/admin");
+        assert_eq!(
+            HeaderValue::from(cookie.clone()),
+            "session=8f4e2a; Max-Age=3600; Path=/admin; SameSite=Strict; Secure; HttpOnly"
+        );
+        assert_eq!(set_cookie("theme", "").to_string(), "theme=");
+
+        let router = Arc::new(
+            Router::<()>::default().register(
+                Route::new(path!("/admin"))
+                    .post(move |_, _| {
+                        let resp = hyper::Response::builder()
+                            .header(SET_COOKIE, cookie.clone())
+                            .body(empty())
+                            .unwrap();
+                        Box::pin(async move { Ok(resp) })
+                    })
+                    .get(|_, ctx| {
+                        let session = ctx.cookies().remove("session").unwrap_or_default();
+                        Box::pin(async move { Ok(hyper::Response::new(full(session))) })
+                    }),
+            ),
+        );
+
+        let req = hyper::Request::post("/admin")
+            .body(Default::default())
+            .unwrap();
+        let resp = send(Arc::clone(&router), req).await;
+        let set_cookie = resp.headers()[SET_COOKIE].to_str().unwrap();
+        let pair = set_cookie.split(';').next().unwrap();
+
+        let req = hyper::Request::get("/admin")
+            .header(COOKIE, format!("theme=dark; {}", pair))
+            .body(Default::default())
+            .unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.body().as_ref(), b"8f4e2a");
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid cookie value")]
+    fn rejects_cookie_values_with_semicolons() {
+        let _ = set_cookie("session", "a; Path=/");
+    }
+
+    #[tokio::test]
+    async fn redirects() {
+        let location = "/login?next=%2Fadmin";
+        let cases = [
+            (redirect(location), StatusCode::FOUND),
+            (redirect_permanent(location), StatusCode::MOVED_PERMANENTLY),
+            (redirect_see_other(location), StatusCode::SEE_OTHER),
+            (redirect_temporary(location), StatusCode::TEMPORARY_REDIRECT),
+            (
+                redirect_permanent_preserving(location),
+                StatusCode::PERMANENT_REDIRECT,
+            ),
+        ];
+
+        for (resp, status) in cases {
+            assert_eq!(resp.status(), status);
+            assert_eq!(resp.headers()[header::LOCATION], location);
+
+            let body = resp.into_body().collect().await.unwrap().to_bytes();
+            assert!(body.is_empty());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "redirect location is empty")]
+    fn rejects_empty_redirects() {
+        let _ = redirect_see_other("");
+    }
+}
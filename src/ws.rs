@@ -0,0 +1,252 @@
+use crate::body::{empty, full};
+use crate::response::IntoResponse;
+use hyper::body::Incoming;
+use hyper::header::{self, HeaderMap, HeaderName, HeaderValue};
+use hyper::upgrade::{OnUpgrade, Upgraded};
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use tokio_tungstenite::WebSocketStream;
+use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
+use tokio_tungstenite::tungstenite::protocol::Role;
+
+pub use tokio_tungstenite::tungstenite;
+
+/// An upgraded WebSocket connection, read from as a `Stream` and written to
+/// as a `Sink` of [`tungstenite::Message`]s.
+pub type WebSocket = WebSocketStream<TokioIo<Upgraded>>;
+
+/// A validated request to upgrade to a WebSocket connection.
+///
+/// # Example
+///
+/// ```
+/// use futures::{SinkExt, StreamExt};
+/// use radmin_router::{IntoResponse, Route, WebSocketUpgrade, path};
+///
+/// Route::<()>::new(path!("/echo")).get(|mut req, _| {
+///     let resp = match WebSocketUpgrade::from_request(&mut req) {
+///         Ok(upgrade) => upgrade.on_upgrade(|mut ws| async move {
+///             while let Some(Ok(message)) = ws.next().await {
+///                 if ws.send(message).await.is_err() {
+///                     break;
+///                 }
+///             }
+///         }),
+///         Err(err) => err.into_response(),
+///     };
+///
+///     Box::pin(async move { Ok(resp) })
+/// });
+/// ```
+pub struct WebSocketUpgrade {
+    accept: HeaderValue,
+    on_upgrade: OnUpgrade,
+}
+
+impl WebSocketUpgrade {
+    /// Validates the opening handshake of `req`.
+    pub fn from_request(req: &mut Request<Incoming>) -> Result<Self, WebSocketError> {
+        if req.method() != Method::GET {
+            return Err(WebSocketError::MethodNotGet);
+        }
+
+        let headers = req.headers();
+        if !has_token(headers, header::CONNECTION, "upgrade")
+            || !has_token(headers, header::UPGRADE, "websocket")
+        {
+            return Err(WebSocketError::NotUpgrade);
+        }
+
+        if headers
+            .get(header::SEC_WEBSOCKET_VERSION)
+            .is_none_or(|version| version != "13")
+        {
+            return Err(WebSocketError::UnsupportedVersion);
+        }
+
+        // The key is 16 random bytes, base64-encoded.
+        let key = headers
+            .get(header::SEC_WEBSOCKET_KEY)
+            .filter(|key| {
+                let key = key.as_bytes();
+                key.len() == 24
+                    && key.ends_with(b"==")
+                    && key[..22]
+                        .iter()
+                        .all(|c| c.is_ascii_alphanumeric() || *c == b'+' || *c == b'/')
+            })
+            .ok_or(WebSocketError::InvalidKey)?;
+        let accept = HeaderValue::from_str(&derive_accept_key(key.as_bytes())).unwrap();
+
+        Ok(Self {
+            accept,
+            on_upgrade: hyper::upgrade::on(req),
+        })
+    }
+
+    /// Returns the `101 Switching Protocols` response completing the
+    /// handshake, and runs `callback` with the connection on a new task once
+    /// it's upgraded.
+    ///
+    /// The server must serve connections with upgrades enabled, as
+    /// [`Router::serve`](crate::Router::serve) does.
+    pub fn on_upgrade<F, Fut>(self, callback: F) -> crate::Response
+    where
+        F: FnOnce(WebSocket) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let on_upgrade = self.on_upgrade;
+        tokio::spawn(async move {
+            let Ok(upgraded) = on_upgrade.await else {
+                return;
+            };
+
+            let ws =
+                WebSocketStream::from_raw_socket(TokioIo::new(upgraded), Role::Server, None).await;
+            callback(ws).await;
+        });
+
+        let mut resp = Response::new(empty());
+        *resp.status_mut() = StatusCode::SWITCHING_PROTOCOLS;
+        let headers = resp.headers_mut();
+        headers.insert(header::CONNECTION, HeaderValue::from_static("upgrade"));
+        headers.insert(header::UPGRADE, HeaderValue::from_static("websocket"));
+        headers.insert(header::SEC_WEBSOCKET_ACCEPT, self.accept);
+        resp
+    }
+}
+
+/// Returns whether the comma-separated header `name` contains `token`,
+/// ignoring case.
+fn has_token(headers: &HeaderMap, name: HeaderName, token: &str) -> bool {
+    headers
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|value| value.trim().eq_ignore_ascii_case(token))
+}
+
+/// An invalid WebSocket opening handshake, rejected by
+/// [`WebSocketUpgrade::from_request`].
+///
+/// Responds with `426 Upgrade Required` for unsupported protocol versions,
+/// `405 Method Not Allowed` for methods other than GET, and
+/// `400 Bad Request` otherwise.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WebSocketError {
+    /// The request method isn't GET.
+    MethodNotGet,
+    /// The request doesn't ask to upgrade to `websocket`.
+    NotUpgrade,
+    /// `Sec-WebSocket-Version` is missing or not 13.
+    UnsupportedVersion,
+    /// `Sec-WebSocket-Key` is missing or malformed.
+    InvalidKey,
+}
+
+impl Display for WebSocketError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::MethodNotGet => "WebSocket handshakes must use GET",
+            Self::NotUpgrade => "request is not a WebSocket upgrade",
+            Self::UnsupportedVersion => "unsupported WebSocket version",
+            Self::InvalidKey => "invalid Sec-WebSocket-Key",
+        })
+    }
+}
+
+impl Error for WebSocketError {}
+
+impl IntoResponse for WebSocketError {
+    fn into_response(self) -> crate::Response {
+        let status = match self {
+            Self::MethodNotGet => StatusCode::METHOD_NOT_ALLOWED,
+            Self::UnsupportedVersion => StatusCode::UPGRADE_REQUIRED,
+            Self::NotUpgrade | Self::InvalidKey => StatusCode::BAD_REQUEST,
+        };
+
+        let mut resp = Response::new(full(self.to_string()));
+        *resp.status_mut() = status;
+        if self == Self::UnsupportedVersion {
+            resp.headers_mut().insert(
+                header::SEC_WEBSOCKET_VERSION,
+                HeaderValue::from_static("13"),
+            );
+        }
+        resp
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{send, serve};
+    use crate::{Route, Router, path};
+    use futures::{SinkExt, StreamExt};
+    use std::sync::Arc;
+    use tungstenite::Message;
+
+    fn router() -> Arc<Router<()>> {
+        Arc::new(
+            Router::<()>::default().register(Route::new(path!("/echo")).get(|mut req, _| {
+                let resp = match WebSocketUpgrade::from_request(&mut req) {
+                    Ok(upgrade) => upgrade.on_upgrade(|mut ws| async move {
+                        if let Some(Ok(message)) = ws.next().await {
+                            ws.send(message).await.unwrap();
+                        }
+                    }),
+                    Err(err) => err.into_response(),
+                };
+
+                Box::pin(async move { Ok(resp) })
+            })),
+        )
+    }
+
+    #[tokio::test]
+    async fn echoes_text_frame() {
+        let router = router();
+        let io = serve(move |req| Router::route(Arc::clone(&router), req));
+
+        let (mut ws, resp) = tokio_tungstenite::client_async("ws://localhost/echo", io)
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::SWITCHING_PROTOCOLS);
+
+        ws.send(Message::text("hello")).await.unwrap();
+        let echoed = ws.next().await.unwrap().unwrap();
+        assert_eq!(echoed, Message::text("hello"));
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_handshakes() {
+        let router = router();
+        let handshake = |key: &str, version: &str| {
+            Request::get("/echo")
+                .header(header::CONNECTION, "keep-alive, Upgrade")
+                .header(header::UPGRADE, "websocket")
+                .header(header::SEC_WEBSOCKET_VERSION, version)
+                .header(header::SEC_WEBSOCKET_KEY, key)
+                .body(Default::default())
+                .unwrap()
+        };
+
+        let resp = send(
+            Arc::clone(&router),
+            handshake("dGhlIHNhbXBsZSBub25jZQ==", "8"),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::UPGRADE_REQUIRED);
+        assert_eq!(resp.headers()[header::SEC_WEBSOCKET_VERSION], "13");
+
+        let resp = send(Arc::clone(&router), handshake("not a key", "13")).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let req = Request::get("/echo").body(Default::default()).unwrap();
+        let resp = send(router, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,8 @@
+#[test]
+fn box_future() {
+    let cases = trybuild::TestCases::new();
+    cases.pass("tests/ui/box_future_local.rs");
+    cases.pass("tests/ui/box_future_generic.rs");
+    cases.pass("tests/ui/box_future_borrow.rs");
+    cases.compile_fail("tests/ui/box_future_not_send.rs");
+}
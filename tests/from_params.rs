@@ -0,0 +1,6 @@
+#[test]
+fn from_params() {
+    let cases = trybuild::TestCases::new();
+    cases.pass("tests/ui/from_params.rs");
+    cases.compile_fail("tests/ui/from_params_unknown_param.rs");
+}
@@ -0,0 +1,59 @@
+use std::path::Path;
+use std::process::Command;
+
+/// Checks the library with `default-features = false`, which the other tests
+/// can't: the dev-dependency on this crate enables every feature for them.
+#[test]
+fn builds_without_features() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let cargo = || {
+        let mut command = Command::new(env!("CARGO"));
+        command
+            .current_dir(manifest_dir)
+            // A target directory of its own keeps this from waiting on the
+            // lock held by the `cargo test` running it.
+            .env("CARGO_TARGET_DIR", manifest_dir.join("target/minimal"));
+        command
+    };
+
+    let output = cargo()
+        .args(["check", "--lib", "--no-default-features", "--quiet"])
+        .env("RUSTFLAGS", "-D warnings")
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let output = cargo()
+        .args([
+            "tree",
+            "--no-default-features",
+            "--edges",
+            "normal",
+            "--depth",
+            "1",
+            "--prefix",
+            "none",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let tree = String::from_utf8_lossy(&output.stdout);
+    // Dependencies of dependencies may still use some, such as `tracing`.
+    for optional in [
+        "tracing ",
+        "serde ",
+        "tokio-rustls ",
+        "tokio-tungstenite ",
+        "uuid ",
+    ] {
+        assert!(
+            !tree.lines().any(|line| line.starts_with(optional)),
+            "`{}` is a dependency without features",
+            optional.trim_end()
+        );
+    }
+}
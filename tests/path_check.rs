@@ -0,0 +1,42 @@
+use radmin_router::Path;
+use std::str::FromStr;
+
+// The macros can't be called with a runtime string, so include their check
+// directly.
+#[path = "../macros/macro_impl/src/check_path.rs"]
+mod check_path;
+
+/// Runs paths through both the compile-time check of the `routes` and
+/// `FromParams` macros and `Path::from_str`, so that they can't drift apart.
+#[test]
+fn macro_check_matches_from_str() {
+    let malformed = [
+        "/files/[...path]/raw",
+        "/users/[id:float]",
+        "/[a][b]",
+        "/posts/[page?]/comments",
+        "/users/[id",
+        "/users/id]",
+        "/users/[a[b]]",
+        "/users/[]",
+        "/files/[...]",
+        "/archive/[year?]/[]",
+    ];
+    let valid = [
+        "/",
+        "/users/[id:int]/posts/[slug]",
+        "/files/[name].[ext]",
+        "/docs/\\[draft\\]",
+        "/archive/[year?]/[month?]",
+        "/assets/[...path]",
+    ];
+
+    for path in malformed {
+        let err = Path::from_str(path).unwrap_err().to_string();
+        assert_eq!(check_path::check_path(path), Err(err), "{}", path);
+    }
+    for path in valid {
+        assert!(Path::from_str(path).is_ok(), "{}", path);
+        assert_eq!(check_path::check_path(path), Ok(()), "{}", path);
+    }
+}
@@ -0,0 +1,8 @@
+#[test]
+fn routes() {
+    let cases = trybuild::TestCases::new();
+    cases.pass("tests/ui/routes.rs");
+    cases.pass("tests/ui/routes_state.rs");
+    cases.compile_fail("tests/ui/routes_unknown_verb.rs");
+    cases.compile_fail("tests/ui/routes_malformed_path.rs");
+}
@@ -0,0 +1,29 @@
+use radmin_router::macros::box_future;
+
+struct Greeter {
+    greeting: String,
+}
+
+impl Greeter {
+    #[box_future]
+    async fn greet(&self, name: &str) -> String {
+        std::future::ready(()).await;
+        format!("{}, {}", self.greeting, name)
+    }
+
+    #[box_future]
+    async fn first<'a>(&self, names: &'a [String]) -> Option<&'a str> {
+        std::future::ready(()).await;
+        names.first().map(String::as_str)
+    }
+}
+
+fn main() {
+    let greeter = Greeter {
+        greeting: String::from("hello"),
+    };
+    let _ = greeter.greet("radmin");
+
+    let names = vec![String::from("radmin")];
+    let _ = greeter.first(&names);
+}
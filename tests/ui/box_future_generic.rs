@@ -0,0 +1,25 @@
+use radmin_router::macros::box_future;
+use std::fmt::Display;
+
+#[box_future]
+async fn describe<T: Display + Send>(value: T) -> String {
+    std::future::ready(()).await;
+    value.to_string()
+}
+
+#[box_future]
+async fn describe_all<'a, T>(values: &'a [T], sep: impl AsRef<str> + Send) -> String
+where
+    T: Display + Sync,
+{
+    let parts: Vec<_> = values.iter().map(ToString::to_string).collect();
+    std::future::ready(()).await;
+    parts.join(sep.as_ref())
+}
+
+fn main() {
+    let _ = describe(1);
+
+    let values = vec![1, 2];
+    let _ = describe_all(&values, ", ");
+}
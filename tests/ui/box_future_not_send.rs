@@ -0,0 +1,13 @@
+use radmin_router::macros::box_future;
+use std::rc::Rc;
+
+#[box_future]
+async fn not_send() -> usize {
+    let count = Rc::new(1);
+    std::future::ready(()).await;
+    *count
+}
+
+fn main() {
+    let _ = not_send();
+}
@@ -0,0 +1,47 @@
+use radmin_router::macros::FromParams;
+use radmin_router::{Context, FromParams as _, ParamError};
+use std::sync::Arc;
+
+#[derive(Debug, PartialEq, FromParams)]
+#[params("/users/[id:int]/posts/[slug]/[page?]")]
+struct PostParams {
+    id: u64,
+    slug: String,
+    page: Option<u32>,
+}
+
+#[derive(Debug, PartialEq, FromParams)]
+struct Unchecked {
+    r#type: String,
+}
+
+fn main() {
+    let ctx = Context::builder(Arc::new(()))
+        .param("id", "42")
+        .param("slug", "hello")
+        .build();
+    assert_eq!(
+        PostParams::from_context(&ctx),
+        Ok(PostParams {
+            id: 42,
+            slug: "hello".into(),
+            page: None,
+        })
+    );
+
+    let ctx = Context::builder(Arc::new(()))
+        .param("id", "42")
+        .param("slug", "hello")
+        .param("page", "two")
+        .build();
+    assert!(matches!(
+        PostParams::from_context(&ctx),
+        Err(ParamError::Invalid { .. })
+    ));
+
+    let ctx = Context::builder(Arc::new(())).build();
+    assert_eq!(
+        Unchecked::from_context(&ctx),
+        Err(ParamError::Missing("type".into()))
+    );
+}
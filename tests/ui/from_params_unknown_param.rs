@@ -0,0 +1,10 @@
+use radmin_router::macros::FromParams;
+
+#[derive(FromParams)]
+#[params("/users/[id]")]
+struct UserParams {
+    id: u64,
+    slug: String,
+}
+
+fn main() {}
@@ -0,0 +1,44 @@
+use hyper::body::Incoming;
+use hyper::{Method, Request};
+use radmin_router::macros::routes;
+use radmin_router::{Context, full};
+use std::sync::Arc;
+
+struct Users {
+    greeting: &'static str,
+}
+
+#[routes]
+impl Users {
+    #[get("/users")]
+    async fn list(&self, _: Request<Incoming>, _: Context<()>) -> radmin_router::Result {
+        Ok(hyper::Response::new(full(self.greeting)))
+    }
+
+    /// Creates a user.
+    #[post("/users")]
+    async fn create(_: Request<Incoming>, _: Context<()>) -> radmin_router::Result {
+        Ok(hyper::Response::new(full("created")))
+    }
+
+    #[get("/users/[id:int]")]
+    #[delete("/users/[id:int]")]
+    async fn user(&self, _: Request<Incoming>, ctx: Context<()>) -> radmin_router::Result {
+        Ok(hyper::Response::new(full(ctx.params[0].clone())))
+    }
+
+    #[allow(dead_code)]
+    fn helper(&self) {}
+}
+
+fn main() {
+    let router = Users { greeting: "hello" }.into_router(Arc::new(()));
+
+    let (route, _) = router.recognize("/users").unwrap();
+    assert_eq!(route.allowed_methods(), [Method::GET, Method::POST]);
+
+    let (route, params) = router.recognize("/users/42").unwrap();
+    assert_eq!(route.allowed_methods(), [Method::GET, Method::DELETE]);
+    assert_eq!(params, ["42"]);
+    assert!(router.recognize("/users/alice").is_none());
+}
@@ -0,0 +1,16 @@
+use hyper::body::Incoming;
+use hyper::Request;
+use radmin_router::macros::routes;
+use radmin_router::Context;
+
+struct Files;
+
+#[routes]
+impl Files {
+    #[get("/files/[...path]/raw")]
+    async fn raw(&self, _: Request<Incoming>, _: Context<()>) -> radmin_router::Result {
+        unimplemented!()
+    }
+}
+
+fn main() {}
@@ -0,0 +1,26 @@
+use hyper::body::Incoming;
+use hyper::Request;
+use radmin_router::macros::routes;
+use radmin_router::{Context, full};
+use std::sync::Arc;
+
+// Deliberately not `Default`.
+struct AppState {
+    name: String,
+}
+
+struct Pages;
+
+#[routes(AppState)]
+impl Pages {
+    #[get("/")]
+    async fn index(_: Request<Incoming>, ctx: Context<AppState>) -> radmin_router::Result {
+        Ok(hyper::Response::new(full(ctx.ex.name.clone())))
+    }
+}
+
+fn main() {
+    let state = Arc::new(AppState { name: "app".to_string() });
+    let router = Pages.into_router(state);
+    assert!(router.recognize("/").is_some());
+}
@@ -0,0 +1,16 @@
+use hyper::body::Incoming;
+use hyper::Request;
+use radmin_router::macros::routes;
+use radmin_router::Context;
+
+struct Users;
+
+#[routes]
+impl Users {
+    #[fetch("/users")]
+    async fn list(&self, _: Request<Incoming>, _: Context<()>) -> radmin_router::Result {
+        unimplemented!()
+    }
+}
+
+fn main() {}